@@ -0,0 +1,277 @@
+//! load-test mode for `wash call`, enabled with --repeat and/or --duration
+//!
+use super::model::{InterfaceModel, ModelOperation};
+use super::{prepare_call, rpc_client_from_opts, CallCommand};
+use crate::util::{configure_table_style, convert_rpc_error, OutputKind, Result};
+use futures::future::join_all;
+use serde_json::json;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use term_table::{row::Row, table_cell::*, Table};
+use wasmbus_rpc::Message;
+
+/// Outcome of a single invocation
+struct Sample {
+    latency: Duration,
+    error: Option<String>,
+}
+
+/// Aggregated results of a load test
+struct BenchReport {
+    requests: usize,
+    /// latencies of successful invocations, sorted ascending
+    latencies: Vec<Duration>,
+    /// error message -> number of occurrences
+    errors: BTreeMap<String, usize>,
+    elapsed: Duration,
+}
+
+impl BenchReport {
+    fn new(samples: Vec<Sample>, elapsed: Duration) -> Self {
+        let requests = samples.len();
+        let mut latencies = Vec::with_capacity(requests);
+        let mut errors = BTreeMap::new();
+        for sample in samples {
+            match sample.error {
+                None => latencies.push(sample.latency),
+                Some(e) => *errors.entry(e).or_insert(0) += 1,
+            }
+        }
+        latencies.sort_unstable();
+        BenchReport {
+            requests,
+            latencies,
+            errors,
+            elapsed,
+        }
+    }
+
+    fn failed(&self) -> usize {
+        self.requests - self.latencies.len()
+    }
+
+    /// Completed invocations (successful or not) per second
+    fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.requests as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    fn mean(&self) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            None
+        } else {
+            Some(self.latencies.iter().sum::<Duration>() / self.latencies.len() as u32)
+        }
+    }
+
+    /// Nearest-rank percentile of successful invocation latencies
+    fn percentile(&self, pct: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let rank = (pct / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        Some(self.latencies[rank.clamp(1, self.latencies.len()) - 1])
+    }
+
+    /// Latency summary rows, in display order
+    fn latency_stats(&self) -> Vec<(&'static str, Option<Duration>)> {
+        vec![
+            ("min", self.latencies.first().copied()),
+            ("mean", self.mean()),
+            ("p50", self.percentile(50.0)),
+            ("p90", self.percentile(90.0)),
+            ("p99", self.percentile(99.0)),
+            ("max", self.latencies.last().copied()),
+        ]
+    }
+}
+
+pub(crate) async fn handle_bench(
+    cmd: CallCommand,
+    typed: Option<(&InterfaceModel, &ModelOperation)>,
+) -> Result<String> {
+    if cmd.concurrency == 0 {
+        return Err("'concurrency' must be at least 1".into());
+    }
+    if cmd.repeat == Some(0) || cmd.duration == Some(0) {
+        return Err("'repeat' and 'duration' must be greater than 0".into());
+    }
+
+    let inv = prepare_call(&cmd, typed)?;
+    let (client, timeout) = rpc_client_from_opts(cmd.opts, cmd.cluster_seed).await?;
+    let timeout = Duration::from_millis(timeout);
+    let limit = cmd.repeat.unwrap_or(u64::MAX);
+    let deadline = cmd
+        .duration
+        .map(|secs| Instant::now() + Duration::from_secs(secs));
+
+    // Each worker claims the next invocation number until the limit or deadline is reached,
+    // so at most `concurrency` invocations are in flight on the shared client at any time
+    let next = AtomicU64::new(0);
+    let (next, client, inv) = (&next, &client, &inv);
+    let started = Instant::now();
+    let workers = (0..cmd.concurrency).map(|_| async move {
+        let mut samples = Vec::new();
+        while next.fetch_add(1, Ordering::Relaxed) < limit {
+            if matches!(deadline, Some(d) if Instant::now() >= d) {
+                break;
+            }
+            let sent = Instant::now();
+            let res = client
+                .send_timeout(
+                    inv.origin.clone(),
+                    inv.target.clone(),
+                    Message {
+                        method: &inv.method,
+                        arg: Cow::Borrowed(&inv.payload),
+                    },
+                    timeout,
+                )
+                .await
+                .map_err(convert_rpc_error);
+            samples.push(Sample {
+                latency: sent.elapsed(),
+                error: res.err().map(|e| e.to_string()),
+            });
+        }
+        samples
+    });
+    let samples = join_all(workers).await.into_iter().flatten().collect();
+    let report = BenchReport::new(samples, started.elapsed());
+
+    Ok(bench_output(&report, &cmd.output.kind))
+}
+
+fn bench_output(report: &BenchReport, output_kind: &OutputKind) -> String {
+    match *output_kind {
+        OutputKind::Text => bench_table(report),
+        OutputKind::Json => {
+            let latency: serde_json::Map<String, serde_json::Value> = report
+                .latency_stats()
+                .into_iter()
+                .map(|(name, d)| (name.to_string(), json!(d.map(as_millis))))
+                .collect();
+            format!(
+                "{}",
+                json!({
+                    "requests": report.requests,
+                    "succeeded": report.latencies.len(),
+                    "failed": report.failed(),
+                    "elapsed_ms": as_millis(report.elapsed),
+                    "throughput": report.throughput(),
+                    "latency_ms": latency,
+                    "errors": report.errors,
+                })
+            )
+        }
+    }
+}
+
+/// Helper function to transform a BenchReport into a table string for printing
+fn bench_table(report: &BenchReport) -> String {
+    let mut table = Table::new();
+    configure_table_style(&mut table);
+
+    let add_row = |table: &mut Table, label: &str, value: String| {
+        table.add_row(Row::new(vec![
+            TableCell::new_with_alignment(label, 1, Alignment::Left),
+            TableCell::new_with_alignment(value, 1, Alignment::Right),
+        ]))
+    };
+
+    add_row(&mut table, "Requests", report.requests.to_string());
+    add_row(&mut table, "Succeeded", report.latencies.len().to_string());
+    add_row(&mut table, "Failed", report.failed().to_string());
+    add_row(
+        &mut table,
+        "Elapsed",
+        format!("{:.3} s", report.elapsed.as_secs_f64()),
+    );
+    add_row(
+        &mut table,
+        "Throughput",
+        format!("{:.1} req/s", report.throughput()),
+    );
+
+    table.add_row(Row::new(vec![TableCell::new_with_alignment(
+        "",
+        2,
+        Alignment::Left,
+    )]));
+    add_row(&mut table, "Latency", String::new());
+    for (name, d) in report.latency_stats() {
+        let value = d
+            .map(|d| format!("{:.3} ms", as_millis(d)))
+            .unwrap_or_else(|| "N/A".to_string());
+        add_row(&mut table, name, value);
+    }
+
+    if !report.errors.is_empty() {
+        table.add_row(Row::new(vec![TableCell::new_with_alignment(
+            "",
+            2,
+            Alignment::Left,
+        )]));
+        add_row(&mut table, "Errors", String::new());
+        for (message, count) in report.errors.iter() {
+            add_row(&mut table, message, count.to_string());
+        }
+    }
+
+    table.render()
+}
+
+fn as_millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BenchReport, Sample};
+    use std::time::Duration;
+
+    fn sample(ms: u64, error: Option<&str>) -> Sample {
+        Sample {
+            latency: Duration::from_millis(ms),
+            error: error.map(|e| e.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_bench_report() {
+        let mut samples: Vec<Sample> = (1..=100).rev().map(|ms| sample(ms, None)).collect();
+        samples.push(sample(5000, Some("timeout")));
+        samples.push(sample(5000, Some("timeout")));
+        samples.push(sample(1, Some("no responders")));
+        let report = BenchReport::new(samples, Duration::from_secs(2));
+
+        assert_eq!(report.requests, 103);
+        assert_eq!(report.failed(), 3);
+        assert_eq!(report.errors.get("timeout"), Some(&2));
+        assert_eq!(report.errors.get("no responders"), Some(&1));
+        assert!((report.throughput() - 51.5).abs() < f64::EPSILON);
+
+        let ms = |n| Some(Duration::from_millis(n));
+        assert_eq!(report.latencies.first().copied(), ms(1));
+        assert_eq!(report.percentile(50.0), ms(50));
+        assert_eq!(report.percentile(90.0), ms(90));
+        assert_eq!(report.percentile(99.0), ms(99));
+        assert_eq!(report.latencies.last().copied(), ms(100));
+        assert_eq!(report.mean(), Some(Duration::from_micros(50_500)));
+    }
+
+    #[test]
+    fn test_bench_report_no_successes() {
+        let report = BenchReport::new(vec![sample(10, Some("timeout"))], Duration::ZERO);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.percentile(50.0), None);
+        assert_eq!(report.mean(), None);
+        assert_eq!(report.throughput(), 0.0);
+    }
+}
@@ -0,0 +1,296 @@
+//! decoding msgpack responses to json, and rendering them with --query and --template
+//!
+use crate::util::{extract_arg_value, Result};
+use handlebars::{no_escape, Handlebars};
+use serde_json::{json, Value as JsonValue};
+use std::str::FromStr;
+
+/// Converts msgpack to json, displaying binary as binary('b'), string('s'), or both('2')
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct MsgpackDecoder {
+    bin: char,
+}
+
+impl MsgpackDecoder {
+    pub(crate) fn new(bin: char) -> Self {
+        MsgpackDecoder { bin }
+    }
+
+    /// Decodes msgpack bytes. An empty message (e.g. from an operation without output)
+    /// decodes to null
+    pub(crate) fn decode(&self, msg: &[u8]) -> JsonValue {
+        if msg.is_empty() {
+            return JsonValue::Null;
+        }
+        match rmpv::decode::read_value(&mut &msg[..]) {
+            Ok(v) => self.value(v),
+            Err(e) => json!({ "error": format!("Could not decode data: {}", e) }),
+        }
+    }
+
+    pub(crate) fn value(&self, mval: rmpv::Value) -> JsonValue {
+        use rmpv::Value as RV;
+        match mval {
+            RV::Nil => JsonValue::Null,
+            RV::Boolean(b) => JsonValue::Bool(b),
+            RV::Integer(i) => i
+                .as_u64()
+                .map(JsonValue::from)
+                .or_else(|| i.as_i64().map(JsonValue::from))
+                .unwrap_or(JsonValue::Null),
+            RV::F32(f) => JsonValue::from(f),
+            RV::F64(f) => JsonValue::from(f),
+            RV::String(s) => JsonValue::String(String::from_utf8_lossy(s.as_bytes()).into_owned()),
+            RV::Binary(v) => self.binary(v),
+            RV::Array(v) => JsonValue::Array(v.into_iter().map(|v| self.value(v)).collect()),
+            RV::Map(entries) => JsonValue::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (self.key(k), self.value(v)))
+                    .collect(),
+            ),
+            RV::Ext(kind, data) => json!({
+                "type": kind,
+                "data": self.binary(data),
+            }),
+        }
+    }
+
+    pub(crate) fn binary(&self, v: Vec<u8>) -> JsonValue {
+        match self.bin {
+            's' => JsonValue::String(String::from_utf8_lossy(&v).into_owned()),
+            '2' => json!({
+                "str": String::from_utf8_lossy(&v),
+                "bin": v,
+            }),
+            /*'b'|*/ _ => JsonValue::Array(v.into_iter().map(JsonValue::from).collect()),
+        }
+    }
+
+    /// json object keys must be strings, so other key types use their json text, e.g. `1` or `true`
+    pub(crate) fn key(&self, key: rmpv::Value) -> String {
+        match self.value(key) {
+            JsonValue::String(s) => s,
+            other => other.to_string(),
+        }
+    }
+}
+
+/// A jq-like path selecting part of a response, e.g. `.items[0].name` or `.["content-type"]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct JsonQuery(Vec<PathSegment>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
+impl FromStr for JsonQuery {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = |reason: &str| format!("invalid query '{}': {}", s, reason);
+        let rest = s
+            .trim()
+            .strip_prefix('.')
+            .ok_or_else(|| invalid("must start with '.'"))?;
+        let mut segments = Vec::new();
+        let mut chars = rest.chars().peekable();
+        // a field name may follow the leading '.' directly
+        let mut expect_field = true;
+        while let Some(c) = chars.peek().copied() {
+            match c {
+                '[' => {
+                    chars.next();
+                    let mut inner = String::new();
+                    for c in chars.by_ref() {
+                        if c == ']' {
+                            break;
+                        }
+                        inner.push(c);
+                    }
+                    let inner = inner.trim();
+                    let segment = if let Some(key) =
+                        inner.strip_prefix('"').and_then(|k| k.strip_suffix('"'))
+                    {
+                        PathSegment::Field(key.to_string())
+                    } else {
+                        PathSegment::Index(
+                            inner
+                                .parse()
+                                .map_err(|_| invalid("expected an index or quoted key in []"))?,
+                        )
+                    };
+                    segments.push(segment);
+                    expect_field = false;
+                }
+                '.' if !expect_field => {
+                    chars.next();
+                    expect_field = true;
+                }
+                _ if expect_field => {
+                    let mut field = String::new();
+                    while let Some(c) = chars.peek().copied() {
+                        if c == '.' || c == '[' {
+                            break;
+                        }
+                        field.push(c);
+                        chars.next();
+                    }
+                    if field.is_empty() {
+                        return Err(invalid("expected a field name after '.'"));
+                    }
+                    segments.push(PathSegment::Field(field));
+                    expect_field = false;
+                }
+                _ => return Err(invalid("expected '.' or '['")),
+            }
+        }
+        if expect_field && !segments.is_empty() {
+            return Err(invalid("ends with '.'"));
+        }
+        Ok(JsonQuery(segments))
+    }
+}
+
+impl JsonQuery {
+    /// Selects the value at the path, or null if the path doesn't exist
+    pub(crate) fn select<'a>(&self, value: &'a JsonValue) -> &'a JsonValue {
+        self.0
+            .iter()
+            .try_fold(value, |v, segment| match segment {
+                PathSegment::Field(name) => v.get(name),
+                PathSegment::Index(i) => v.get(i),
+            })
+            .unwrap_or(&JsonValue::Null)
+    }
+}
+
+mod helpers {
+    use handlebars::handlebars_helper;
+
+    // renders a value as json, e.g. {{json tags}}
+    handlebars_helper!(json: |v: Json| v.to_string());
+}
+
+/// Renders a decoded response with --query and/or --template
+pub(crate) struct ResponseFormat {
+    query: Option<JsonQuery>,
+    template: Option<Handlebars<'static>>,
+}
+
+const TEMPLATE_NAME: &str = "response";
+
+impl ResponseFormat {
+    /// Returns None if neither a query nor a template was provided.
+    /// The template may be a file path or the template itself
+    pub(crate) fn new(query: Option<&str>, template: Option<&str>) -> Result<Option<Self>> {
+        if query.is_none() && template.is_none() {
+            return Ok(None);
+        }
+        let query = query.map(JsonQuery::from_str).transpose()?;
+        let template = match template {
+            Some(template) => {
+                let mut hb = Handlebars::new();
+                hb.set_strict_mode(true);
+                hb.register_escape_fn(no_escape);
+                hb.register_helper("json", Box::new(helpers::json));
+                hb.register_template_string(TEMPLATE_NAME, extract_arg_value(template)?)
+                    .map_err(|e| format!("invalid template: {}", e))?;
+                Some(hb)
+            }
+            None => None,
+        };
+        Ok(Some(ResponseFormat { query, template }))
+    }
+
+    /// Applies the query, then renders the result with the template. Without a template,
+    /// strings are printed as-is and other values as json
+    pub(crate) fn render(&self, value: &JsonValue) -> Result<String> {
+        let value = match self.query {
+            Some(ref query) => query.select(value),
+            None => value,
+        };
+        match (&self.template, value) {
+            (Some(hb), value) => hb
+                .render(TEMPLATE_NAME, value)
+                .map_err(|e| format!("Error rendering template: {}", e).into()),
+            (None, JsonValue::String(s)) => Ok(s.clone()),
+            (None, value) => Ok(serde_json::to_string_pretty(value)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{JsonQuery, MsgpackDecoder, ResponseFormat};
+    use crate::util::Result;
+    use rmpv::Value as RV;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_decode_msgpack() {
+        let value = RV::Map(vec![
+            (RV::from("nothing"), RV::Nil),
+            (RV::from(1), RV::from("one")),
+            (RV::from(true), RV::from(-2)),
+            (RV::from("ext"), RV::Ext(7, b"hi".to_vec())),
+            (RV::from("bytes"), RV::Binary(b"hi".to_vec())),
+        ]);
+        let mut msg = Vec::new();
+        rmpv::encode::write_value(&mut msg, &value).unwrap();
+
+        // decoders are independent, so different binary formats can be used in one process
+        assert_eq!(
+            MsgpackDecoder::new('s').decode(&msg),
+            json!({
+                "nothing": null,
+                "1": "one",
+                "true": -2,
+                "ext": {"type": 7, "data": "hi"},
+                "bytes": "hi",
+            })
+        );
+        assert_eq!(
+            MsgpackDecoder::new('b').decode(&msg)["bytes"],
+            json!([104, 105])
+        );
+        assert_eq!(MsgpackDecoder::new('b').decode(&[]), json!(null));
+    }
+
+    #[test]
+    fn test_json_query() -> Result<()> {
+        let value = json!({"items": [{"name": "a"}, {"name": "b"}], "content-type": "text"});
+        let select = |q: &str| JsonQuery::from_str(q).map(|q| q.select(&value).clone());
+        assert_eq!(select(".")?, value);
+        assert_eq!(select(".items[1].name")?, json!("b"));
+        assert_eq!(select(".items.[0]")?, json!({"name": "a"}));
+        assert_eq!(select(r#".["content-type"]"#)?, json!("text"));
+        assert_eq!(select(".missing.field")?, json!(null));
+        assert!(select("items").is_err());
+        assert!(select(".items.").is_err());
+        assert!(select(".items[x]").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_format() -> Result<()> {
+        let value = json!({"user": {"name": "<ada>", "roles": ["admin"]}});
+        assert!(ResponseFormat::new(None, None)?.is_none());
+
+        let query = ResponseFormat::new(Some(".user.name"), None)?.unwrap();
+        assert_eq!(query.render(&value)?, "<ada>");
+
+        let template = ResponseFormat::new(None, Some("{{user.name}}: {{json user.roles}}"))?;
+        assert_eq!(template.unwrap().render(&value)?, r#"<ada>: ["admin"]"#);
+
+        let both = ResponseFormat::new(Some(".user"), Some("{{name}}"))?.unwrap();
+        assert_eq!(both.render(&value)?, "<ada>");
+
+        let strict = ResponseFormat::new(None, Some("{{nope}}"))?.unwrap();
+        assert!(strict.render(&value).is_err());
+        Ok(())
+    }
+}
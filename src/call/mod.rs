@@ -0,0 +1,1006 @@
+use crate::ctl::id::ServiceId;
+use crate::ctx::{context::WashContext, context_dir, get_default_context, load_context};
+use crate::util::Result;
+use crate::util::{
+    convert_rpc_error, extract_arg_value, format_output, json_str_to_msgpack_bytes,
+    nats_client_from_opts, CommandFailure, Output, OutputKind, RegistryOpts,
+    DEFAULT_LATTICE_PREFIX, DEFAULT_NATS_HOST, DEFAULT_NATS_PORT, DEFAULT_NATS_TIMEOUT,
+};
+use log::{debug, error};
+use nats::asynk::Connection;
+use serde_json::json;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use structopt::clap::AppSettings;
+use structopt::StructOpt;
+use wascap::prelude::KeyPair;
+use wasmbus_rpc::{core::WasmCloudEntity, rpc_topic, Message, RpcClient};
+
+mod bench;
+mod decode;
+mod model;
+mod repl;
+mod replay;
+mod resolve;
+mod testing;
+mod trace;
+use decode::{MsgpackDecoder, ResponseFormat};
+use model::{InterfaceModel, ModelOperation};
+use replay::ReplayCommand;
+use trace::CallTrace;
+
+/// fake key (not a real public key)  used to construct origin for invoking actors
+const WASH_ORIGIN_KEY: &str = "__WASH__";
+
+/// hostname used for actor invocations
+const WASH_HOST_ID: &str = "NwashHostCallerId000000000000000000000000000000000000000";
+
+#[derive(Debug, StructOpt, Clone)]
+#[structopt(
+    global_settings(&[AppSettings::ColoredHelp, AppSettings::VersionlessSubcommands]),
+    settings(&[AppSettings::SubcommandsNegateReqs, AppSettings::ArgsNegateSubcommands]),
+    name = "call")]
+pub(crate) struct CallCli {
+    #[structopt(flatten)]
+    command: CallCommand,
+}
+
+impl CallCli {
+    pub(crate) fn command(self) -> CallCommand {
+        self.command
+    }
+}
+
+pub(crate) async fn handle_command(mut cmd: CallCommand) -> Result<String> {
+    if let Some(CallSubcommand::Replay(replay)) = cmd.subcommand {
+        return replay::handle_replay(replay).await;
+    }
+    let suite = cmd.target.clone().unwrap_or_default();
+    let output_kind = cmd.output.kind;
+    let is_test = cmd.test;
+    let save_output = cmd.save.clone();
+    let junit = cmd.junit.clone();
+    let decoder = MsgpackDecoder::new(cmd.bin);
+    let format = ResponseFormat::new(cmd.query.as_deref(), cmd.template.as_deref())?;
+    let model = if cmd.model.is_empty() {
+        None
+    } else {
+        Some(InterfaceModel::load(&cmd.model)?)
+    };
+    if let (Some(model), true) = (&model, cmd.list_operations) {
+        return Ok(model::list_operations_output(model, &output_kind));
+    }
+    if let (false, Some(actor)) = (cmd.provider, &cmd.target) {
+        cmd.target = Some(resolve::resolve_actor(actor, &cmd.opts, &cmd.registry).await?);
+    }
+    if cmd.interactive {
+        return repl::handle_repl(cmd, model.as_ref()).await;
+    }
+    let operation = match (&model, &cmd.operation) {
+        (Some(model), Some(name)) => Some(model.find_operation(name)?.clone()),
+        _ => None,
+    };
+    let typed = model.as_ref().zip(operation.as_ref());
+    if cmd.repeat.is_some() || cmd.duration.is_some() {
+        return bench::handle_bench(cmd, typed).await;
+    }
+    let mut trace = if cmd.trace {
+        Some(CallTrace::default())
+    } else {
+        None
+    };
+    let res = handle_call(cmd, typed, trace.as_mut()).await;
+    if is_test && save_output.is_none() {
+        return match res {
+            Ok(msg) => testing::test_output(&msg, &suite, junit.as_deref(), &output_kind),
+            // the test suite didn't run, so this is a failure too
+            Err(e) => Err(CommandFailure(call_output(Err(e), None, &output_kind, |_| {
+                serde_json::Value::Null
+            }))
+            .into()),
+        };
+    }
+    let mut decode = |msg: Vec<u8>| {
+        let started = Instant::now();
+        let value = match typed {
+            Some((model, op)) => model.decode_output(op, &msg, &decoder),
+            None => decoder.decode(&msg),
+        };
+        if let Some(ref mut trace) = trace {
+            trace.decode = Some(started.elapsed());
+        }
+        value
+    };
+    let formatted = format.is_some();
+    let output = match (format, res) {
+        (Some(format), Ok(msg)) => format.render(&decode(msg))?,
+        (_, res) => call_output(res, save_output, &output_kind, decode),
+    };
+    Ok(match (trace, output_kind) {
+        (Some(trace), OutputKind::Json) if !formatted => {
+            let response =
+                serde_json::from_str(&output).unwrap_or(serde_json::Value::String(output));
+            json!({ "response": response, "trace": trace.to_json() }).to_string()
+        }
+        (Some(trace), _) => {
+            eprintln!("{}", trace.to_table());
+            output
+        }
+        (None, _) => output,
+    })
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub(crate) struct ConnectionOpts {
+    /// RPC Host for connection, defaults to 127.0.0.1 for local nats
+    #[structopt(short = "r", long = "rpc-host", env = "WASMCLOUD_RPC_HOST")]
+    rpc_host: Option<String>,
+
+    /// RPC Port for connections, defaults to 4222 for local nats
+    #[structopt(short = "p", long = "rpc-port", env = "WASMCLOUD_RPC_PORT")]
+    rpc_port: Option<String>,
+
+    /// JWT file for RPC authentication. Must be supplied with rpc_seed.
+    #[structopt(long = "rpc-jwt", env = "WASMCLOUD_RPC_JWT", hide_env_values = true)]
+    rpc_jwt: Option<String>,
+
+    /// Seed file or literal for RPC authentication. Must be supplied with rpc_jwt.
+    #[structopt(long = "rpc-seed", env = "WASMCLOUD_RPC_SEED", hide_env_values = true)]
+    rpc_seed: Option<String>,
+
+    /// Credsfile for RPC authentication. Combines rpc_seed and rpc_jwt.
+    /// See https://docs.nats.io/developing-with-nats/security/creds for details.
+    #[structopt(long = "rpc-credsfile", env = "WASH_RPC_CREDS", hide_env_values = true)]
+    rpc_credsfile: Option<PathBuf>,
+
+    /// Lattice prefix for wasmcloud command interface, defaults to "default"
+    #[structopt(short = "x", long = "lattice-prefix", env = "WASMCLOUD_LATTICE_PREFIX")]
+    lattice_prefix: Option<String>,
+
+    /// Timeout length for RPC, defaults to 2000 milliseconds
+    #[structopt(short = "t", long = "rpc-timeout-ms", env = "WASMCLOUD_RPC_TIMEOUT_MS")]
+    timeout_ms: Option<u64>,
+
+    /// Path to a context with values to use for RPC connection, authentication, and cluster seed invocation signing
+    #[structopt(long = "context")]
+    pub(crate) context: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) struct CallCommand {
+    #[structopt(flatten)]
+    opts: ConnectionOpts,
+
+    #[structopt(flatten)]
+    pub(crate) output: Output,
+
+    /// Optional file to send as the operation payload. Unless --payload-format is json,
+    /// the file contents are read as bytes
+    #[structopt(short, long)]
+    pub(crate) data: Option<PathBuf>,
+
+    /// Format of the payload: json (converted to msgpack), msgpack (sent as-is after validation),
+    /// raw (sent as-is), hex, or base64 (decoded before sending)
+    #[structopt(long = "payload-format", default_value = "json")]
+    pub(crate) payload_format: PayloadFormat,
+
+    /// Optional file for saving binary response
+    #[structopt(long)]
+    pub(crate) save: Option<PathBuf>,
+
+    /// When using json output, display binary as binary('b'), string('s'), or both('2')
+    #[structopt(long, default_value = "b")]
+    pub(crate) bin: char,
+
+    /// Print only the part of the decoded response selected by this jq-like path,
+    /// e.g. '.items[0].name'. Strings are printed without quotes
+    #[structopt(long, conflicts_with_all = &["save", "test"])]
+    pub(crate) query: Option<String>,
+
+    /// Render the decoded response (or the --query result) with this handlebars template,
+    /// given as a file path or the template itself, e.g. '{{name}}: {{json tags}}'
+    #[structopt(long, conflicts_with_all = &["save", "test"])]
+    pub(crate) template: Option<String>,
+
+    /// Report connection time, request and response sizes, round-trip latency, the NATS subject,
+    /// origin, target and operation. Included in json output, otherwise printed to stderr
+    #[structopt(long, conflicts_with_all = &["repeat", "duration", "interactive", "test"])]
+    pub(crate) trace: bool,
+
+    /// When invoking a test actor, interpret the response as TestResults.
+    /// Exits with a non-zero status if any test failed
+    #[structopt(long, conflicts_with_all = &["save", "repeat", "duration", "interactive"])]
+    pub(crate) test: bool,
+
+    /// Write test results to this file as JUnit XML. Used with --test
+    #[structopt(long, requires = "test")]
+    pub(crate) junit: Option<PathBuf>,
+
+    /// wasmCloud host cluster seed. This cluster seed must match the cluster seed used to
+    /// launch the wasmCloud host in order to pass antiforgery checks made by the host
+    /// This is only optional if a default context is available or a context is provided
+    #[structopt(short = "c", long = "cluster-seed", env = "WASMCLOUD_CLUSTER_SEED")]
+    pub(crate) cluster_seed: Option<String>,
+
+    /// Send the invocation this many times and report latency and throughput
+    /// statistics instead of the actor response
+    #[structopt(long, conflicts_with_all = &["save", "interactive", "record"])]
+    pub(crate) repeat: Option<u64>,
+
+    /// Number of invocations to keep in flight at once when using --repeat or --duration
+    #[structopt(long, default_value = "1")]
+    pub(crate) concurrency: usize,
+
+    /// Keep sending invocations for this many seconds and report latency and throughput
+    /// statistics. When combined with --repeat, stops at whichever limit is reached first
+    #[structopt(long, conflicts_with_all = &["save", "interactive", "record"])]
+    pub(crate) duration: Option<u64>,
+
+    /// Smithy model files, or a codegen.toml listing them, describing the actor's interface.
+    /// When provided, the json payload is checked against the operation's input shape and
+    /// the response is decoded using the operation's output shape
+    #[structopt(long, number_of_values = 1)]
+    pub(crate) model: Vec<String>,
+
+    /// List the operations declared in the model instead of invoking an actor
+    #[structopt(long = "list-operations", requires = "model")]
+    pub(crate) list_operations: bool,
+
+    /// Invoke a capability provider instead of an actor. The target argument is then the
+    /// provider's public key
+    #[structopt(long = "provider", requires = "contract")]
+    pub(crate) provider: bool,
+
+    /// Capability contract ID of the provider to invoke
+    #[structopt(long = "contract", name = "contract", requires = "provider")]
+    pub(crate) contract: Option<String>,
+
+    /// Link name of the provider to invoke
+    #[structopt(short = "l", long = "link-name", default_value = "default")]
+    pub(crate) link_name: String,
+
+    /// Start an interactive session that keeps the RPC connection open for repeated calls.
+    /// The target and operation arguments are optional and select the initial target
+    #[structopt(long, conflicts_with_all = &["save", "record"])]
+    pub(crate) interactive: bool,
+
+    /// Append the request and response to this file, for use with `wash call replay`.
+    /// Only successful calls are recorded
+    #[structopt(long)]
+    pub(crate) record: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pub(crate) registry: RegistryOpts,
+
+    #[structopt(subcommand)]
+    pub(crate) subcommand: Option<CallSubcommand>,
+
+    /// Public key, OCI reference, signed module file, or name (or call alias) of the actor.
+    /// Names are looked up in the lattice claims cache. With --provider, the public key of
+    /// the provider
+    #[structopt(
+        name = "target",
+        required_unless_one = &["list-operations", "interactive"]
+    )]
+    pub(crate) target: Option<String>,
+
+    /// Operation to invoke on actor or provider. When using --model, the service prefix may be omitted
+    #[structopt(
+        name = "operation",
+        required_unless_one = &["list-operations", "interactive"]
+    )]
+    pub(crate) operation: Option<String>,
+
+    /// Payload to send with operation (in the form of '{"field": "value"}' )
+    #[structopt(name = "payload")]
+    pub(crate) payload: Vec<String>,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) enum CallSubcommand {
+    /// Re-issue calls recorded with --record and compare the responses to the recording
+    #[structopt(name = "replay")]
+    Replay(ReplayCommand),
+}
+
+pub(crate) async fn handle_call(
+    cmd: CallCommand,
+    typed: Option<(&InterfaceModel, &ModelOperation)>,
+    mut trace: Option<&mut CallTrace>,
+) -> Result<Vec<u8>> {
+    let inv = prepare_call(&cmd, typed)?;
+    let started = Instant::now();
+    let conn = lattice_connection(cmd.opts).await;
+    let subject = conn
+        .as_ref()
+        .ok()
+        .map(|conn| rpc_topic(&inv.target, &conn.lattice_prefix));
+    let cluster_seed = cmd.cluster_seed.clone();
+    let client = conn.and_then(|conn| rpc_client(conn, cluster_seed));
+    if let Some(ref mut trace) = trace {
+        trace.connect = Some(started.elapsed());
+        if let Some(subject) = subject {
+            trace.invocation(&inv, subject);
+        }
+    }
+    let (client, timeout) = client?;
+
+    let sent = Instant::now();
+    let response = client
+        .send_timeout(
+            inv.origin.clone(),
+            inv.target.clone(),
+            Message {
+                method: &inv.method,
+                arg: inv.payload.as_slice().into(),
+            },
+            Duration::from_millis(timeout),
+        )
+        .await
+        .map_err(convert_rpc_error);
+    if let Some(trace) = trace {
+        trace.round_trip = Some(sent.elapsed());
+        trace.response_bytes = response.as_ref().ok().map(Vec::len);
+    }
+    let response = response?;
+    if let Some(ref path) = cmd.record {
+        replay::record_call(path, &inv, &response)
+            .map_err(|e| format!("Error recording call to {}: {}", path.display(), e))?;
+    }
+    Ok(response)
+}
+
+/// An invocation built from the call arguments, ready to send
+struct Invocation {
+    origin: WasmCloudEntity,
+    target: WasmCloudEntity,
+    method: String,
+    payload: Vec<u8>,
+}
+
+/// Validates the call arguments and encodes the payload. When a model is provided,
+/// a json payload is checked against the operation's input shape
+fn prepare_call(
+    cmd: &CallCommand,
+    typed: Option<(&InterfaceModel, &ModelOperation)>,
+) -> Result<Invocation> {
+    let method = match typed {
+        Some((_, op)) => op.name.clone(),
+        None => cmd
+            .operation
+            .clone()
+            .ok_or("an operation to invoke is required")?,
+    };
+    debug!(
+        "calling actor with operation: {}, data: {}",
+        &method,
+        cmd.payload.join("")
+    );
+    if !"bs2".contains(cmd.bin) {
+        return Err(Box::<dyn std::error::Error>::from(
+            "'bin' parameter must be 'b', 's', or '2'",
+        ));
+    }
+
+    let origin = WasmCloudEntity::new_actor(WASH_ORIGIN_KEY)?;
+    let target = target_entity(cmd)?;
+
+    if cmd.data.is_some() && !cmd.payload.is_empty() {
+        return Err(Box::<dyn std::error::Error>::from(
+            "you can use either -d/--data or the payload args, but not both.".to_string(),
+        ));
+    }
+    let payload = if cmd.payload_format == PayloadFormat::Json {
+        let payload = if let Some(ref fname) = cmd.data {
+            std::fs::read_to_string(fname)?
+        } else {
+            cmd.payload.join("")
+        };
+        encode_json_payload(&payload, typed)?
+    } else {
+        let payload = if let Some(ref fname) = cmd.data {
+            std::fs::read(fname)?
+        } else {
+            cmd.payload.join("").into_bytes()
+        };
+        decode_payload(payload, cmd.payload_format)?
+    };
+    Ok(Invocation {
+        origin,
+        target,
+        method,
+        payload,
+    })
+}
+
+/// Builds the invocation target, a provider when --provider is set and otherwise an actor
+fn target_entity(cmd: &CallCommand) -> Result<WasmCloudEntity> {
+    let target = cmd.target.as_deref().unwrap_or_default();
+    Ok(if cmd.provider {
+        let provider_id = ServiceId::from_str(target)
+            .map_err(|e| format!("invalid provider id {}: {}", target, e))?;
+        WasmCloudEntity {
+            public_key: provider_id.to_string(),
+            contract_id: cmd.contract.clone().unwrap_or_default(),
+            link_name: cmd.link_name.clone(),
+        }
+    } else {
+        WasmCloudEntity::new_actor(target)?
+    })
+}
+
+/// Converts a json payload to msgpack, checking it against the operation's input shape
+/// when a model is provided
+fn encode_json_payload(
+    payload: &str,
+    typed: Option<(&InterfaceModel, &ModelOperation)>,
+) -> Result<Vec<u8>> {
+    match typed {
+        // operations without input are sent an empty payload
+        Some((_, op)) if op.input.is_none() && payload.trim().is_empty() => Ok(Vec::new()),
+        Some((model, op)) => {
+            let json = serde_json::from_str::<serde_json::Value>(payload)?;
+            model.validate_input(op, &json)?;
+            Ok(wasmbus_rpc::serialize(&json)?)
+        }
+        None => json_str_to_msgpack_bytes(payload),
+    }
+}
+
+/// Encoding of the payload supplied with --data or the payload args
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum PayloadFormat {
+    Json,
+    Msgpack,
+    Raw,
+    Hex,
+    Base64,
+}
+
+impl FromStr for PayloadFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(PayloadFormat::Json),
+            "msgpack" => Ok(PayloadFormat::Msgpack),
+            "raw" => Ok(PayloadFormat::Raw),
+            "hex" => Ok(PayloadFormat::Hex),
+            "base64" => Ok(PayloadFormat::Base64),
+            _ => Err(format!(
+                "invalid payload format '{}', must be one of json, msgpack, raw, hex, or base64",
+                s
+            )),
+        }
+    }
+}
+
+/// Converts a non-json payload into the exact bytes sent to the operation
+fn decode_payload(payload: Vec<u8>, format: PayloadFormat) -> Result<Vec<u8>> {
+    match format {
+        PayloadFormat::Json => json_str_to_msgpack_bytes(std::str::from_utf8(&payload)?),
+        PayloadFormat::Raw => Ok(payload),
+        PayloadFormat::Msgpack => {
+            let mut buf = payload.as_slice();
+            rmpv::decode::read_value(&mut buf)
+                .map_err(|e| format!("payload is not valid msgpack: {}", e))?;
+            if !buf.is_empty() {
+                return Err(format!(
+                    "payload is not valid msgpack: {} trailing bytes after value",
+                    buf.len()
+                )
+                .into());
+            }
+            Ok(payload)
+        }
+        PayloadFormat::Hex => data_encoding::HEXLOWER_PERMISSIVE
+            .decode(std::str::from_utf8(&payload)?.trim().as_bytes())
+            .map_err(|e| format!("payload is not valid hex: {}", e).into()),
+        PayloadFormat::Base64 => data_encoding::BASE64
+            .decode(std::str::from_utf8(&payload)?.trim().as_bytes())
+            .map_err(|e| format!("payload is not valid base64: {}", e).into()),
+    }
+}
+
+// Helper output functions, used to ensure consistent output between call & standalone commands
+pub(crate) fn call_output(
+    response: Result<Vec<u8>>,
+    save_output: Option<PathBuf>,
+    output_kind: &OutputKind,
+    decode: impl FnOnce(Vec<u8>) -> serde_json::Value,
+) -> String {
+    match response {
+        Ok(msg) => {
+            if let Some(ref save_path) = save_output {
+                return match std::fs::write(save_path, msg) {
+                    Ok(_) => String::new(),
+                    Err(e) => format!(
+                        "Error saving results to {}: {}",
+                        &save_path.display(),
+                        e.to_string(),
+                    ),
+                };
+            }
+            format_output(
+                format!("\nCall response (raw): {}", String::from_utf8_lossy(&msg)),
+                decode(msg),
+                output_kind,
+            )
+        }
+        Err(e) => format_output(
+            format!("\nError invoking operation: {}", e),
+            json!({ "error": format!("{}", e) }),
+            output_kind,
+        ),
+    }
+}
+
+/// A NATS connection to the lattice, along with the settings resolved from
+/// the provided flags, the context, and defaults
+struct LatticeConnection {
+    nc: Connection,
+    lattice_prefix: String,
+    timeout: u64,
+    ctx: Option<WashContext>,
+}
+
+async fn lattice_connection(opts: ConnectionOpts) -> Result<LatticeConnection> {
+    let ctx = if let Some(context) = opts.context {
+        load_context(&context).ok()
+    } else if let Ok(ctx_dir) = context_dir(None) {
+        get_default_context(&ctx_dir).ok()
+    } else {
+        None
+    };
+
+    // Determine connection parameters, taking explicitly provided flags,
+    // then provided context values, lastly using defaults
+    let timeout = opts.timeout_ms.unwrap_or_else(|| {
+        ctx.as_ref()
+            .map(|c| c.rpc_timeout)
+            .unwrap_or(DEFAULT_NATS_TIMEOUT)
+    });
+
+    let lattice_prefix = opts.lattice_prefix.unwrap_or_else(|| {
+        ctx.as_ref()
+            .map(|c| c.rpc_lattice_prefix.clone())
+            .unwrap_or_else(|| DEFAULT_LATTICE_PREFIX.to_string())
+    });
+
+    let rpc_host = opts.rpc_host.unwrap_or_else(|| {
+        ctx.as_ref()
+            .map(|c| c.rpc_host.clone())
+            .unwrap_or_else(|| DEFAULT_NATS_HOST.to_string())
+    });
+
+    let rpc_port = opts.rpc_port.unwrap_or_else(|| {
+        ctx.as_ref()
+            .map(|c| c.rpc_port.to_string())
+            .unwrap_or_else(|| DEFAULT_NATS_PORT.to_string())
+    });
+
+    let rpc_jwt = if opts.rpc_jwt.is_some() {
+        opts.rpc_jwt
+    } else {
+        ctx.as_ref().map(|c| c.rpc_jwt.clone()).unwrap_or_default()
+    };
+
+    let rpc_seed = if opts.rpc_seed.is_some() {
+        opts.rpc_seed
+    } else {
+        ctx.as_ref().map(|c| c.rpc_seed.clone()).unwrap_or_default()
+    };
+
+    let rpc_credsfile = if opts.rpc_credsfile.is_some() {
+        opts.rpc_credsfile
+    } else {
+        ctx.as_ref()
+            .map(|c| c.rpc_credsfile.clone())
+            .unwrap_or_default()
+    };
+
+    let nc = nats_client_from_opts(&rpc_host, &rpc_port, rpc_jwt, rpc_seed, rpc_credsfile).await?;
+    Ok(LatticeConnection {
+        nc,
+        lattice_prefix,
+        timeout,
+        ctx,
+    })
+}
+
+async fn rpc_client_from_opts(
+    opts: ConnectionOpts,
+    cmd_cluster_seed: Option<String>,
+) -> Result<(RpcClient, u64)> {
+    rpc_client(lattice_connection(opts).await?, cmd_cluster_seed)
+}
+
+fn rpc_client(
+    conn: LatticeConnection,
+    cmd_cluster_seed: Option<String>,
+) -> Result<(RpcClient, u64)> {
+    let LatticeConnection {
+        nc,
+        lattice_prefix,
+        timeout,
+        ctx,
+    } = conn;
+
+    Ok((
+        RpcClient::new_asynk(
+            nc,
+            &lattice_prefix,
+            cluster_key(cmd_cluster_seed, ctx.as_ref())?,
+            WASH_HOST_ID.to_string(),
+            Some(Duration::from_millis(timeout)),
+        ),
+        timeout,
+    ))
+}
+
+/// Loads the key used to sign invocations, from the provided cluster seed or the context
+fn cluster_key(cmd_cluster_seed: Option<String>, ctx: Option<&WashContext>) -> Result<KeyPair> {
+    // Cluster seed is optional on the CLI to allow for context to supply that variable.
+    // If no context is supplied, and there is no default context, then the cluster seed
+    // cannot be determined and the RPC will almost certainly fail, unless the antiforgery
+    // check allows the invocation to be unsigned.
+    let cluster_seed = cmd_cluster_seed.unwrap_or_else(|| {
+        ctx.map(|c| {
+            c.cluster_seed.clone().unwrap_or_else(|| {
+                error!("No cluster seed provided and no context available, this RPC will fail.");
+                "".to_string()
+            })
+        })
+        .unwrap_or_default()
+    });
+
+    Ok(KeyPair::from_seed(&extract_arg_value(&cluster_seed)?)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::replay::CompareMode;
+    use super::{
+        decode_payload, target_entity, CallCli, CallCommand, CallSubcommand, PayloadFormat,
+    };
+    use crate::util::Result;
+    use std::path::PathBuf;
+    use structopt::StructOpt;
+
+    const RPC_HOST: &str = "127.0.0.1";
+    const RPC_PORT: &str = "4222";
+    const LATTICE_PREFIX: &str = "default";
+    const SAVE_FNAME: &str = "/dev/null";
+    const DATA_FNAME: &str = "/tmp/data.json";
+
+    const ACTOR_ID: &str = "MDPDJEYIAK6MACO67PRFGOSSLODBISK4SCEYDY3HEOY4P5CVJN6UCWUK";
+    const PROVIDER_ID: &str = "VAG3QITQQ2ODAOWB5TTQSDJ53XK3SHBEIFNK4AYJ5RKAX2UNSCAPHA5M";
+
+    #[test]
+    fn test_rpc_comprehensive() -> Result<()> {
+        let call_all = CallCli::from_iter_safe(&[
+            "call",
+            "-o",
+            "json",
+            "--data",
+            DATA_FNAME,
+            "--save",
+            SAVE_FNAME,
+            "--bin",
+            "2",
+            "--payload-format",
+            "hex",
+            "--context",
+            "~/.wash/contexts/default.json",
+            "--cluster-seed",
+            "SCASDASDASD",
+            "--lattice-prefix",
+            LATTICE_PREFIX,
+            "--rpc-host",
+            RPC_HOST,
+            "--rpc-port",
+            RPC_PORT,
+            "--rpc-timeout-ms",
+            "0",
+            "--model",
+            "./interface.smithy",
+            "--record",
+            "./calls.json",
+            "--allow-latest",
+            "--user",
+            "name",
+            "--password",
+            "opensesame",
+            "--insecure",
+            ACTOR_ID,
+            "HandleOperation",
+            "{ \"hello\": \"world\"}",
+        ])?;
+        match call_all.command {
+            CallCommand {
+                opts,
+                output,
+                data,
+                payload_format,
+                save,
+                bin,
+                test,
+                target,
+                operation,
+                payload,
+                cluster_seed,
+                repeat,
+                concurrency,
+                duration,
+                model,
+                list_operations,
+                provider,
+                contract,
+                link_name,
+                interactive,
+                record,
+                subcommand,
+                junit,
+                registry,
+                query,
+                template,
+                trace,
+            } => {
+                assert_eq!(&opts.rpc_host.unwrap(), RPC_HOST);
+                assert_eq!(&opts.rpc_port.unwrap(), RPC_PORT);
+                assert_eq!(&opts.lattice_prefix.unwrap(), LATTICE_PREFIX);
+                assert_eq!(opts.timeout_ms.unwrap(), 0);
+                assert_eq!(
+                    opts.context,
+                    Some(PathBuf::from("~/.wash/contexts/default.json"))
+                );
+                assert_eq!(output.kind, crate::util::OutputKind::Json);
+                assert_eq!(data, Some(PathBuf::from(DATA_FNAME)));
+                assert_eq!(payload_format, PayloadFormat::Hex);
+                assert_eq!(save, Some(PathBuf::from(SAVE_FNAME)));
+                assert_eq!(cluster_seed.unwrap(), "SCASDASDASD");
+                assert!(!test);
+                assert_eq!(bin, '2');
+                assert!(repeat.is_none());
+                assert_eq!(concurrency, 1);
+                assert!(duration.is_none());
+                assert_eq!(model, vec!["./interface.smithy".to_string()]);
+                assert!(!list_operations);
+                assert!(!provider);
+                assert!(contract.is_none());
+                assert_eq!(link_name, "default");
+                assert!(!interactive);
+                assert_eq!(record, Some(PathBuf::from("./calls.json")));
+                assert!(subcommand.is_none());
+                assert!(junit.is_none());
+                assert!(registry.allow_latest);
+                assert_eq!(registry.user.unwrap(), "name");
+                assert_eq!(registry.password.unwrap(), "opensesame");
+                assert!(registry.insecure);
+                assert!(query.is_none());
+                assert!(template.is_none());
+                assert!(!trace);
+                assert_eq!(target.unwrap(), ACTOR_ID);
+                assert_eq!(operation.unwrap(), "HandleOperation");
+                assert_eq!(payload, vec!["{ \"hello\": \"world\"}".to_string()])
+            }
+            #[allow(unreachable_patterns)]
+            cmd => panic!("call constructed incorrect command: {:?}", cmd),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_template() -> Result<()> {
+        let call = CallCli::from_iter_safe(&[
+            "call",
+            "--query",
+            ".items[0]",
+            "--template",
+            "{{name}}",
+            ACTOR_ID,
+            "HandleOperation",
+        ])?
+        .command();
+        assert_eq!(call.query.unwrap(), ".items[0]");
+        assert_eq!(call.template.unwrap(), "{{name}}");
+
+        let save = CallCli::from_iter_safe(&[
+            "call",
+            "--query",
+            ".",
+            "--save",
+            SAVE_FNAME,
+            ACTOR_ID,
+            "HandleOperation",
+        ]);
+        assert!(save.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_trace() -> Result<()> {
+        let call = CallCli::from_iter_safe(&["call", "--trace", ACTOR_ID, "HandleOperation"])?;
+        assert!(call.command().trace);
+
+        let bench = CallCli::from_iter_safe(&[
+            "call",
+            "--trace",
+            "--repeat",
+            "10",
+            ACTOR_ID,
+            "HandleOperation",
+        ]);
+        assert!(bench.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bench_and_test_flags() -> Result<()> {
+        let bench = CallCli::from_iter_safe(&[
+            "call",
+            "--repeat",
+            "100",
+            "--concurrency",
+            "8",
+            "--duration",
+            "30",
+            ACTOR_ID,
+            "HandleOperation",
+        ])?
+        .command();
+        assert_eq!(bench.repeat, Some(100));
+        assert_eq!(bench.concurrency, 8);
+        assert_eq!(bench.duration, Some(30));
+
+        let test = CallCli::from_iter_safe(&[
+            "call",
+            "--test",
+            "--junit",
+            "./results.xml",
+            ACTOR_ID,
+            "HandleOperation",
+        ])?
+        .command();
+        assert!(test.test);
+        assert_eq!(test.junit, Some(PathBuf::from("./results.xml")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mode_conflicts() {
+        let conflicting: &[&[&str]] = &[
+            &["--repeat", "10", "--record", "./calls.json"],
+            &["--duration", "10", "--record", "./calls.json"],
+            &["--repeat", "10", "--test"],
+            &["--duration", "10", "--save", SAVE_FNAME],
+            &["--duration", "10", "--interactive"],
+            &["--interactive", "--trace"],
+            &["--interactive", "--record", "./calls.json"],
+            &["--interactive", "--test"],
+            &["--save", SAVE_FNAME, "--test"],
+            &["--trace", "--test"],
+        ];
+        for flags in conflicting {
+            let args = std::iter::once("call")
+                .chain(flags.iter().copied())
+                .chain([ACTOR_ID, "HandleOperation"]);
+            assert!(
+                CallCli::from_iter_safe(args).is_err(),
+                "{:?} should be rejected",
+                flags
+            );
+        }
+    }
+
+    #[test]
+    fn test_call_provider() -> Result<()> {
+        let call = CallCli::from_iter_safe(&[
+            "call",
+            "--provider",
+            "--contract",
+            "wasmcloud:keyvalue",
+            "--link-name",
+            "redis",
+            PROVIDER_ID,
+            "KeyValue.Get",
+            "{\"key\": \"count\"}",
+        ])?
+        .command();
+        assert!(call.provider);
+        assert_eq!(call.target.as_deref(), Some(PROVIDER_ID));
+        assert_eq!(call.operation.as_deref(), Some("KeyValue.Get"));
+        assert_eq!(call.payload, vec!["{\"key\": \"count\"}".to_string()]);
+        let target = target_entity(&call)?;
+        assert_eq!(target.public_key, PROVIDER_ID);
+        assert_eq!(target.contract_id, "wasmcloud:keyvalue");
+        assert_eq!(target.link_name, "redis");
+
+        // the provider needs a contract, and the operation is still required
+        assert!(CallCli::from_iter_safe(&["call", "--provider", PROVIDER_ID, "Op"]).is_err());
+        assert!(
+            CallCli::from_iter_safe(&["call", "--provider", "--contract", "c", PROVIDER_ID])
+                .is_err()
+        );
+        let call =
+            CallCli::from_iter_safe(&["call", "--provider", "--contract", "c", ACTOR_ID, "Op"])?
+                .command();
+        assert!(target_entity(&call).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay() -> Result<()> {
+        let replay = CallCli::from_iter_safe(&[
+            "call",
+            "replay",
+            "--compare",
+            "json",
+            "-o",
+            "json",
+            "./calls.json",
+        ])?;
+        match replay.command.subcommand {
+            Some(CallSubcommand::Replay(cmd)) => {
+                assert_eq!(cmd.compare, CompareMode::Json);
+                assert_eq!(cmd.output.kind, crate::util::OutputKind::Json);
+                assert_eq!(cmd.file, PathBuf::from("./calls.json"));
+            }
+            cmd => panic!("call constructed incorrect command: {:?}", cmd),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_interactive() -> Result<()> {
+        let repl = CallCli::from_iter_safe(&["call", "--interactive"])?;
+        assert!(repl.command.interactive);
+        assert_eq!(repl.command.target, None);
+        let repl = CallCli::from_iter_safe(&["call", "--interactive", ACTOR_ID])?;
+        assert_eq!(repl.command.target.unwrap(), ACTOR_ID);
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_operations() -> Result<()> {
+        let list =
+            CallCli::from_iter_safe(&["call", "--model", "codegen.toml", "--list-operations"])?;
+        assert!(list.command.list_operations);
+        assert_eq!(list.command.target, None);
+        assert!(CallCli::from_iter_safe(&["call", "--list-operations"]).is_err());
+        assert!(CallCli::from_iter_safe(&["call", "--model", "codegen.toml"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_payload() -> Result<()> {
+        // msgpack encoding of {"a": 1}
+        let msgpack = vec![0x81, 0xa1, 0x61, 0x01];
+        assert_eq!(
+            decode_payload(b"81a16101\n".to_vec(), PayloadFormat::Hex)?,
+            msgpack
+        );
+        assert_eq!(
+            decode_payload(b"gaFhAQ==".to_vec(), PayloadFormat::Base64)?,
+            msgpack
+        );
+        assert_eq!(
+            decode_payload(msgpack.clone(), PayloadFormat::Msgpack)?,
+            msgpack
+        );
+        assert_eq!(
+            decode_payload(b"{\"a\": 1}".to_vec(), PayloadFormat::Json)?,
+            msgpack
+        );
+        assert_eq!(
+            decode_payload(vec![0xff, 0x00], PayloadFormat::Raw)?,
+            vec![0xff, 0x00]
+        );
+        assert!(decode_payload(vec![0x81, 0xa1], PayloadFormat::Msgpack).is_err());
+        assert!(decode_payload(vec![0x01, 0x02], PayloadFormat::Msgpack).is_err());
+        assert!(decode_payload(b"xyz".to_vec(), PayloadFormat::Hex).is_err());
+        assert!("cbor".parse::<PayloadFormat>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,487 @@
+//! smithy interface models for `wash call`, used to list operations,
+//! validate payloads against input shapes, and decode responses using output shapes
+//!
+use super::decode::MsgpackDecoder;
+use crate::smithy::{build_model, select_config};
+use crate::util::{configure_table_style, OutputKind, Result};
+use atelier_core::model::shapes::{HasTraits, MemberShape, ShapeKind, Simple};
+use atelier_core::model::values::Value as NodeValue;
+use atelier_core::model::{HasIdentity, Model, ShapeID};
+use heck::CamelCase;
+use serde_json::{json, Value as JsonValue};
+use std::path::PathBuf;
+use std::str::FromStr;
+use term_table::{row::Row, table_cell::*, Table};
+
+/// An operation of a service declared in the model
+#[derive(Debug, Clone)]
+pub(crate) struct ModelOperation {
+    /// Name used for dispatch, in the form `Service.Operation`
+    pub(crate) name: String,
+    pub(crate) input: Option<ShapeID>,
+    pub(crate) output: Option<ShapeID>,
+}
+
+/// Shape kinds relevant to validating and decoding payloads
+enum Resolved<'m> {
+    Simple(Simple),
+    Aggregate(&'m ShapeKind),
+    Unknown,
+}
+
+pub(crate) struct InterfaceModel {
+    model: Model,
+    operations: Vec<ModelOperation>,
+}
+
+impl InterfaceModel {
+    /// Loads a model from smithy files, or from the models listed in a codegen.toml
+    pub(crate) fn load(sources: &[String]) -> Result<Self> {
+        let model = match sources {
+            [config] if config.ends_with(".toml") => {
+                let config = select_config(&Some(PathBuf::from(config)))?;
+                build_model(Vec::new(), config.models, config.base_dir, 0)?
+            }
+            _ => build_model(sources.to_vec(), Vec::new(), PathBuf::from("."), 0)?,
+        };
+        Ok(InterfaceModel::new(model))
+    }
+
+    fn new(model: Model) -> Self {
+        let mut operations = Vec::new();
+        for shape in model.shapes() {
+            if let ShapeKind::Service(service) = shape.body() {
+                for op_id in service.operations() {
+                    if let Some(ShapeKind::Operation(op)) = model.shape(op_id).map(|s| s.body()) {
+                        operations.push(ModelOperation {
+                            name: format!(
+                                "{}.{}",
+                                shape.id().shape_name().to_string().to_camel_case(),
+                                op_id.shape_name().to_string().to_camel_case()
+                            ),
+                            input: op.input().clone(),
+                            output: op.output().clone(),
+                        });
+                    }
+                }
+            }
+        }
+        operations.sort_by(|a, b| a.name.cmp(&b.name));
+        InterfaceModel { model, operations }
+    }
+
+    pub(crate) fn operations(&self) -> &[ModelOperation] {
+        &self.operations
+    }
+
+    /// Finds an operation by its full name (`Service.Operation`),
+    /// or by operation name alone if only one service declares it
+    pub(crate) fn find_operation(&self, name: &str) -> Result<&ModelOperation> {
+        if let Some(op) = self.operations.iter().find(|op| op.name == name) {
+            return Ok(op);
+        }
+        let matches = self
+            .operations
+            .iter()
+            .filter(|op| op.name.rsplit('.').next() == Some(name))
+            .collect::<Vec<_>>();
+        match matches.as_slice() {
+            [op] => Ok(op),
+            [] => Err(format!(
+                "operation '{}' was not found in the model. Use --list-operations to see available operations",
+                name
+            )
+            .into()),
+            _ => Err(format!(
+                "operation '{}' is ambiguous, use one of: {}",
+                name,
+                matches
+                    .iter()
+                    .map(|op| op.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .into()),
+        }
+    }
+
+    /// Checks a json payload against the operation's input shape,
+    /// returning an error listing every mismatch found
+    pub(crate) fn validate_input(&self, op: &ModelOperation, payload: &JsonValue) -> Result<()> {
+        let mut errors = Vec::new();
+        match &op.input {
+            Some(input) => self.validate(input, payload, "$", &mut errors),
+            None if payload.is_null() => {}
+            None => errors.push(format!("$: operation {} does not take input", op.name)),
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "payload does not match the input of {}:\n  {}",
+                op.name,
+                errors.join("\n  ")
+            )
+            .into())
+        }
+    }
+
+    fn validate(&self, id: &ShapeID, value: &JsonValue, path: &str, errors: &mut Vec<String>) {
+        let mismatch = |errors: &mut Vec<String>, expected: &str| {
+            errors.push(format!(
+                "{}: expected {}, found {}",
+                path,
+                expected,
+                json_type(value)
+            ))
+        };
+        match self.resolve(id) {
+            Resolved::Simple(simple) => {
+                if !simple_matches(&simple, value) {
+                    mismatch(errors, &simple.to_string())
+                }
+            }
+            Resolved::Aggregate(ShapeKind::List(list))
+            | Resolved::Aggregate(ShapeKind::Set(list)) => match value.as_array() {
+                Some(items) => items.iter().enumerate().for_each(|(i, item)| {
+                    self.validate(
+                        list.member().target(),
+                        item,
+                        &format!("{}[{}]", path, i),
+                        errors,
+                    )
+                }),
+                None => mismatch(errors, "list"),
+            },
+            Resolved::Aggregate(ShapeKind::Map(map)) => match value.as_object() {
+                Some(entries) => entries.iter().for_each(|(k, v)| {
+                    self.validate(map.value().target(), v, &format!("{}.{}", path, k), errors)
+                }),
+                None => mismatch(errors, "map"),
+            },
+            Resolved::Aggregate(ShapeKind::Structure(strukt))
+            | Resolved::Aggregate(ShapeKind::Union(strukt)) => {
+                let fields = match value.as_object() {
+                    Some(fields) => fields,
+                    None => return mismatch(errors, &format!("structure {}", id.shape_name())),
+                };
+                let members = strukt
+                    .members()
+                    .map(|m| (serialized_name(m), m))
+                    .collect::<Vec<_>>();
+                for name in fields.keys() {
+                    if !members.iter().any(|(n, _)| n == name) {
+                        errors.push(format!(
+                            "{}: unknown field '{}' for {}. Expected one of: {}",
+                            path,
+                            name,
+                            id.shape_name(),
+                            members
+                                .iter()
+                                .map(|(n, _)| n.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ));
+                    }
+                }
+                for (name, member) in members.iter() {
+                    match fields.get(name) {
+                        Some(JsonValue::Null) | None if member.is_required() => {
+                            errors.push(format!("{}: missing required field '{}'", path, name))
+                        }
+                        Some(JsonValue::Null) | None => {}
+                        Some(v) => {
+                            self.validate(member.target(), v, &format!("{}.{}", path, name), errors)
+                        }
+                    }
+                }
+            }
+            // services, operations, resources, and unresolved shapes aren't payload types
+            Resolved::Aggregate(_) | Resolved::Unknown => {}
+        }
+    }
+
+    /// Decodes a msgpack response using the operation's output shape.
+    /// Values that don't match the shape are decoded as untyped msgpack.
+    pub(crate) fn decode_output(
+        &self,
+        op: &ModelOperation,
+        msg: &[u8],
+        decoder: &MsgpackDecoder,
+    ) -> JsonValue {
+        let output = match &op.output {
+            Some(output) if !msg.is_empty() => output,
+            _ => return decoder.decode(msg),
+        };
+        match rmpv::decode::read_value(&mut &msg[..]) {
+            Ok(v) => self.decode(output, v, decoder),
+            Err(e) => json!({ "error": format!("Could not decode data: {}", e) }),
+        }
+    }
+
+    fn decode(&self, id: &ShapeID, value: rmpv::Value, decoder: &MsgpackDecoder) -> JsonValue {
+        use rmpv::Value as RV;
+        match (self.resolve(id), value) {
+            (_, RV::Nil) => JsonValue::Null,
+            (Resolved::Simple(Simple::Blob), RV::Binary(v)) => decoder.binary(v),
+            (Resolved::Aggregate(ShapeKind::List(list)), RV::Array(items))
+            | (Resolved::Aggregate(ShapeKind::Set(list)), RV::Array(items)) => JsonValue::Array(
+                items
+                    .into_iter()
+                    .map(|v| self.decode(list.member().target(), v, decoder))
+                    .collect(),
+            ),
+            (Resolved::Aggregate(ShapeKind::Map(map)), RV::Map(entries)) => JsonValue::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| {
+                        (
+                            decoder.key(k),
+                            self.decode(map.value().target(), v, decoder),
+                        )
+                    })
+                    .collect(),
+            ),
+            (Resolved::Aggregate(ShapeKind::Structure(strukt)), RV::Map(entries))
+            | (Resolved::Aggregate(ShapeKind::Union(strukt)), RV::Map(entries)) => {
+                JsonValue::Object(
+                    entries
+                        .into_iter()
+                        .map(|(k, v)| {
+                            let name = decoder.key(k);
+                            let value = match strukt.members().find(|m| serialized_name(m) == name)
+                            {
+                                Some(member) => self.decode(member.target(), v, decoder),
+                                None => decoder.value(v),
+                            };
+                            (name, value)
+                        })
+                        .collect(),
+                )
+            }
+            (_, v) => decoder.value(v),
+        }
+    }
+
+    /// Resolves a shape id to its definition, including simple types from the smithy prelude
+    fn resolve(&self, id: &ShapeID) -> Resolved<'_> {
+        match self.model.shape(id).map(|s| s.body()) {
+            Some(ShapeKind::Simple(simple)) => Resolved::Simple(simple.clone()),
+            Some(ShapeKind::Unresolved) | None => prelude_simple(id)
+                .map(Resolved::Simple)
+                .unwrap_or(Resolved::Unknown),
+            Some(kind) => Resolved::Aggregate(kind),
+        }
+    }
+}
+
+pub(crate) fn list_operations_output(model: &InterfaceModel, output_kind: &OutputKind) -> String {
+    let shape_name = |id: &Option<ShapeID>| {
+        id.as_ref()
+            .map(|id| id.shape_name().to_string())
+            .unwrap_or_default()
+    };
+    match *output_kind {
+        OutputKind::Text => {
+            let mut table = Table::new();
+            configure_table_style(&mut table);
+            table.add_row(Row::new(vec![
+                TableCell::new_with_alignment("Operation", 1, Alignment::Left),
+                TableCell::new_with_alignment("Input", 1, Alignment::Left),
+                TableCell::new_with_alignment("Output", 1, Alignment::Left),
+            ]));
+            model.operations().iter().for_each(|op| {
+                table.add_row(Row::new(vec![
+                    TableCell::new_with_alignment(op.name.clone(), 1, Alignment::Left),
+                    TableCell::new_with_alignment(shape_name(&op.input), 1, Alignment::Left),
+                    TableCell::new_with_alignment(shape_name(&op.output), 1, Alignment::Left),
+                ]))
+            });
+            table.render()
+        }
+        OutputKind::Json => {
+            let operations = model
+                .operations()
+                .iter()
+                .map(|op| {
+                    json!({
+                        "operation": op.name,
+                        "input": op.input.as_ref().map(|id| id.to_string()),
+                        "output": op.output.as_ref().map(|id| id.to_string()),
+                    })
+                })
+                .collect::<Vec<_>>();
+            format!("{}", json!({ "operations": operations }))
+        }
+    }
+}
+
+/// Name of a structure member on the wire, which is the declared name
+/// unless overridden with `@serialization(name: ...)`
+fn serialized_name(member: &MemberShape) -> String {
+    let id = ShapeID::new_unchecked("org.wasmcloud.model", "serialization", None);
+    match member.traits().get(&id) {
+        Some(Some(NodeValue::Object(fields))) => match fields.get("name") {
+            Some(NodeValue::String(name)) => name.clone(),
+            _ => member.id().to_string(),
+        },
+        _ => member.id().to_string(),
+    }
+}
+
+/// Maps smithy prelude shapes (e.g. `smithy.api#Integer`, `smithy.api#PrimitiveLong`) to simple types
+fn prelude_simple(id: &ShapeID) -> Option<Simple> {
+    if id.namespace().to_string() != "smithy.api" {
+        return None;
+    }
+    let name = id.shape_name().to_string();
+    let name = name.strip_prefix("Primitive").unwrap_or(&name);
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    Simple::from_str(&format!("{}{}", first.to_ascii_lowercase(), chars.as_str())).ok()
+}
+
+fn simple_matches(simple: &Simple, value: &JsonValue) -> bool {
+    let int_in = |min: i64, max: i64| {
+        value
+            .as_i64()
+            .map(|i| i >= min && i <= max)
+            .unwrap_or(false)
+    };
+    match simple {
+        Simple::Blob => {
+            value.is_string()
+                || value
+                    .as_array()
+                    .map(|a| {
+                        a.iter()
+                            .all(|b| b.as_u64().map(|b| b <= 255).unwrap_or(false))
+                    })
+                    .unwrap_or(false)
+        }
+        Simple::Boolean => value.is_boolean(),
+        Simple::Document => true,
+        Simple::String => value.is_string(),
+        Simple::Byte => int_in(i8::MIN as i64, i8::MAX as i64),
+        Simple::Short => int_in(i16::MIN as i64, i16::MAX as i64),
+        Simple::Integer => int_in(i32::MIN as i64, i32::MAX as i64),
+        Simple::Long => value.is_i64() || value.is_u64(),
+        Simple::Float | Simple::Double => value.is_number(),
+        Simple::BigInteger | Simple::BigDecimal => value.is_number() || value.is_string(),
+        Simple::Timestamp => value.is_object(),
+    }
+}
+
+fn json_type(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(n) if n.is_f64() => "float",
+        JsonValue::Number(_) => "integer",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "list",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{InterfaceModel, MsgpackDecoder};
+    use crate::util::Result;
+    use serde_json::json;
+
+    const ECHO_MODEL: &str = r#"
+namespace org.example.echo
+
+service Echo {
+    version: "0.1",
+    operations: [ Say, Ping ]
+}
+
+operation Say {
+    input: SayRequest,
+    output: SayResponse
+}
+
+operation Ping {}
+
+structure SayRequest {
+    @required
+    message: String,
+    count: Integer,
+    tags: TagList,
+}
+
+list TagList {
+    member: String
+}
+
+structure SayResponse {
+    @required
+    reply: String,
+    data: Blob,
+}
+"#;
+
+    fn echo_model() -> Result<InterfaceModel> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("echo.smithy");
+        std::fs::write(&path, ECHO_MODEL)?;
+        InterfaceModel::load(&[path.to_string_lossy().to_string()])
+    }
+
+    #[test]
+    fn test_model_operations() -> Result<()> {
+        let model = echo_model()?;
+        let names = model
+            .operations()
+            .iter()
+            .map(|op| op.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Echo.Ping", "Echo.Say"]);
+        assert_eq!(model.find_operation("Say")?.name, "Echo.Say");
+        assert_eq!(model.find_operation("Echo.Ping")?.name, "Echo.Ping");
+        assert!(model.find_operation("Shout").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_validate_input() -> Result<()> {
+        let model = echo_model()?;
+        let say = model.find_operation("Say")?;
+        model.validate_input(say, &json!({"message": "hi", "count": 2, "tags": ["a"]}))?;
+
+        let err = model
+            .validate_input(say, &json!({"count": "two", "tagz": [], "tags": [1]}))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("$: missing required field 'message'"));
+        assert!(err.contains("$.count: expected integer, found string"));
+        assert!(err.contains("$: unknown field 'tagz'"));
+        assert!(err.contains("$.tags[0]: expected string, found integer"));
+
+        let ping = model.find_operation("Ping")?;
+        model.validate_input(ping, &json!(null))?;
+        assert!(model.validate_input(ping, &json!({})).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_decode_output() -> Result<()> {
+        let model = echo_model()?;
+        let say = model.find_operation("Say")?;
+        let mut msg = Vec::new();
+        rmpv::encode::write_value(
+            &mut msg,
+            &rmpv::Value::Map(vec![
+                ("reply".into(), "hello".into()),
+                ("data".into(), rmpv::Value::Binary(b"hi".to_vec())),
+            ]),
+        )?;
+        assert_eq!(
+            model.decode_output(say, &msg, &MsgpackDecoder::new('s')),
+            json!({"reply": "hello", "data": "hi"})
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,365 @@
+//! interactive session for `wash call`, enabled with --interactive
+//!
+use super::decode::{MsgpackDecoder, ResponseFormat};
+use super::model::InterfaceModel;
+use super::resolve::resolve_actor;
+use super::{
+    encode_json_payload, rpc_client_from_opts, target_entity, CallCommand, WASH_ORIGIN_KEY,
+};
+use crate::ctl::id::ServiceId;
+use crate::util::{convert_rpc_error, OutputKind, Result};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use wasmbus_rpc::{core::WasmCloudEntity, Message, RpcClient};
+
+/// file in ~/.wash where session history is kept
+const HISTORY_FILE: &str = "call_history";
+
+const COMMANDS: &[&str] = &[":actor", ":help", ":ops", ":provider", ":quit"];
+
+const HELP: &str = r#"Commands:
+  <operation> [payload]                        invoke an operation, e.g. Echo.Say {"message": "hi"}
+  <payload>                                    invoke the last operation with a new json payload
+  :actor <actor-id>                            invoke an actor, by public key, OCI reference, or name
+  :provider <provider-id> <contract> [link]    invoke a capability provider
+  :ops                                         list known operations
+  :help                                        show this help
+  :quit                                        end the session
+Use the up arrow to recall and edit previous calls, and tab to complete operation names."#;
+
+/// A line entered in the interactive session
+#[derive(Debug, PartialEq)]
+enum ReplCommand<'a> {
+    Quit,
+    Help,
+    Operations,
+    Actor(&'a str),
+    Provider {
+        id: &'a str,
+        contract: &'a str,
+        link_name: &'a str,
+    },
+    Call {
+        operation: Option<&'a str>,
+        payload: &'a str,
+    },
+}
+
+/// Completes command and operation names
+struct ReplHelper {
+    operations: BTreeSet<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let prefix = &line[..pos];
+        if prefix.contains(char::is_whitespace) {
+            return Ok((pos, Vec::new()));
+        }
+        let candidates = if prefix.starts_with(':') {
+            COMMANDS
+                .iter()
+                .filter(|c| c.starts_with(prefix))
+                .map(|c| c.to_string())
+                .collect()
+        } else {
+            // match either the full name or the operation name without its service prefix
+            self.operations
+                .iter()
+                .filter(|op| {
+                    op.starts_with(prefix)
+                        || op
+                            .rsplit('.')
+                            .next()
+                            .unwrap_or_default()
+                            .starts_with(prefix)
+                })
+                .cloned()
+                .collect()
+        };
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+pub(crate) async fn handle_repl(
+    cmd: CallCommand,
+    model: Option<&InterfaceModel>,
+) -> Result<String> {
+    if !"bs2".contains(cmd.bin) {
+        return Err("'bin' parameter must be 'b', 's', or '2'".into());
+    }
+    let mut target = if cmd.target.is_some() {
+        Some(target_entity(&cmd)?)
+    } else {
+        None
+    };
+    let mut operation = cmd.operation.clone();
+    let (decoder, output_kind) = (MsgpackDecoder::new(cmd.bin), cmd.output.kind);
+    let format = ResponseFormat::new(cmd.query.as_deref(), cmd.template.as_deref())?;
+    let (opts, registry) = (cmd.opts.clone(), cmd.registry.clone());
+    let (client, timeout) = rpc_client_from_opts(cmd.opts, cmd.cluster_seed).await?;
+    let origin = WasmCloudEntity::new_actor(WASH_ORIGIN_KEY)?;
+
+    let mut rl = Editor::<ReplHelper>::new();
+    rl.set_helper(Some(ReplHelper {
+        operations: model
+            .map(|m| m.operations().iter().map(|op| op.name.clone()).collect())
+            .unwrap_or_default(),
+    }));
+    let history = dirs::home_dir().map(|h| h.join(".wash").join(HISTORY_FILE));
+    if let Some(ref history) = history {
+        // there's no history the first time a session is started
+        let _ = rl.load_history(history);
+    }
+
+    println!("Interactive call session. Type :help for commands, :quit to exit");
+    loop {
+        let prompt = format!("{}> ", operation.as_deref().unwrap_or("wash"));
+        // readline blocks, so let the runtime move other tasks (e.g. the nats client) elsewhere
+        let line = match tokio::task::block_in_place(|| rl.readline(&prompt)) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        rl.add_history_entry(line);
+
+        match parse_line(line) {
+            Ok(ReplCommand::Quit) => break,
+            Ok(ReplCommand::Help) => println!("{}", HELP),
+            Ok(ReplCommand::Operations) => {
+                if let Some(helper) = rl.helper() {
+                    helper.operations.iter().for_each(|op| println!("{}", op));
+                }
+            }
+            Ok(ReplCommand::Actor(id)) => match resolve_actor(id, &opts, &registry)
+                .await
+                .and_then(|key| WasmCloudEntity::new_actor(&key).map_err(|e| e.into()))
+            {
+                Ok(actor) => target = Some(actor),
+                Err(e) => eprintln!("Error: {}", e),
+            },
+            Ok(ReplCommand::Provider {
+                id,
+                contract,
+                link_name,
+            }) => match ServiceId::from_str(id) {
+                Ok(id) => {
+                    target = Some(WasmCloudEntity {
+                        public_key: id.to_string(),
+                        contract_id: contract.to_string(),
+                        link_name: link_name.to_string(),
+                    })
+                }
+                Err(e) => eprintln!("Error: invalid provider id: {}", e),
+            },
+            Ok(ReplCommand::Call {
+                operation: op,
+                payload,
+            }) => {
+                if let Some(op) = op {
+                    operation = Some(op.to_string());
+                }
+                let (target, op) = match (&target, &operation) {
+                    (Some(target), Some(op)) => (target, op),
+                    (None, _) => {
+                        eprintln!("Error: no actor selected, use :actor or :provider first");
+                        continue;
+                    }
+                    (_, None) => {
+                        eprintln!("Error: no operation selected");
+                        continue;
+                    }
+                };
+                let call = ReplCall {
+                    client: &client,
+                    origin: &origin,
+                    target,
+                    timeout: Duration::from_millis(timeout),
+                    model,
+                    decoder,
+                };
+                match call.invoke(op, payload).await {
+                    Ok((name, response)) => {
+                        if let Some(helper) = rl.helper_mut() {
+                            helper.operations.insert(name);
+                        }
+                        match (&format, output_kind) {
+                            (Some(format), _) => match format.render(&response) {
+                                Ok(rendered) => println!("{}", rendered),
+                                Err(e) => eprintln!("Error: {}", e),
+                            },
+                            (None, OutputKind::Json) => println!("{}", response),
+                            (None, OutputKind::Text) => println!(
+                                "{}",
+                                serde_json::to_string_pretty(&response)
+                                    .unwrap_or_else(|_| response.to_string())
+                            ),
+                        }
+                    }
+                    Err(e) => eprintln!("Error invoking operation: {}", e),
+                }
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+
+    if let Some(ref history) = history {
+        if let Err(e) = save_history(&mut rl, history) {
+            eprintln!("Error saving session history: {}", e);
+        }
+    }
+    Ok(String::new())
+}
+
+/// Everything needed to send one invocation from the session
+struct ReplCall<'a> {
+    client: &'a RpcClient,
+    origin: &'a WasmCloudEntity,
+    target: &'a WasmCloudEntity,
+    timeout: Duration,
+    model: Option<&'a InterfaceModel>,
+    decoder: MsgpackDecoder,
+}
+
+impl ReplCall<'_> {
+    /// Sends the operation, returning the full operation name and the decoded response
+    async fn invoke(&self, operation: &str, payload: &str) -> Result<(String, serde_json::Value)> {
+        let typed = match self.model {
+            Some(model) => Some((model, model.find_operation(operation)?)),
+            None => None,
+        };
+        let method = typed
+            .map(|(_, op)| op.name.clone())
+            .unwrap_or_else(|| operation.to_string());
+        let bytes = encode_json_payload(payload, typed)?;
+        let msg = self
+            .client
+            .send_timeout(
+                self.origin.clone(),
+                self.target.clone(),
+                Message {
+                    method: &method,
+                    arg: bytes.into(),
+                },
+                self.timeout,
+            )
+            .await
+            .map_err(convert_rpc_error)?;
+        let response = match typed {
+            Some((model, op)) => model.decode_output(op, &msg, &self.decoder),
+            None => self.decoder.decode(&msg),
+        };
+        Ok((method, response))
+    }
+}
+
+fn parse_line(line: &str) -> std::result::Result<ReplCommand<'_>, String> {
+    let (word, rest) = match line.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (line, ""),
+    };
+    let args = rest.split_whitespace().collect::<Vec<_>>();
+    match word {
+        ":quit" | ":q" | ":exit" => Ok(ReplCommand::Quit),
+        ":help" | ":h" => Ok(ReplCommand::Help),
+        ":ops" => Ok(ReplCommand::Operations),
+        ":actor" => match args.as_slice() {
+            [id] => Ok(ReplCommand::Actor(id)),
+            _ => Err("usage: :actor <actor-id>".to_string()),
+        },
+        ":provider" => match args.as_slice() {
+            [id, contract] => Ok(ReplCommand::Provider {
+                id,
+                contract,
+                link_name: "default",
+            }),
+            [id, contract, link_name] => Ok(ReplCommand::Provider {
+                id,
+                contract,
+                link_name,
+            }),
+            _ => Err("usage: :provider <provider-id> <contract> [link-name]".to_string()),
+        },
+        cmd if cmd.starts_with(':') => Err(format!("unknown command '{}', try :help", cmd)),
+        _ if line.starts_with('{') || line.starts_with('[') => Ok(ReplCommand::Call {
+            operation: None,
+            payload: line,
+        }),
+        operation => Ok(ReplCommand::Call {
+            operation: Some(operation),
+            payload: rest,
+        }),
+    }
+}
+
+fn save_history(rl: &mut Editor<ReplHelper>, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    rl.save_history(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_line, ReplCommand};
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line(":q"), Ok(ReplCommand::Quit));
+        assert_eq!(
+            parse_line(":provider VABC wasmcloud:keyvalue"),
+            Ok(ReplCommand::Provider {
+                id: "VABC",
+                contract: "wasmcloud:keyvalue",
+                link_name: "default"
+            })
+        );
+        assert_eq!(
+            parse_line("Echo.Say {\"message\": \"hi there\"}"),
+            Ok(ReplCommand::Call {
+                operation: Some("Echo.Say"),
+                payload: "{\"message\": \"hi there\"}"
+            })
+        );
+        assert_eq!(
+            parse_line("{\"message\": \"again\"}"),
+            Ok(ReplCommand::Call {
+                operation: None,
+                payload: "{\"message\": \"again\"}"
+            })
+        );
+        assert!(parse_line(":actor").is_err());
+        assert!(parse_line(":bogus").is_err());
+    }
+}
@@ -0,0 +1,316 @@
+//! recording calls made with `wash call --record`, and replaying them with `wash call replay`
+//!
+use super::decode::MsgpackDecoder;
+use super::{rpc_client_from_opts, ConnectionOpts, Invocation, WASH_ORIGIN_KEY};
+use crate::util::{
+    configure_table_style, convert_rpc_error, CommandFailure, Output, OutputKind, Result,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use structopt::StructOpt;
+use term_table::{row::Row, table_cell::*, Table};
+use wasmbus_rpc::{core::WasmCloudEntity, Message};
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) struct ReplayCommand {
+    #[structopt(flatten)]
+    opts: ConnectionOpts,
+
+    #[structopt(flatten)]
+    pub(crate) output: Output,
+
+    /// wasmCloud host cluster seed. This cluster seed must match the cluster seed used to
+    /// launch the wasmCloud host in order to pass antiforgery checks made by the host
+    /// This is only optional if a default context is available or a context is provided
+    #[structopt(short = "c", long = "cluster-seed", env = "WASMCLOUD_CLUSTER_SEED")]
+    pub(crate) cluster_seed: Option<String>,
+
+    /// How responses are compared to the recording: exact (byte-for-byte),
+    /// or json (structural comparison of the decoded responses)
+    #[structopt(long, default_value = "exact")]
+    pub(crate) compare: CompareMode,
+
+    /// File of calls recorded with `wash call --record`
+    #[structopt(name = "file")]
+    pub(crate) file: PathBuf,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum CompareMode {
+    Exact,
+    Json,
+}
+
+impl FromStr for CompareMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(CompareMode::Exact),
+            "json" => Ok(CompareMode::Json),
+            _ => Err(format!(
+                "invalid compare mode '{}', must be exact or json",
+                s
+            )),
+        }
+    }
+}
+
+/// Calls recorded with `wash call --record`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Recording {
+    pub(crate) calls: Vec<RecordedCall>,
+}
+
+/// A request/response pair. Payloads are stored as base64 msgpack, along with their
+/// json decoding for readability. Only the base64 payloads are used for replay.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RecordedCall {
+    pub(crate) target: WasmCloudEntity,
+    pub(crate) operation: String,
+    #[serde(with = "base64_bytes")]
+    pub(crate) request: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub(crate) response: Vec<u8>,
+    #[serde(default)]
+    pub(crate) request_json: JsonValue,
+    #[serde(default)]
+    pub(crate) response_json: JsonValue,
+}
+
+mod base64_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&data_encoding::BASE64.encode(bytes))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(d)?;
+        data_encoding::BASE64
+            .decode(encoded.as_bytes())
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Appends a successful call to the recording file, creating it if needed
+pub(crate) fn record_call(path: &Path, inv: &Invocation, response: &[u8]) -> Result<()> {
+    let mut recording = if path.exists() {
+        serde_json::from_slice::<Recording>(&std::fs::read(path)?)
+            .map_err(|e| format!("invalid recording file {}: {}", path.display(), e))?
+    } else {
+        Recording::default()
+    };
+    recording.calls.push(RecordedCall {
+        target: inv.target.clone(),
+        operation: inv.method.clone(),
+        request: inv.payload.clone(),
+        response: response.to_vec(),
+        request_json: MsgpackDecoder::new('b').decode(&inv.payload),
+        response_json: MsgpackDecoder::new('b').decode(response),
+    });
+    std::fs::write(path, serde_json::to_vec_pretty(&recording)?)?;
+    Ok(())
+}
+
+/// Result of replaying one recorded call
+struct ReplayResult {
+    operation: String,
+    /// differences from the recorded response, or the invocation error
+    differences: Vec<String>,
+}
+
+pub(crate) async fn handle_replay(cmd: ReplayCommand) -> Result<String> {
+    let recording = serde_json::from_slice::<Recording>(&std::fs::read(&cmd.file)?)
+        .map_err(|e| format!("invalid recording file {}: {}", cmd.file.display(), e))?;
+    let (client, timeout) = rpc_client_from_opts(cmd.opts, cmd.cluster_seed).await?;
+    let origin = WasmCloudEntity::new_actor(WASH_ORIGIN_KEY)?;
+
+    let mut results = Vec::new();
+    for call in recording.calls.iter() {
+        let res = client
+            .send_timeout(
+                origin.clone(),
+                call.target.clone(),
+                Message {
+                    method: &call.operation,
+                    arg: call.request.as_slice().into(),
+                },
+                Duration::from_millis(timeout),
+            )
+            .await
+            .map_err(convert_rpc_error);
+        let differences = match res {
+            Ok(response) => compare_responses(&call.response, &response, cmd.compare),
+            Err(e) => vec![format!("error invoking actor: {}", e)],
+        };
+        results.push(ReplayResult {
+            operation: call.operation.clone(),
+            differences,
+        });
+    }
+
+    let output = replay_output(&results, &cmd.output.kind);
+    if results.iter().any(|r| !r.differences.is_empty()) {
+        Err(CommandFailure(output).into())
+    } else {
+        Ok(output)
+    }
+}
+
+/// Returns a description of each difference between the recorded and actual responses
+fn compare_responses(expected: &[u8], actual: &[u8], mode: CompareMode) -> Vec<String> {
+    match mode {
+        CompareMode::Exact if expected == actual => Vec::new(),
+        CompareMode::Exact => vec![format!(
+            "response bytes differ (expected {} bytes, got {} bytes)",
+            expected.len(),
+            actual.len()
+        )],
+        CompareMode::Json => {
+            let mut differences = Vec::new();
+            let decoder = MsgpackDecoder::new('b');
+            json_diff(
+                "$",
+                &decoder.decode(expected),
+                &decoder.decode(actual),
+                &mut differences,
+            );
+            differences
+        }
+    }
+}
+
+fn json_diff(path: &str, expected: &JsonValue, actual: &JsonValue, diffs: &mut Vec<String>) {
+    match (expected, actual) {
+        (JsonValue::Object(e), JsonValue::Object(a)) => {
+            for (k, ev) in e.iter() {
+                match a.get(k) {
+                    Some(av) => json_diff(&format!("{}.{}", path, k), ev, av, diffs),
+                    None => diffs.push(format!("{}.{}: missing from response", path, k)),
+                }
+            }
+            for k in a.keys().filter(|k| !e.contains_key(*k)) {
+                diffs.push(format!("{}.{}: not in recorded response", path, k));
+            }
+        }
+        (JsonValue::Array(e), JsonValue::Array(a)) if e.len() == a.len() => {
+            for (i, (ev, av)) in e.iter().zip(a.iter()).enumerate() {
+                json_diff(&format!("{}[{}]", path, i), ev, av, diffs);
+            }
+        }
+        (JsonValue::Array(e), JsonValue::Array(a)) => diffs.push(format!(
+            "{}: expected {} items, got {}",
+            path,
+            e.len(),
+            a.len()
+        )),
+        (e, a) if e != a => diffs.push(format!("{}: expected {}, got {}", path, e, a)),
+        _ => {}
+    }
+}
+
+fn replay_output(results: &[ReplayResult], output_kind: &OutputKind) -> String {
+    let passed = results.iter().filter(|r| r.differences.is_empty()).count();
+    match *output_kind {
+        OutputKind::Text => {
+            let mut table = Table::new();
+            configure_table_style(&mut table);
+            table.add_row(Row::new(vec![
+                TableCell::new_with_alignment("#", 1, Alignment::Left),
+                TableCell::new_with_alignment("Operation", 1, Alignment::Left),
+                TableCell::new_with_alignment("Result", 1, Alignment::Left),
+            ]));
+            for (i, r) in results.iter().enumerate() {
+                let result = if r.differences.is_empty() {
+                    "match".to_string()
+                } else {
+                    format!("MISMATCH\n{}", r.differences.join("\n"))
+                };
+                table.add_row(Row::new(vec![
+                    TableCell::new_with_alignment(i + 1, 1, Alignment::Left),
+                    TableCell::new_with_alignment(&r.operation, 1, Alignment::Left),
+                    TableCell::new_with_alignment(result, 1, Alignment::Left),
+                ]));
+            }
+            format!(
+                "{}\n{} of {} recorded calls matched",
+                table.render(),
+                passed,
+                results.len()
+            )
+        }
+        OutputKind::Json => {
+            let results = results
+                .iter()
+                .map(|r| {
+                    json!({
+                        "operation": r.operation,
+                        "passed": r.differences.is_empty(),
+                        "differences": r.differences,
+                    })
+                })
+                .collect::<Vec<_>>();
+            format!(
+                "{}",
+                json!({
+                    "passed": passed,
+                    "failed": results.len() - passed,
+                    "results": results,
+                })
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compare_responses, CompareMode, Recording};
+    use crate::util::Result;
+
+    fn msgpack(v: serde_json::Value) -> Vec<u8> {
+        wasmbus_rpc::serialize(&v).unwrap()
+    }
+
+    #[test]
+    fn test_compare_responses() {
+        let recorded = msgpack(serde_json::json!({"a": 1, "b": [1, 2]}));
+        assert!(compare_responses(&recorded, &recorded, CompareMode::Exact).is_empty());
+
+        let reordered = msgpack(serde_json::json!({"b": [1, 2], "a": 1}));
+        assert!(compare_responses(&recorded, &reordered, CompareMode::Json).is_empty());
+
+        let changed = msgpack(serde_json::json!({"a": 2, "b": [1], "c": true}));
+        assert_eq!(
+            compare_responses(&recorded, &changed, CompareMode::Json),
+            vec![
+                "$.a: expected 1, got 2".to_string(),
+                "$.b: expected 2 items, got 1".to_string(),
+                "$.c: not in recorded response".to_string(),
+            ]
+        );
+        assert_eq!(
+            compare_responses(&recorded, &changed, CompareMode::Exact).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_recording_roundtrip() -> Result<()> {
+        let json = r#"{"calls": [{
+            "target": {"public_key": "MABC", "link_name": "", "contract_id": ""},
+            "operation": "Echo.Say",
+            "request": "gaFhAQ==",
+            "response": "wA=="
+        }]}"#;
+        let recording: Recording = serde_json::from_str(json)?;
+        assert_eq!(recording.calls[0].request, vec![0x81, 0xa1, 0x61, 0x01]);
+        assert_eq!(recording.calls[0].response, vec![0xc0]);
+        let encoded = serde_json::to_value(&recording)?;
+        assert_eq!(encoded["calls"][0]["request"], "gaFhAQ==");
+        Ok(())
+    }
+}
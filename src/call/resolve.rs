@@ -0,0 +1,166 @@
+//! resolving the target argument of `wash call` to an actor public key
+//!
+use super::{lattice_connection, ConnectionOpts};
+use crate::util::{convert_error, RegistryOpts, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+use wascap::wasm::extract_claims;
+use wasmcloud_control_interface::Client as CtlClient;
+
+/// How a target argument is resolved to a public key
+#[derive(Debug, PartialEq, Eq)]
+enum ActorRef<'a> {
+    /// an actor public key, used as-is
+    PublicKey(&'a str),
+    /// a signed module on disk, whose claims hold the public key
+    File(&'a str),
+    /// an OCI reference, pulled to read the module's claims
+    Oci(&'a str),
+    /// an actor name or call alias, looked up in the lattice claims cache
+    Name(&'a str),
+}
+
+impl<'a> ActorRef<'a> {
+    fn parse(actor: &'a str) -> Self {
+        if is_actor_public_key(actor) {
+            ActorRef::PublicKey(actor)
+        } else if std::path::Path::new(actor).is_file() {
+            ActorRef::File(actor)
+        } else if actor.contains('/') || actor.contains(':') {
+            ActorRef::Oci(actor)
+        } else {
+            ActorRef::Name(actor)
+        }
+    }
+}
+
+fn is_actor_public_key(s: &str) -> bool {
+    s.len() == 56 && s.starts_with('M') && s.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Resolves an actor public key, signed module path, OCI reference, or actor name
+/// (or call alias) to the actor's public key
+pub(crate) async fn resolve_actor(
+    actor: &str,
+    opts: &ConnectionOpts,
+    registry: &RegistryOpts,
+) -> Result<String> {
+    match ActorRef::parse(actor) {
+        ActorRef::PublicKey(key) => Ok(key.to_string()),
+        ActorRef::File(path) => module_subject(&std::fs::read(path)?, actor),
+        ActorRef::Oci(url) => {
+            let module = crate::reg::pull_artifact(
+                url.to_string(),
+                None,
+                registry.allow_latest,
+                registry.user.clone(),
+                registry.password.clone(),
+                registry.insecure,
+            )
+            .await
+            .map_err(|e| format!("Error pulling actor {}: {}", url, e))?;
+            module_subject(&module, actor)
+        }
+        ActorRef::Name(name) => {
+            let claims = lattice_claims(opts)
+                .await
+                .map_err(|e| format!("Error looking up actor '{}' in the lattice: {}", name, e))?;
+            find_by_name(&claims, name)
+        }
+    }
+}
+
+/// Retrieves the claims cached in the lattice, over the RPC connection
+async fn lattice_claims(opts: &ConnectionOpts) -> Result<Vec<HashMap<String, String>>> {
+    let conn = lattice_connection(opts.clone()).await?;
+    let client = CtlClient::new(
+        conn.nc,
+        Some(conn.lattice_prefix),
+        Duration::from_millis(conn.timeout),
+    );
+    Ok(client.get_claims().await.map_err(convert_error)?.claims)
+}
+
+/// Returns the subject of the claims embedded in a signed module
+fn module_subject(module: &[u8], actor: &str) -> Result<String> {
+    match extract_claims(module)? {
+        Some(token) => Ok(token.claims.subject),
+        None => Err(format!("No claims found in actor module {}", actor).into()),
+    }
+}
+
+/// Finds the public key of the actor whose name or call alias matches, among
+/// the claims cached in the lattice
+fn find_by_name(claims: &[HashMap<String, String>], name: &str) -> Result<String> {
+    let matches = claims
+        .iter()
+        .filter(|c| {
+            c.get("sub")
+                .map(|sub| is_actor_public_key(sub))
+                .unwrap_or(false)
+        })
+        .filter(|c| {
+            c.get("name").map(String::as_str) == Some(name)
+                || c.get("call_alias").map(String::as_str) == Some(name)
+        })
+        .filter_map(|c| c.get("sub").cloned())
+        .collect::<BTreeSet<_>>();
+    match matches.len() {
+        1 => Ok(matches.into_iter().next().unwrap_or_default()),
+        0 => Err(format!(
+            "No actor named '{}' found in the lattice claims. Use a public key or OCI reference instead",
+            name
+        )
+        .into()),
+        _ => Err(format!(
+            "Actor name '{}' is ambiguous, it matches {}",
+            name,
+            matches.into_iter().collect::<Vec<_>>().join(", ")
+        )
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_by_name, ActorRef};
+    use std::collections::HashMap;
+
+    const ECHO: &str = "MDPDJEYIAK6MACO67PRFGOSSLODBISK4SCEYDY3HEOY4P5CVJN6UCWUK";
+    const KVCOUNTER: &str = "MCFMFDWFHGKELOXPCNCDXKK5OFLHBVEWRAOXR5JSQUD2TOFRE3DFPM7E";
+
+    #[test]
+    fn test_parse_actor_ref() {
+        assert_eq!(ActorRef::parse(ECHO), ActorRef::PublicKey(ECHO));
+        assert_eq!(
+            ActorRef::parse("wasmcloud.azurecr.io/echo:0.3.2"),
+            ActorRef::Oci("wasmcloud.azurecr.io/echo:0.3.2")
+        );
+        assert_eq!(
+            ActorRef::parse("localhost:5000/echo:0.3.2"),
+            ActorRef::Oci("localhost:5000/echo:0.3.2")
+        );
+        assert_eq!(ActorRef::parse("echo"), ActorRef::Name("echo"));
+    }
+
+    #[test]
+    fn test_find_by_name() {
+        let claims = |sub: &str, name: &str, alias: &str| {
+            vec![("sub", sub), ("name", name), ("call_alias", alias)]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        let cache = vec![
+            claims(ECHO, "Echo", "echo"),
+            claims(KVCOUNTER, "KV Counter", "counter"),
+            claims(KVCOUNTER, "KV Counter", "counter"),
+        ];
+        assert_eq!(find_by_name(&cache, "Echo").unwrap(), ECHO);
+        assert_eq!(find_by_name(&cache, "counter").unwrap(), KVCOUNTER);
+        assert!(find_by_name(&cache, "missing").is_err());
+
+        let ambiguous = vec![claims(ECHO, "Echo", ""), claims(KVCOUNTER, "Echo", "")];
+        assert!(find_by_name(&ambiguous, "Echo").is_err());
+    }
+}
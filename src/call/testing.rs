@@ -0,0 +1,161 @@
+//! output of test actors invoked with `wash call --test`
+//!
+use crate::util::{CommandFailure, OutputKind, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::path::Path;
+use wasmcloud_test_util::testing::{TestResult, TestResults};
+
+/// structure for deserializing the error report in a failed test's snap_data
+#[derive(Deserialize)]
+struct ErrorReport {
+    error: String,
+}
+
+/// Interprets a response as TestResults, printing them in the requested format and optionally
+/// writing a JUnit XML report. Returns an error if the response isn't TestResults, or
+/// a `CommandFailure` with the output if any test failed
+pub(crate) fn test_output(
+    msg: &[u8],
+    suite: &str,
+    junit: Option<&Path>,
+    output_kind: &OutputKind,
+) -> Result<String> {
+    let results = wasmbus_rpc::deserialize::<TestResults>(msg).map_err(|e| {
+        format!(
+            "Error interpreting response as TestResults: {}. (raw): {}",
+            e,
+            String::from_utf8_lossy(msg)
+        )
+    })?;
+    if let Some(path) = junit {
+        std::fs::write(path, junit_xml(suite, &results))
+            .map_err(|e| format!("Error writing JUnit report to {}: {}", path.display(), e))?;
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    let output = match *output_kind {
+        OutputKind::Text => {
+            wasmcloud_test_util::cli::print_test_results(&results);
+            String::new()
+        }
+        OutputKind::Json => {
+            let tests = results
+                .iter()
+                .map(|r| json!({ "name": r.name, "passed": r.passed, "error": error_message(r) }))
+                .collect::<Vec<_>>();
+            format!(
+                "{}",
+                json!({
+                    "passed": results.len() - failed,
+                    "failed": failed,
+                    "results": tests,
+                })
+            )
+        }
+    };
+    if failed > 0 {
+        Err(CommandFailure(output).into())
+    } else {
+        Ok(output)
+    }
+}
+
+/// Error message reported by a failed test, if any
+fn error_message(result: &TestResult) -> Option<String> {
+    result
+        .snap_data
+        .as_ref()
+        .and_then(|bytes| serde_json::from_slice::<ErrorReport>(bytes).ok())
+        .map(|r| r.error)
+}
+
+fn junit_xml(suite: &str, results: &[TestResult]) -> String {
+    let failed = results.iter().filter(|r| !r.passed).count();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites tests=\"{}\" failures=\"{}\">\n",
+        results.len(),
+        failed
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+        xml_escape(suite),
+        results.len(),
+        failed
+    ));
+    for result in results {
+        let case = format!(
+            "    <testcase name=\"{}\" classname=\"{}\"",
+            xml_escape(&result.name),
+            xml_escape(suite)
+        );
+        if result.passed {
+            xml.push_str(&format!("{}/>\n", case));
+        } else {
+            let error = xml_escape(&error_message(result).unwrap_or_default());
+            xml.push_str(&format!(
+                "{}>\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                case, error, error
+            ));
+        }
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+fn xml_escape(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&apos;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{junit_xml, test_output};
+    use crate::util::{CommandFailure, OutputKind};
+    use wasmcloud_test_util::testing::TestResult;
+
+    fn results() -> Vec<TestResult> {
+        vec![
+            TestResult {
+                name: "adds <numbers>".to_string(),
+                passed: true,
+                snap_data: None,
+            },
+            TestResult {
+                name: "divides".to_string(),
+                passed: false,
+                snap_data: Some(br#"{"error": "expected \"2\" & got 3"}"#.to_vec()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_junit_xml() {
+        let xml = junit_xml("MATH", &results());
+        assert!(xml.contains(r#"<testsuite name="MATH" tests="2" failures="1">"#));
+        assert!(xml.contains(r#"<testcase name="adds &lt;numbers&gt;" classname="MATH"/>"#));
+        assert!(xml.contains(r#"<failure message="expected &quot;2&quot; &amp; got 3">"#));
+    }
+
+    #[test]
+    fn test_failed_tests_exit_nonzero() {
+        let msg = wasmbus_rpc::serialize(&results()).unwrap();
+        let err = test_output(&msg, "MATH", None, &OutputKind::Json).unwrap_err();
+        let output = err.downcast_ref::<CommandFailure>().unwrap();
+        let json: serde_json::Value = serde_json::from_str(&output.0).unwrap();
+        assert_eq!(json["failed"], 1);
+        assert_eq!(json["results"][1]["error"], "expected \"2\" & got 3");
+
+        let msg = wasmbus_rpc::serialize(&results()[..1].to_vec()).unwrap();
+        assert!(test_output(&msg, "MATH", None, &OutputKind::Json).is_ok());
+    }
+}
@@ -0,0 +1,108 @@
+//! timing and invocation details for `wash call --trace`
+//!
+use super::Invocation;
+use crate::util::configure_table_style;
+use serde_json::json;
+use std::time::Duration;
+use term_table::{row::Row, table_cell::*, Table};
+
+/// What happened during a call, filled in as each stage completes so that a
+/// failed call still reports the stages it reached. Only what wash itself knows is
+/// recorded; the invocation that `RpcClient` builds and signs isn't visible to it
+#[derive(Debug, Default)]
+pub(crate) struct CallTrace {
+    pub(crate) connect: Option<Duration>,
+    pub(crate) subject: Option<String>,
+    pub(crate) origin: Option<String>,
+    pub(crate) target: Option<String>,
+    pub(crate) operation: Option<String>,
+    /// size of the operation payload
+    pub(crate) request_bytes: Option<usize>,
+    pub(crate) response_bytes: Option<usize>,
+    pub(crate) round_trip: Option<Duration>,
+    pub(crate) decode: Option<Duration>,
+}
+
+impl CallTrace {
+    /// Records what is about to be sent, once connected to the lattice
+    pub(super) fn invocation(&mut self, inv: &Invocation, subject: String) {
+        self.subject = Some(subject);
+        self.origin = Some(inv.origin.url());
+        self.target = Some(format!("{}/{}", inv.target.url(), inv.method));
+        self.operation = Some(inv.method.clone());
+        self.request_bytes = Some(inv.payload.len());
+    }
+
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        json!({
+            "connect_ms": self.connect.map(as_millis),
+            "subject": self.subject,
+            "origin": self.origin,
+            "target": self.target,
+            "operation": self.operation,
+            "request_bytes": self.request_bytes,
+            "response_bytes": self.response_bytes,
+            "round_trip_ms": self.round_trip.map(as_millis),
+            "decode_ms": self.decode.map(as_millis),
+        })
+    }
+
+    pub(crate) fn to_table(&self) -> String {
+        let mut table = Table::new();
+        configure_table_style(&mut table);
+        let mut add_row = |label: &str, value: Option<String>| {
+            table.add_row(Row::new(vec![
+                TableCell::new_with_alignment(label, 1, Alignment::Left),
+                TableCell::new_with_alignment(
+                    value.unwrap_or_else(|| "N/A".to_string()),
+                    1,
+                    Alignment::Left,
+                ),
+            ]))
+        };
+        let ms = |d: Option<Duration>| d.map(|d| format!("{:.3} ms", as_millis(d)));
+        let bytes = |n: Option<usize>| n.map(|n| format!("{} bytes", n));
+
+        add_row("Connect", ms(self.connect));
+        add_row("Subject", self.subject.clone());
+        add_row("Origin", self.origin.clone());
+        add_row("Target", self.target.clone());
+        add_row("Operation", self.operation.clone());
+        add_row("Request size", bytes(self.request_bytes));
+        add_row("Response size", bytes(self.response_bytes));
+        add_row("Round trip", ms(self.round_trip));
+        add_row("Decode", ms(self.decode));
+        table.render()
+    }
+}
+
+fn as_millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::CallTrace;
+    use std::time::Duration;
+
+    #[test]
+    fn test_trace_report() {
+        let trace = CallTrace {
+            connect: Some(Duration::from_millis(3)),
+            subject: Some("wasmbus.rpc.default.MABC".to_string()),
+            request_bytes: Some(12),
+            ..Default::default()
+        };
+        let json = trace.to_json();
+        assert_eq!(json["connect_ms"], 3.0);
+        assert_eq!(json["subject"], "wasmbus.rpc.default.MABC");
+        assert_eq!(json["request_bytes"], 12);
+        // stages that weren't reached are null
+        assert!(json["round_trip_ms"].is_null());
+        assert!(json["target"].is_null());
+
+        let table = trace.to_table();
+        assert!(table.contains("wasmbus.rpc.default.MABC"));
+        assert!(table.contains("12 bytes"));
+    }
+}
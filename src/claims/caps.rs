@@ -0,0 +1,316 @@
+//! Known capability contracts, used to catch typos in capability IDs when signing.
+//! The standard wascap contracts are always known, and user-defined contracts are read
+//! from the `[capabilities]` table of a file, by default $HOME/.wash/capabilities.toml, e.g.
+//!
+//! ```toml
+//! [capabilities]
+//! "acme:payments" = "Payments"
+//! ```
+//!
+use crate::util::{configure_table_style, Output, OutputKind};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use term_table::{
+    row::Row,
+    table_cell::{Alignment, TableCell},
+    Table,
+};
+use wascap::caps::*;
+
+const CAPS_FILE: &str = ".wash/capabilities.toml";
+
+const STANDARD_CAPS: [&str; 8] = [
+    MESSAGING,
+    KEY_VALUE,
+    HTTP_SERVER,
+    HTTP_CLIENT,
+    BLOB,
+    EVENTSTREAMS,
+    EXTRAS,
+    LOGGING,
+];
+
+#[derive(Debug, Clone, StructOpt)]
+pub(crate) enum CapsCommand {
+    /// List the known capability contracts
+    #[structopt(name = "list")]
+    List(ListCapsCommand),
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub(crate) struct ListCapsCommand {
+    /// File defining additional capability contracts. Defaults to $WASH_CAPS ($HOME/.wash/capabilities.toml)
+    #[structopt(long = "caps-file", env = "WASH_CAPS", hide_env_values = true)]
+    pub(crate) caps_file: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pub(crate) output: Output,
+}
+
+/// top-level data structure for a capabilities file
+#[derive(Deserialize, Debug, Default)]
+struct CapsFile {
+    #[serde(default)]
+    capabilities: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Contract {
+    name: String,
+    /// the file that defined the contract, or None for standard contracts
+    source: Option<PathBuf>,
+}
+
+/// The capability contracts known when signing
+#[derive(Debug, Default)]
+pub(crate) struct CapabilityRegistry {
+    contracts: BTreeMap<String, Contract>,
+}
+
+impl CapabilityRegistry {
+    /// Loads the standard contracts and those defined in the capabilities file.
+    /// The default file is optional, but a file that was given must exist
+    pub(crate) fn load(caps_file: Option<PathBuf>) -> Result<Self, Box<dyn ::std::error::Error>> {
+        let mut registry = CapabilityRegistry::default();
+        for cap in STANDARD_CAPS.iter() {
+            registry.contracts.insert(
+                cap.to_string(),
+                Contract {
+                    name: capability_name(cap),
+                    source: None,
+                },
+            );
+        }
+        match caps_file {
+            Some(path) => registry.add_file(&path)?,
+            None => {
+                if let Some(path) = dirs::home_dir().map(|home| home.join(CAPS_FILE)) {
+                    if path.is_file() {
+                        registry.add_file(&path)?;
+                    }
+                }
+            }
+        }
+        Ok(registry)
+    }
+
+    fn add_file(&mut self, path: &Path) -> Result<(), Box<dyn ::std::error::Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read capabilities file {}: {}", path.display(), e))?;
+        let file: CapsFile = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid capabilities file {}: {}", path.display(), e))?;
+        for (id, name) in file.capabilities {
+            if !is_contract_id(&id) {
+                return Err(format!(
+                    "Invalid capability contract ID '{}' in {}, expected <namespace>:<name>",
+                    id,
+                    path.display()
+                )
+                .into());
+            }
+            self.contracts.insert(
+                id,
+                Contract {
+                    name,
+                    source: Some(path.to_path_buf()),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    pub(crate) fn contains(&self, cap: &str) -> bool {
+        self.contracts.contains_key(cap)
+    }
+
+    /// The known contract closest to an unknown capability ID, if it's close enough to be a typo
+    pub(crate) fn suggest(&self, cap: &str) -> Option<&str> {
+        self.contracts
+            .keys()
+            .map(|known| (edit_distance(cap, known), known))
+            .filter(|(distance, _)| *distance <= 3)
+            .min()
+            .map(|(_, known)| known.as_str())
+    }
+
+    /// Reports capability IDs that aren't known contracts, failing if `deny_unknown`
+    /// is set, or printing a warning otherwise
+    pub(crate) fn check(
+        &self,
+        caps: &[String],
+        deny_unknown: bool,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        let unknown = caps
+            .iter()
+            .filter(|cap| !self.contains(cap))
+            .map(|cap| match self.suggest(cap) {
+                Some(known) => format!("'{}' (did you mean '{}'?)", cap, known),
+                None => format!("'{}'", cap),
+            })
+            .collect::<Vec<_>>();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        let message = format!(
+            "Unknown capability contract ID {}. Add custom contracts to the capabilities file, see `wash claims caps list`",
+            unknown.join(", ")
+        );
+        if deny_unknown {
+            Err(message.into())
+        } else {
+            eprintln!("Warning: {}", message);
+            Ok(())
+        }
+    }
+}
+
+pub(crate) fn handle_caps(cmd: CapsCommand) -> Result<String, Box<dyn ::std::error::Error>> {
+    match cmd {
+        CapsCommand::List(cmd) => {
+            let registry = CapabilityRegistry::load(cmd.caps_file)?;
+            Ok(list_output(&registry, &cmd.output.kind))
+        }
+    }
+}
+
+fn list_output(registry: &CapabilityRegistry, output_kind: &OutputKind) -> String {
+    match *output_kind {
+        OutputKind::Json => {
+            let contracts = registry
+                .contracts
+                .iter()
+                .map(|(id, contract)| {
+                    json!({
+                        "id": id,
+                        "name": contract.name,
+                        "source": contract.source,
+                    })
+                })
+                .collect::<Vec<_>>();
+            format!("{}", json!({ "capabilities": contracts }))
+        }
+        OutputKind::Text => {
+            let mut table = Table::new();
+            configure_table_style(&mut table);
+            table.add_row(Row::new(vec![
+                TableCell::new_with_alignment("Contract ID", 1, Alignment::Left),
+                TableCell::new_with_alignment("Name", 1, Alignment::Left),
+                TableCell::new_with_alignment("Source", 1, Alignment::Left),
+            ]));
+            for (id, contract) in registry.contracts.iter() {
+                table.add_row(Row::new(vec![
+                    TableCell::new_with_alignment(id, 1, Alignment::Left),
+                    TableCell::new_with_alignment(&contract.name, 1, Alignment::Left),
+                    TableCell::new_with_alignment(
+                        contract
+                            .source
+                            .as_ref()
+                            .map(|p| p.display().to_string())
+                            .unwrap_or_else(|| "standard".to_string()),
+                        1,
+                        Alignment::Left,
+                    ),
+                ]));
+            }
+            table.render()
+        }
+    }
+}
+
+/// Contract IDs are namespaced, e.g. wasmcloud:keyvalue
+fn is_contract_id(id: &str) -> bool {
+    match id.split_once(':') {
+        Some((namespace, name)) => !namespace.is_empty() && !name.is_empty(),
+        None => false,
+    }
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::claims::{ClaimsCli, ClaimsCliCommand};
+
+    #[test]
+    fn test_capability_registry() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("capabilities.toml");
+        fs::write(&path, "[capabilities]\n\"acme:payments\" = \"Payments\"\n")?;
+
+        let registry = CapabilityRegistry::load(Some(path.clone()))?;
+        assert!(registry.contains(KEY_VALUE));
+        assert!(registry.contains("acme:payments"));
+        assert!(!registry.contains("wasmcloud:keyvaule"));
+        assert_eq!(registry.suggest("wasmcloud:keyvaule"), Some(KEY_VALUE));
+        assert_eq!(registry.suggest("acme:paymnets"), Some("acme:payments"));
+        assert_eq!(registry.suggest("other:thing"), None);
+
+        let caps = vec![KEY_VALUE.to_string(), "acme:payments".to_string()];
+        assert!(registry.check(&caps, true).is_ok());
+        let typo = vec!["wasmcloud:keyvaule".to_string()];
+        let err = registry.check(&typo, true).unwrap_err().to_string();
+        assert!(err.contains("did you mean 'wasmcloud:keyvalue'"));
+        // unknown capabilities are only a warning by default
+        assert!(registry.check(&typo, false).is_ok());
+
+        let json: serde_json::Value =
+            serde_json::from_str(&list_output(&registry, &OutputKind::Json))?;
+        assert_eq!(json["capabilities"][0]["id"], "acme:payments");
+        assert_eq!(json["capabilities"][0]["name"], "Payments");
+        assert!(list_output(&registry, &OutputKind::Text).contains("standard"));
+
+        fs::write(&path, "[capabilities]\n\"payments\" = \"Payments\"\n")?;
+        assert!(CapabilityRegistry::load(Some(path)).is_err());
+        assert!(CapabilityRegistry::load(Some(dir.path().join("missing.toml"))).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("keyvalue", "keyvalue"), 0);
+        assert_eq!(edit_distance("keyvaule", "keyvalue"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    /// Enumerates all options and flags of the `claims caps list` command
+    /// to ensure command line arguments do not change between versions
+    fn test_claims_caps_list_comprehensive() {
+        let cmd = ClaimsCli::from_iter_safe(&[
+            "claims",
+            "caps",
+            "list",
+            "--caps-file",
+            "./capabilities.toml",
+            "--output",
+            "json",
+        ])
+        .unwrap();
+        match cmd.command {
+            ClaimsCliCommand::Caps(CapsCommand::List(cmd)) => {
+                assert_eq!(cmd.caps_file.unwrap(), PathBuf::from("./capabilities.toml"));
+                assert_eq!(cmd.output.kind, OutputKind::Json);
+            }
+            cmd => panic!("claims caps list constructed incorrect command {:?}", cmd),
+        }
+    }
+}
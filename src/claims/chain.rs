@@ -0,0 +1,508 @@
+use super::{decode_jwt, inspect_bytes, load_artifact, looks_like_jwt, InspectedClaims};
+use crate::util::{configure_table_style, CommandFailure, Output, OutputKind, RegistryOpts};
+use serde::Serialize;
+use serde_json::json;
+use structopt::StructOpt;
+use term_table::{
+    row::Row,
+    table_cell::{Alignment, TableCell},
+    Table,
+};
+use wascap::jwt::{validate_token, Account, Actor, CapabilityProvider, Operator, TokenValidation};
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) struct ChainCommand {
+    /// Path to signed actor module or provider archive, or OCI URL of either
+    pub(crate) module: String,
+
+    /// Operator JWT, or path to a file containing it
+    #[structopt(long = "operator")]
+    pub(crate) operator: String,
+
+    /// Account JWT, or path to a file containing it. Can be repeated, the account
+    /// that signed the module is found among them
+    #[structopt(long = "account", number_of_values = 1, required = true)]
+    pub(crate) accounts: Vec<String>,
+
+    /// Digest to verify artifact against (if OCI URL is provided for <module>)
+    #[structopt(short = "d", long = "digest")]
+    digest: Option<String>,
+
+    #[structopt(flatten)]
+    registry: RegistryOpts,
+
+    #[structopt(flatten)]
+    pub(crate) output: Output,
+}
+
+/// One signed entity in the chain, and whether it was signed by the entity above it
+#[derive(Debug, Clone, Serialize)]
+struct ChainLink {
+    role: &'static str,
+    name: String,
+    subject: String,
+    issuer: String,
+    passed: bool,
+    detail: String,
+}
+
+/// The claims of an entity in the chain that matter for verifying it
+struct Signed {
+    name: String,
+    subject: String,
+    issuer: String,
+    /// additional keys the entity allows to sign on its behalf
+    valid_signers: Vec<String>,
+    validation: TokenValidation,
+}
+
+pub(crate) async fn handle_chain(
+    cmd: ChainCommand,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let operator = match decode_jwt(&read_jwt(&cmd.operator)?)? {
+        InspectedClaims::Operator(token) => Signed {
+            validation: validate_token::<Operator>(&token.jwt)?,
+            name: token.claims.name(),
+            valid_signers: token
+                .claims
+                .metadata
+                .and_then(|md| md.valid_signers)
+                .unwrap_or_default(),
+            subject: token.claims.subject,
+            issuer: token.claims.issuer,
+        },
+        _ => return Err(format!("{} is not an operator JWT", cmd.operator).into()),
+    };
+    let mut accounts = Vec::new();
+    for account in cmd.accounts.iter() {
+        match decode_jwt(&read_jwt(account)?)? {
+            InspectedClaims::Account(token) => accounts.push(Signed {
+                validation: validate_token::<Account>(&token.jwt)?,
+                name: token.claims.name(),
+                valid_signers: token
+                    .claims
+                    .metadata
+                    .and_then(|md| md.valid_signers)
+                    .unwrap_or_default(),
+                subject: token.claims.subject,
+                issuer: token.claims.issuer,
+            }),
+            _ => return Err(format!("{} is not an account JWT", account).into()),
+        }
+    }
+
+    let bytes = load_artifact(
+        &cmd.module,
+        cmd.digest.clone(),
+        cmd.registry.allow_latest,
+        cmd.registry.user.clone(),
+        cmd.registry.password.clone(),
+        cmd.registry.insecure,
+    )
+    .await?;
+    let module = match inspect_bytes(&bytes)? {
+        Some(InspectedClaims::Actor(token)) => Signed {
+            validation: validate_token::<Actor>(&token.jwt)?,
+            name: token.claims.name(),
+            valid_signers: Vec::new(),
+            subject: token.claims.subject,
+            issuer: token.claims.issuer,
+        },
+        Some(InspectedClaims::Provider { token, .. }) => Signed {
+            validation: validate_token::<CapabilityProvider>(&token.jwt)?,
+            name: token.claims.name(),
+            valid_signers: Vec::new(),
+            subject: token.claims.subject,
+            issuer: token.claims.issuer,
+        },
+        _ => {
+            return Err(format!(
+                "{} is not a signed actor module or provider archive",
+                cmd.module
+            )
+            .into())
+        }
+    };
+
+    let chain = verify_chain(&operator, &accounts, &module);
+    let output = chain_output(&cmd.module, &chain, &cmd.output.kind);
+    if chain.iter().all(|link| link.passed) {
+        Ok(output)
+    } else {
+        Err(CommandFailure(output).into())
+    }
+}
+
+/// Reads a JWT from a file, or uses the argument itself if it is a JWT
+fn read_jwt(arg: &str) -> Result<String, Box<dyn ::std::error::Error>> {
+    match std::fs::read_to_string(arg) {
+        Ok(contents) => Ok(contents.trim().to_string()),
+        Err(_) if looks_like_jwt(arg) => Ok(arg.to_string()),
+        Err(e) => Err(format!("Unable to read JWT from {}: {}", arg, e).into()),
+    }
+}
+
+/// Verifies each link of the chain, from the operator down to the module
+fn verify_chain(operator: &Signed, accounts: &[Signed], module: &Signed) -> Vec<ChainLink> {
+    let mut chain = vec![link(
+        "Operator",
+        operator,
+        if operator.issuer == operator.subject {
+            Ok("self-signed".to_string())
+        } else {
+            Err("operator is not self-signed".to_string())
+        },
+    )];
+
+    match accounts
+        .iter()
+        .find(|account| signed_by(module, account).is_some())
+    {
+        Some(account) => {
+            chain.push(link(
+                "Account",
+                account,
+                signed_by(account, operator).ok_or_else(|| {
+                    format!(
+                        "issuer {} is not the operator or one of its signing keys",
+                        account.issuer
+                    )
+                }),
+            ));
+            chain.push(link(
+                "Module",
+                module,
+                Ok(signed_by(module, account).unwrap_or_default()),
+            ));
+        }
+        None => chain.push(link(
+            "Module",
+            module,
+            Err(format!(
+                "issuer {} does not match any of the accounts or their signing keys",
+                module.issuer
+            )),
+        )),
+    }
+    chain
+}
+
+/// Describes how the entity was signed by the parent, if it was signed by the parent's
+/// own key or one of its additional signing keys
+fn signed_by(entity: &Signed, parent: &Signed) -> Option<String> {
+    if entity.issuer == parent.subject {
+        Some(format!("signed by {}", parent.subject))
+    } else if parent.valid_signers.contains(&entity.issuer) {
+        Some(format!("signed by signing key {}", entity.issuer))
+    } else {
+        None
+    }
+}
+
+fn link(role: &'static str, entity: &Signed, issued: Result<String, String>) -> ChainLink {
+    let mut problems = Vec::new();
+    if !entity.validation.signature_valid {
+        problems.push("invalid signature".to_string());
+    }
+    if entity.validation.expired {
+        problems.push(format!("expired {}", entity.validation.expires_human));
+    }
+    if entity.validation.cannot_use_yet {
+        problems.push(format!(
+            "cannot be used until {}",
+            entity.validation.not_before_human
+        ));
+    }
+    let detail = match issued {
+        Ok(detail) => detail,
+        Err(problem) => {
+            problems.insert(0, problem);
+            String::new()
+        }
+    };
+    ChainLink {
+        role,
+        name: entity.name.clone(),
+        subject: entity.subject.clone(),
+        issuer: entity.issuer.clone(),
+        passed: problems.is_empty(),
+        detail: if problems.is_empty() {
+            detail
+        } else {
+            problems.join(", ")
+        },
+    }
+}
+
+fn chain_output(module: &str, chain: &[ChainLink], output_kind: &OutputKind) -> String {
+    let passed = chain.iter().all(|link| link.passed);
+    match *output_kind {
+        OutputKind::Json => format!(
+            "{}",
+            json!({
+                "module": module,
+                "passed": passed,
+                "chain": chain,
+            })
+        ),
+        OutputKind::Text => {
+            let mut table = Table::new();
+            configure_table_style(&mut table);
+            table.add_row(Row::new(vec![TableCell::new_with_alignment(
+                format!("Signing chain of {}", module),
+                4,
+                Alignment::Center,
+            )]));
+            for link in chain {
+                table.add_row(Row::new(vec![
+                    TableCell::new(link.role),
+                    TableCell::new(&link.name),
+                    TableCell::new(&link.subject),
+                    TableCell::new(if link.passed { "PASS" } else { "FAIL" }),
+                ]));
+                table.add_row(Row::new(vec![
+                    TableCell::new(""),
+                    TableCell::new_with_alignment(&link.detail, 3, Alignment::Left),
+                ]));
+            }
+            format!(
+                "{}\nSigning chain {}",
+                table.render(),
+                if passed { "verified" } else { "is invalid" }
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::claims::{ClaimsCli, ClaimsCliCommand};
+
+    const OPERATOR: &str = "OCEIXXH3HP44U55QHPS4HJQ6GUOOAA6LG66CBRE7TET346MIJGVW2CXC";
+    const OPERATOR_SIGNER: &str = "OBFXHV2HGB4ESUL7XWMOHWSAYEEA2VB6VJEMLB73XH3MNBKRRJQXY6YN";
+    const ACCOUNT: &str = "ACOJJN6WUP4ODD75XEBKKTCCUJJCY5ZKQ56XVKYK4BEJWGVAOOQHZMCW";
+    const ACCOUNT_SIGNER: &str = "AAB4NUVOIJQ4G7FDLL3MNYQ7PCAQTEYQNC52IF2PPCKH4APRBKXM5JSM";
+    const MODULE: &str = "MDPDJEYIAK6MACO67PRFGOSSLODBISK4SCEYDY3HEOY4P5CVJN6UCWUK";
+
+    fn signed(subject: &str, issuer: &str, valid_signers: &[&str]) -> Signed {
+        Signed {
+            name: "test".to_string(),
+            subject: subject.to_string(),
+            issuer: issuer.to_string(),
+            valid_signers: valid_signers.iter().map(|s| s.to_string()).collect(),
+            validation: TokenValidation {
+                expired: false,
+                cannot_use_yet: false,
+                expires_human: "never".to_string(),
+                not_before_human: "immediately".to_string(),
+                signature_valid: true,
+            },
+        }
+    }
+
+    fn results(chain: &[ChainLink]) -> Vec<(&str, bool)> {
+        chain.iter().map(|link| (link.role, link.passed)).collect()
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let operator = signed(OPERATOR, OPERATOR, &[OPERATOR_SIGNER]);
+        let accounts = vec![
+            signed(ACCOUNT_SIGNER, OPERATOR, &[]),
+            signed(ACCOUNT, OPERATOR, &[ACCOUNT_SIGNER]),
+        ];
+
+        let chain = verify_chain(&operator, &accounts, &signed(MODULE, ACCOUNT, &[]));
+        assert_eq!(
+            results(&chain),
+            vec![("Operator", true), ("Account", true), ("Module", true)]
+        );
+        assert_eq!(chain[1].subject, ACCOUNT);
+
+        // signed with the additional keys of the account and the operator
+        let accounts = vec![signed(ACCOUNT, OPERATOR_SIGNER, &[ACCOUNT_SIGNER])];
+        let chain = verify_chain(&operator, &accounts, &signed(MODULE, ACCOUNT_SIGNER, &[]));
+        assert_eq!(
+            results(&chain),
+            vec![("Operator", true), ("Account", true), ("Module", true)]
+        );
+        assert_eq!(
+            chain[2].detail,
+            format!("signed by signing key {}", ACCOUNT_SIGNER)
+        );
+    }
+
+    #[test]
+    fn test_verify_broken_chain() {
+        let operator = signed(OPERATOR, OPERATOR, &[]);
+        let module = signed(MODULE, ACCOUNT, &[]);
+
+        // the account wasn't signed by this operator
+        let accounts = vec![signed(ACCOUNT, OPERATOR_SIGNER, &[])];
+        assert_eq!(
+            results(&verify_chain(&operator, &accounts, &module)),
+            vec![("Operator", true), ("Account", false), ("Module", true)]
+        );
+
+        // no account signed the module
+        let accounts = vec![signed(ACCOUNT_SIGNER, OPERATOR, &[])];
+        assert_eq!(
+            results(&verify_chain(&operator, &accounts, &module)),
+            vec![("Operator", true), ("Module", false)]
+        );
+
+        let mut expired = signed(ACCOUNT, OPERATOR, &[]);
+        expired.validation.expired = true;
+        expired.validation.expires_human = "2 days ago".to_string();
+        let chain = verify_chain(&operator, &[expired], &module);
+        assert_eq!(chain[1].detail, "expired 2 days ago");
+
+        let not_self_signed = signed(OPERATOR, OPERATOR_SIGNER, &[]);
+        let chain = verify_chain(&not_self_signed, &[], &module);
+        assert!(!chain[0].passed);
+        assert!(chain_output("echo.wasm", &chain, &OutputKind::Text).ends_with("is invalid"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_chain() -> Result<(), Box<dyn ::std::error::Error>> {
+        use nkeys::KeyPair;
+        use wascap::jwt::Claims;
+
+        let operator = KeyPair::new_operator();
+        let operator_signer = KeyPair::new_operator();
+        let account = KeyPair::new_account();
+        let operator_token = Claims::<Operator>::new(
+            "Ops".to_string(),
+            operator.public_key(),
+            operator.public_key(),
+            vec![operator_signer.public_key()],
+        )
+        .encode(&operator)?;
+        // the account is signed with the operator's additional signing key
+        let account_token = Claims::<Account>::new(
+            "Acme".to_string(),
+            operator_signer.public_key(),
+            account.public_key(),
+            vec![],
+        )
+        .encode(&operator_signer)?;
+
+        let dir = tempfile::tempdir()?;
+        let module = dir.path().join("echo_s.wasm");
+        std::fs::write(
+            &module,
+            wascap::wasm::sign_buffer_with_claims(
+                "Echo".to_string(),
+                b"\0asm\x01\0\0\0",
+                KeyPair::new_module(),
+                KeyPair::from_seed(&account.seed()?)?,
+                None,
+                None,
+                vec![],
+                vec![],
+                false,
+                None,
+                None,
+                None,
+            )?,
+        )?;
+        let chain_cmd = |operator_token: &str| {
+            let module = module.to_str().unwrap();
+            match ClaimsCli::from_iter_safe(&[
+                "claims",
+                "chain",
+                module,
+                "--operator",
+                operator_token,
+                "--account",
+                &account_token,
+                "--output",
+                "json",
+            ])
+            .unwrap()
+            .command
+            {
+                ClaimsCliCommand::Chain(cmd) => cmd,
+                cmd => panic!("claims chain constructed incorrect command {:?}", cmd),
+            }
+        };
+
+        let output: serde_json::Value =
+            serde_json::from_str(&handle_chain(chain_cmd(&operator_token)).await?)?;
+        assert_eq!(output["passed"], true);
+        assert_eq!(output["chain"][1]["subject"], account.public_key());
+        assert_eq!(
+            output["chain"][1]["detail"],
+            format!("signed by signing key {}", operator_signer.public_key())
+        );
+
+        // without the signing key in its token, the operator didn't sign the account
+        let other_operator = Claims::<Operator>::new(
+            "Ops".to_string(),
+            operator.public_key(),
+            operator.public_key(),
+            vec![],
+        )
+        .encode(&operator)?;
+        let err = handle_chain(chain_cmd(&other_operator)).await.unwrap_err();
+        let output: serde_json::Value = serde_json::from_str(&err.to_string())?;
+        assert_eq!(output["passed"], false);
+        assert_eq!(output["chain"][1]["passed"], false);
+        Ok(())
+    }
+
+    #[test]
+    /// Enumerates all options and flags of the `claims chain` command
+    /// to ensure command line arguments do not change between versions
+    fn test_claims_chain_comprehensive() {
+        let cmd = ClaimsCli::from_iter_safe(&[
+            "claims",
+            "chain",
+            "echo.wasm",
+            "--operator",
+            "operator.jwt",
+            "--account",
+            "account.jwt",
+            "--account",
+            "other_account.jwt",
+            "--digest",
+            "sha256:5790f650cff526fcbc1271107a05111a6647002098b74a9a5e2e26e3c0a116b8",
+            "--allow-latest",
+            "--user",
+            "name",
+            "--password",
+            "opensesame",
+            "--insecure",
+            "--output",
+            "json",
+        ])
+        .unwrap();
+        match cmd.command {
+            ClaimsCliCommand::Chain(cmd) => {
+                assert_eq!(cmd.module, "echo.wasm");
+                assert_eq!(cmd.operator, "operator.jwt");
+                assert_eq!(cmd.accounts, vec!["account.jwt", "other_account.jwt"]);
+                assert_eq!(
+                    cmd.digest.unwrap(),
+                    "sha256:5790f650cff526fcbc1271107a05111a6647002098b74a9a5e2e26e3c0a116b8"
+                );
+                assert!(cmd.registry.allow_latest);
+                assert_eq!(cmd.registry.user.unwrap(), "name");
+                assert_eq!(cmd.registry.password.unwrap(), "opensesame");
+                assert!(cmd.registry.insecure);
+                assert_eq!(cmd.output.kind, OutputKind::Json);
+            }
+            cmd => panic!("claims chain constructed incorrect command {:?}", cmd),
+        }
+        // an account is required
+        assert!(ClaimsCli::from_iter_safe(&[
+            "claims",
+            "chain",
+            "echo.wasm",
+            "--operator",
+            "operator.jwt"
+        ])
+        .is_err());
+    }
+}
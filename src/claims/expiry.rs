@@ -0,0 +1,408 @@
+use super::{inspect_bytes, InspectedClaims};
+use crate::util::{configure_table_style, CommandFailure, Output, OutputKind, RegistryOpts};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
+use term_table::{
+    row::Row,
+    table_cell::{Alignment, TableCell},
+    Table,
+};
+use walkdir::WalkDir;
+use wascap::jwt::{Claims, WascapEntity};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) struct ExpiryCommand {
+    /// Signed modules, provider archives, or JWTs to check, directories to scan for
+    /// modules (.wasm) and provider archives (.par, .par.gz), or OCI URLs
+    #[structopt(required = true)]
+    pub(crate) targets: Vec<String>,
+
+    /// Report artifacts that expire within this many days
+    #[structopt(short = "w", long = "within", default_value = "30")]
+    pub(crate) within_days: u64,
+
+    /// Scan directories recursively
+    #[structopt(short = "r", long = "recursive")]
+    pub(crate) recursive: bool,
+
+    #[structopt(flatten)]
+    registry: RegistryOpts,
+
+    #[structopt(flatten)]
+    pub(crate) output: Output,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ExpiryStatus {
+    Valid,
+    Expiring,
+    Expired,
+    /// the artifact couldn't be read or has no claims
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ExpiryEntry {
+    artifact: String,
+    status: ExpiryStatus,
+    subject: Option<String>,
+    name: Option<String>,
+    /// expiry in seconds since the epoch, or None if the claims never expire
+    expires_at: Option<u64>,
+    /// human-friendly relative expiry, e.g. "in 3 days"
+    expires: Option<String>,
+    error: Option<String>,
+}
+
+impl ExpiryEntry {
+    fn error(artifact: &str, error: impl ToString) -> Self {
+        ExpiryEntry {
+            artifact: artifact.to_string(),
+            status: ExpiryStatus::Error,
+            subject: None,
+            name: None,
+            expires_at: None,
+            expires: None,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+pub(crate) async fn handle_expiry(
+    cmd: ExpiryCommand,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let window = cmd.within_days.saturating_mul(SECONDS_PER_DAY);
+
+    let mut entries = Vec::new();
+    for target in cmd.targets.iter() {
+        let path = Path::new(target);
+        if path.is_dir() {
+            for file in scan_dir(path, cmd.recursive) {
+                let artifact = file.display().to_string();
+                match std::fs::read(&file) {
+                    // unsigned modules found while scanning aren't reported
+                    Ok(bytes) => match inspect_bytes(&bytes) {
+                        Ok(Some(claims)) => entries.push(entry(&artifact, claims, now, window)),
+                        Ok(None) => {}
+                        Err(e) => entries.push(ExpiryEntry::error(&artifact, e)),
+                    },
+                    Err(e) => entries.push(ExpiryEntry::error(&artifact, e)),
+                }
+            }
+        } else {
+            let bytes = if path.is_file() {
+                std::fs::read(path).map_err(|e| e.to_string())
+            } else {
+                crate::reg::pull_artifact(
+                    target.to_string(),
+                    None,
+                    cmd.registry.allow_latest,
+                    cmd.registry.user.clone(),
+                    cmd.registry.password.clone(),
+                    cmd.registry.insecure,
+                )
+                .await
+                .map_err(|e| format!("Error pulling artifact: {}", e))
+            };
+            entries.push(
+                match bytes.map(|bytes| inspect_bytes(&bytes).map_err(|e| e.to_string())) {
+                    Ok(Ok(Some(claims))) => entry(target, claims, now, window),
+                    Ok(Ok(None)) => ExpiryEntry::error(target, "No claims found"),
+                    Ok(Err(e)) | Err(e) => ExpiryEntry::error(target, e),
+                },
+            );
+        }
+    }
+
+    let output = expiry_output(&entries, cmd.within_days, &cmd.output.kind);
+    if entries.iter().all(|e| e.status == ExpiryStatus::Valid) {
+        Ok(output)
+    } else {
+        Err(CommandFailure(output).into())
+    }
+}
+
+/// Modules and provider archives in the directory, in a stable order
+fn scan_dir(dir: &Path, recursive: bool) -> Vec<std::path::PathBuf> {
+    WalkDir::new(dir)
+        .max_depth(if recursive { usize::MAX } else { 1 })
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| {
+            let name = p.to_string_lossy();
+            name.ends_with(".wasm") || name.ends_with(".par") || name.ends_with(".par.gz")
+        })
+        .collect()
+}
+
+fn entry(artifact: &str, claims: InspectedClaims, now: u64, window: u64) -> ExpiryEntry {
+    fn details<T>(claims: &Claims<T>) -> (String, String, Option<u64>)
+    where
+        T: Serialize + DeserializeOwned + WascapEntity,
+    {
+        (claims.subject.clone(), claims.name(), claims.expires)
+    }
+    let (subject, name, expires_at) = match claims {
+        InspectedClaims::Actor(ref token) => details(&token.claims),
+        InspectedClaims::Provider { ref token, .. } => details(&token.claims),
+        InspectedClaims::Account(ref token) => details(&token.claims),
+        InspectedClaims::Operator(ref token) => details(&token.claims),
+    };
+    ExpiryEntry {
+        artifact: artifact.to_string(),
+        status: expiry_status(expires_at, now, window),
+        subject: Some(subject),
+        name: Some(name),
+        expires_at,
+        expires: Some(describe_expiry(expires_at, now)),
+        error: None,
+    }
+}
+
+fn expiry_status(expires_at: Option<u64>, now: u64, window: u64) -> ExpiryStatus {
+    match expires_at {
+        Some(exp) if exp <= now => ExpiryStatus::Expired,
+        Some(exp) if exp - now <= window => ExpiryStatus::Expiring,
+        _ => ExpiryStatus::Valid,
+    }
+}
+
+fn describe_expiry(expires_at: Option<u64>, now: u64) -> String {
+    let days = |secs: u64| match secs / SECONDS_PER_DAY {
+        0 => "less than a day".to_string(),
+        1 => "1 day".to_string(),
+        n => format!("{} days", n),
+    };
+    match expires_at {
+        None => "never".to_string(),
+        Some(exp) if exp <= now => format!("{} ago", days(now - exp)),
+        Some(exp) => format!("in {}", days(exp - now)),
+    }
+}
+
+fn expiry_output(entries: &[ExpiryEntry], within_days: u64, output_kind: &OutputKind) -> String {
+    let count = |status| entries.iter().filter(|e| e.status == status).count();
+    let summary = format!(
+        "Checked {} artifacts: {} expired, {} expiring within {} days, {} errors",
+        entries.len(),
+        count(ExpiryStatus::Expired),
+        count(ExpiryStatus::Expiring),
+        within_days,
+        count(ExpiryStatus::Error),
+    );
+    match *output_kind {
+        OutputKind::Json => format!(
+            "{}",
+            json!({
+                "within_days": within_days,
+                "passed": entries.iter().all(|e| e.status == ExpiryStatus::Valid),
+                "artifacts": entries,
+            })
+        ),
+        OutputKind::Text => {
+            let reported = entries
+                .iter()
+                .filter(|e| e.status != ExpiryStatus::Valid)
+                .collect::<Vec<_>>();
+            if reported.is_empty() {
+                return summary;
+            }
+            let mut table = Table::new();
+            configure_table_style(&mut table);
+            table.add_row(Row::new(vec![
+                TableCell::new_with_alignment("Artifact", 1, Alignment::Left),
+                TableCell::new_with_alignment("Name", 1, Alignment::Left),
+                TableCell::new_with_alignment("Status", 1, Alignment::Left),
+                TableCell::new_with_alignment("Expires", 1, Alignment::Left),
+            ]));
+            for e in reported {
+                let status = match e.status {
+                    ExpiryStatus::Expired => "EXPIRED",
+                    ExpiryStatus::Expiring => "EXPIRING",
+                    ExpiryStatus::Error => "ERROR",
+                    ExpiryStatus::Valid => "VALID",
+                };
+                table.add_row(Row::new(vec![
+                    TableCell::new_with_alignment(&e.artifact, 1, Alignment::Left),
+                    TableCell::new_with_alignment(
+                        e.name.as_deref().unwrap_or("N/A"),
+                        1,
+                        Alignment::Left,
+                    ),
+                    TableCell::new_with_alignment(status, 1, Alignment::Left),
+                    TableCell::new_with_alignment(
+                        e.error
+                            .as_deref()
+                            .or(e.expires.as_deref())
+                            .unwrap_or_default(),
+                        1,
+                        Alignment::Left,
+                    ),
+                ]));
+            }
+            format!("{}\n{}", table.render(), summary)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::claims::{ClaimsCli, ClaimsCliCommand};
+    use wascap::prelude::KeyPair;
+
+    const NOW: u64 = 1_600_000_000;
+    const WINDOW: u64 = 30 * SECONDS_PER_DAY;
+
+    fn signed_module(expires_in_days: Option<u64>) -> Vec<u8> {
+        wascap::wasm::sign_buffer_with_claims(
+            "Test".to_string(),
+            b"\0asm\x01\0\0\0",
+            KeyPair::new_module(),
+            KeyPair::new_account(),
+            expires_in_days,
+            None,
+            vec![],
+            vec![],
+            false,
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_expiry_status() {
+        assert_eq!(expiry_status(None, NOW, WINDOW), ExpiryStatus::Valid);
+        assert_eq!(
+            expiry_status(Some(NOW - 1), NOW, WINDOW),
+            ExpiryStatus::Expired
+        );
+        assert_eq!(expiry_status(Some(NOW), NOW, WINDOW), ExpiryStatus::Expired);
+        assert_eq!(
+            expiry_status(Some(NOW + WINDOW), NOW, WINDOW),
+            ExpiryStatus::Expiring
+        );
+        assert_eq!(
+            expiry_status(Some(NOW + WINDOW + 1), NOW, WINDOW),
+            ExpiryStatus::Valid
+        );
+
+        assert_eq!(describe_expiry(None, NOW), "never");
+        assert_eq!(
+            describe_expiry(Some(NOW + 3 * SECONDS_PER_DAY), NOW),
+            "in 3 days"
+        );
+        assert_eq!(
+            describe_expiry(Some(NOW - SECONDS_PER_DAY), NOW),
+            "1 day ago"
+        );
+        assert_eq!(describe_expiry(Some(NOW + 60), NOW), "in less than a day");
+    }
+
+    #[test]
+    fn test_scan_and_report() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir(dir.path().join("nested"))?;
+        std::fs::write(dir.path().join("expiring.wasm"), signed_module(Some(5)))?;
+        std::fs::write(dir.path().join("forever.wasm"), signed_module(None))?;
+        std::fs::write(dir.path().join("notes.txt"), "not an artifact")?;
+        std::fs::write(
+            dir.path().join("nested").join("later.wasm"),
+            signed_module(Some(90)),
+        )?;
+
+        let files = scan_dir(dir.path(), false);
+        assert_eq!(files.len(), 2);
+        assert_eq!(scan_dir(dir.path(), true).len(), 3);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let entries = scan_dir(dir.path(), true)
+            .into_iter()
+            .map(|file| {
+                let claims = inspect_bytes(&std::fs::read(&file)?)?.unwrap();
+                Ok(entry(&file.display().to_string(), claims, now, WINDOW))
+            })
+            .collect::<Result<Vec<_>, Box<dyn ::std::error::Error>>>()?;
+        let statuses = entries.iter().map(|e| e.status).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                ExpiryStatus::Expiring,
+                ExpiryStatus::Valid,
+                ExpiryStatus::Valid
+            ]
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&expiry_output(&entries, 30, &OutputKind::Json))?;
+        assert_eq!(json["passed"], false);
+        assert_eq!(json["artifacts"][0]["status"], "expiring");
+        assert_eq!(json["artifacts"][0]["name"], "Test");
+        assert!(json["artifacts"][1]["expires_at"].is_null());
+
+        let text = expiry_output(&entries, 30, &OutputKind::Text);
+        assert!(text.contains("expiring.wasm"));
+        assert!(!text.contains("forever.wasm"));
+        assert!(text.ends_with("1 expiring within 30 days, 0 errors"));
+        Ok(())
+    }
+
+    #[test]
+    /// Enumerates all options and flags of the `claims expiry` command
+    /// to ensure command line arguments do not change between versions
+    fn test_claims_expiry_comprehensive() {
+        let cmd = ClaimsCli::from_iter_safe(&[
+            "claims",
+            "expiry",
+            "./build",
+            "wasmcloud.azurecr.io/echo:0.3.2",
+            "--within",
+            "14",
+            "--recursive",
+            "--allow-latest",
+            "--user",
+            "name",
+            "--password",
+            "opensesame",
+            "--insecure",
+            "--output",
+            "json",
+        ])
+        .unwrap();
+        match cmd.command {
+            ClaimsCliCommand::Expiry(cmd) => {
+                assert_eq!(
+                    cmd.targets,
+                    vec!["./build", "wasmcloud.azurecr.io/echo:0.3.2"]
+                );
+                assert_eq!(cmd.within_days, 14);
+                assert!(cmd.recursive);
+                assert!(cmd.registry.allow_latest);
+                assert_eq!(cmd.registry.user.unwrap(), "name");
+                assert_eq!(cmd.registry.password.unwrap(), "opensesame");
+                assert!(cmd.registry.insecure);
+                assert_eq!(cmd.output.kind, OutputKind::Json);
+            }
+            cmd => panic!("claims expiry constructed incorrect command {:?}", cmd),
+        }
+        let cmd = ClaimsCli::from_iter_safe(&["claims", "expiry", "./build"]).unwrap();
+        match cmd.command {
+            ClaimsCliCommand::Expiry(cmd) => assert_eq!(cmd.within_days, 30),
+            cmd => panic!("claims expiry constructed incorrect command {:?}", cmd),
+        }
+        assert!(ClaimsCli::from_iter_safe(&["claims", "expiry"]).is_err());
+    }
+}
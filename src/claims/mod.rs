@@ -13,8 +13,10 @@
 // limitations under the License.
 
 use crate::keys::extract_keypair;
+use crate::signer::extract_signer;
 use crate::util::{format_output, Output, OutputKind};
 use nkeys::{KeyPair, KeyPairType};
+use provider_archive::ProviderArchive;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -34,7 +36,21 @@ use wascap::caps::*;
 use wascap::jwt::{
     Account, Actor, CapabilityProvider, Claims, Operator, Token, TokenValidation, WascapEntity,
 };
-use wascap::wasm::{days_from_now_to_jwt_time, sign_buffer_with_claims};
+use wascap::wasm::{days_from_now_to_jwt_time, extract_claims, sign_buffer_with_claims};
+
+mod caps;
+mod chain;
+mod expiry;
+mod profile;
+mod strip;
+mod update;
+mod verify;
+use caps::{CapabilityRegistry, CapsCommand};
+use chain::ChainCommand;
+use expiry::ExpiryCommand;
+use strip::{ExtractCommand, StripCommand};
+use update::UpdateCommand;
+use verify::VerifyCommand;
 
 #[derive(Debug, StructOpt, Clone)]
 #[structopt(
@@ -53,21 +69,47 @@ impl ClaimsCli {
 
 #[derive(Debug, Clone, StructOpt)]
 pub(crate) enum ClaimsCliCommand {
-    /// Examine the capabilities of a WebAssembly module
+    /// Examine the claims of a WebAssembly module, provider archive, or JWT
     #[structopt(name = "inspect")]
     Inspect(InspectCommand),
     /// Sign a WebAssembly module, specifying capabilities and other claims
     /// including expiration, tags, and additional metadata
     #[structopt(name = "sign")]
     Sign(SignCommand),
+    /// Update the claims of a signed WebAssembly module and re-sign it, preserving
+    /// all claims that aren't changed
+    #[structopt(name = "update")]
+    Update(UpdateCommand),
+    /// Remove the embedded claims from a signed WebAssembly module, producing an unsigned module
+    #[structopt(name = "strip")]
+    Strip(StripCommand),
+    /// Write the claims JWT of a signed module or provider archive to a separate file
+    #[structopt(name = "extract")]
+    Extract(ExtractCommand),
     /// Generate a signed JWT by supplying basic token information, a signing seed key, and metadata
     #[structopt(name = "token")]
     Token(TokenCommand),
+    /// Verify the signature, integrity, validity period, issuer, and capabilities of a
+    /// signed module or provider archive. Exits with a non-zero status if any check fails
+    #[structopt(name = "verify")]
+    Verify(VerifyCommand),
+    /// Verify that a signed module or provider archive was signed by an account, and that
+    /// account by an operator, directly or through their additional signing keys
+    #[structopt(name = "chain")]
+    Chain(ChainCommand),
+    /// Manage the known capability contracts, used to check capability IDs when signing
+    #[structopt(name = "caps")]
+    Caps(CapsCommand),
+    /// Report signed modules, provider archives, and JWTs that have expired or expire soon.
+    /// Exits with a non-zero status if any are found
+    #[structopt(name = "expiry")]
+    Expiry(ExpiryCommand),
 }
 
 #[derive(StructOpt, Debug, Clone)]
 pub(crate) struct InspectCommand {
-    /// Path to signed actor module or OCI URL of signed actor module
+    /// Path to a signed actor module, provider archive (.par or .par.gz), or JWT file,
+    /// an OCI URL of a signed module or archive, or a raw JWT
     pub(crate) module: String,
 
     /// Extract the raw JWT from the file and print to stdout
@@ -155,6 +197,11 @@ struct GenerateCommon {
     #[structopt(long = "disable-keygen")]
     disable_keygen: bool,
 
+    /// External signer holding the issuer key, either exec:<command> or agent:<socket path>.
+    /// Used instead of the issuer seed, which then never needs to be on disk
+    #[structopt(long = "signer", env = "WASH_SIGNER", hide_env_values = true)]
+    signer: Option<String>,
+
     #[structopt(flatten)]
     pub(crate) output: Output,
 }
@@ -286,9 +333,9 @@ pub(crate) struct ActorMetadata {
     /// Enable access to an append-only event stream provider
     #[structopt(short = "e", long = "events")]
     eventstream: bool,
-    /// A human-readable, descriptive name for the token
+    /// A human-readable, descriptive name for the token. Required, unless provided by the signing profile
     #[structopt(short = "n", long = "name")]
-    name: String,
+    name: Option<String>,
     /// Add custom capabilities
     #[structopt(short = "c", long = "cap", name = "capabilities")]
     custom_caps: Vec<String>,
@@ -326,6 +373,19 @@ pub(crate) struct ActorMetadata {
     )]
     subject: Option<String>,
 
+    /// Signing profile (TOML) whose [claims] table provides any claims not given as flags.
+    /// If this flag is not provided, wasmcloud.toml in the current directory is used if it exists
+    #[structopt(long = "profile")]
+    profile: Option<PathBuf>,
+
+    /// File defining additional capability contracts. Defaults to $WASH_CAPS ($HOME/.wash/capabilities.toml)
+    #[structopt(long = "caps-file", env = "WASH_CAPS", hide_env_values = true)]
+    caps_file: Option<PathBuf>,
+
+    /// Fail instead of warning when a capability ID isn't a known contract
+    #[structopt(long = "deny-unknown-caps")]
+    deny_unknown_caps: bool,
+
     #[structopt(flatten)]
     common: GenerateCommon,
 }
@@ -336,7 +396,14 @@ pub(crate) async fn handle_command(
     match command {
         ClaimsCliCommand::Inspect(inspectcmd) => render_caps(inspectcmd).await,
         ClaimsCliCommand::Sign(signcmd) => sign_file(signcmd),
+        ClaimsCliCommand::Update(updatecmd) => update::update_file(updatecmd),
+        ClaimsCliCommand::Strip(stripcmd) => strip::strip_file(stripcmd),
+        ClaimsCliCommand::Extract(extractcmd) => strip::extract_file(extractcmd),
         ClaimsCliCommand::Token(gencmd) => generate_token(gencmd),
+        ClaimsCliCommand::Verify(verifycmd) => verify::handle_verify(verifycmd).await,
+        ClaimsCliCommand::Chain(chaincmd) => chain::handle_chain(chaincmd).await,
+        ClaimsCliCommand::Caps(capscmd) => caps::handle_caps(capscmd),
+        ClaimsCliCommand::Expiry(expirycmd) => expiry::handle_expiry(expirycmd).await,
     }
 }
 
@@ -370,16 +437,19 @@ fn get_keypair_vec(
 }
 
 fn generate_actor(actor: ActorMetadata) -> Result<String, Box<dyn ::std::error::Error>> {
-    let issuer = extract_keypair(
+    let actor = actor.with_profile()?;
+    let name = actor.name()?;
+    let issuer = extract_signer(
+        actor.common.signer.clone(),
         actor.issuer.clone(),
-        Some(actor.name.clone()),
+        Some(name.clone()),
         actor.common.directory.clone(),
         KeyPairType::Account,
         actor.common.disable_keygen,
     )?;
     let subject = extract_keypair(
         actor.subject.clone(),
-        Some(actor.name.clone()),
+        Some(name.clone()),
         actor.common.directory.clone(),
         KeyPairType::Module,
         actor.common.disable_keygen,
@@ -411,12 +481,14 @@ fn generate_actor(actor: ActorMetadata) -> Result<String, Box<dyn ::std::error::
         caps_list.push(wascap::caps::EXTRAS.to_string());
     }
     caps_list.extend(actor.custom_caps.iter().cloned());
+    CapabilityRegistry::load(actor.caps_file.clone())?
+        .check(&caps_list, actor.deny_unknown_caps)?;
 
     if actor.provider && caps_list.len() > 1 {
         return Err("Capability providers cannot provide multiple capabilities at once.".into());
     }
     let claims: Claims<Actor> = Claims::<Actor>::with_dates(
-        actor.name.clone(),
+        name,
         issuer.public_key(),
         subject.public_key(),
         Some(caps_list),
@@ -429,7 +501,7 @@ fn generate_actor(actor: ActorMetadata) -> Result<String, Box<dyn ::std::error::
         sanitize_alias(actor.call_alias)?,
     );
 
-    let jwt = claims.encode(&issuer)?;
+    let jwt = issuer.encode(&claims)?;
     let out = format_output(
         jwt.clone(),
         json!({ "token": jwt }),
@@ -440,7 +512,8 @@ fn generate_actor(actor: ActorMetadata) -> Result<String, Box<dyn ::std::error::
 }
 
 fn generate_operator(operator: OperatorMetadata) -> Result<String, Box<dyn ::std::error::Error>> {
-    let self_sign_key = extract_keypair(
+    let self_sign_key = extract_signer(
+        operator.common.signer.clone(),
         operator.issuer.clone(),
         Some(operator.name.clone()),
         operator.common.directory.clone(),
@@ -471,7 +544,7 @@ fn generate_operator(operator: OperatorMetadata) -> Result<String, Box<dyn ::std
         },
     );
 
-    let jwt = claims.encode(&self_sign_key)?;
+    let jwt = self_sign_key.encode(&claims)?;
     let out = format_output(
         jwt.clone(),
         json!({ "token": jwt }),
@@ -481,7 +554,8 @@ fn generate_operator(operator: OperatorMetadata) -> Result<String, Box<dyn ::std
 }
 
 fn generate_account(account: AccountMetadata) -> Result<String, Box<dyn ::std::error::Error>> {
-    let issuer = extract_keypair(
+    let issuer = extract_signer(
+        account.common.signer.clone(),
         account.issuer.clone(),
         Some(account.name.clone()),
         account.common.directory.clone(),
@@ -517,7 +591,7 @@ fn generate_account(account: AccountMetadata) -> Result<String, Box<dyn ::std::e
             vec![]
         },
     );
-    let jwt = claims.encode(&issuer)?;
+    let jwt = issuer.encode(&claims)?;
     let out = format_output(
         jwt.clone(),
         json!({ "token": jwt }),
@@ -527,7 +601,8 @@ fn generate_account(account: AccountMetadata) -> Result<String, Box<dyn ::std::e
 }
 
 fn generate_provider(provider: ProviderMetadata) -> Result<String, Box<dyn ::std::error::Error>> {
-    let issuer = extract_keypair(
+    let issuer = extract_signer(
+        provider.common.signer.clone(),
         provider.issuer.clone(),
         Some(provider.name.clone()),
         provider.common.directory.clone(),
@@ -554,7 +629,7 @@ fn generate_provider(provider: ProviderMetadata) -> Result<String, Box<dyn ::std
         days_from_now_to_jwt_time(provider.common.not_before_days),
         days_from_now_to_jwt_time(provider.common.expires_in_days),
     );
-    let jwt = claims.encode(&issuer)?;
+    let jwt = issuer.encode(&claims)?;
     let out = format_output(
         jwt.clone(),
         json!({ "token": jwt }),
@@ -563,13 +638,15 @@ fn generate_provider(provider: ProviderMetadata) -> Result<String, Box<dyn ::std
     Ok(out)
 }
 
-fn sign_file(cmd: SignCommand) -> Result<String, Box<dyn ::std::error::Error>> {
+fn sign_file(mut cmd: SignCommand) -> Result<String, Box<dyn ::std::error::Error>> {
+    cmd.metadata = cmd.metadata.with_profile()?;
     let mut sfile = File::open(&cmd.source)
         .map_err(|e| format!("Failed to open file for signing '{}': {}", &cmd.source, e))?;
     let mut buf = Vec::new();
     sfile.read_to_end(&mut buf).unwrap();
 
-    let issuer = extract_keypair(
+    let issuer = extract_signer(
+        cmd.metadata.common.signer.clone(),
         cmd.metadata.issuer.clone(),
         Some(cmd.source.clone()),
         cmd.metadata.common.directory.clone(),
@@ -610,16 +687,18 @@ fn sign_file(cmd: SignCommand) -> Result<String, Box<dyn ::std::error::Error>> {
         caps_list.push(wascap::caps::EVENTSTREAMS.to_string());
     }
     caps_list.extend(cmd.metadata.custom_caps.iter().cloned());
+    CapabilityRegistry::load(cmd.metadata.caps_file.clone())?
+        .check(&caps_list, cmd.metadata.deny_unknown_caps)?;
 
     if cmd.metadata.provider && caps_list.len() > 1 {
         return Err("Capability providers cannot provide multiple capabilities at once.".into());
     }
 
-    let signed = sign_buffer_with_claims(
-        cmd.metadata.name.clone(),
+    let mut signed = sign_buffer_with_claims(
+        cmd.metadata.name()?,
         &buf,
         subject,
-        issuer,
+        issuer.keypair()?,
         cmd.metadata.common.expires_in_days,
         cmd.metadata.common.not_before_days,
         caps_list.clone(),
@@ -629,6 +708,12 @@ fn sign_file(cmd: SignCommand) -> Result<String, Box<dyn ::std::error::Error>> {
         cmd.metadata.ver.clone(),
         sanitize_alias(cmd.metadata.call_alias)?,
     )?;
+    if issuer.is_external() {
+        // wascap only signs with a local key, so the claims embedded with a throwaway key
+        // (which also computes the module hash) are signed again by the external signer
+        let token = extract_claims(&signed)?.ok_or("Failed to embed claims in module")?;
+        signed = strip::replace_claims(&signed, &issuer.resign::<Actor>(&token.jwt)?)?;
+    }
 
     let destination = match cmd.destination.clone() {
         Some(d) => d,
@@ -671,49 +756,207 @@ fn sign_file(cmd: SignCommand) -> Result<String, Box<dyn ::std::error::Error>> {
     Ok(output)
 }
 
-async fn get_caps(
+/// Claims found by `claims inspect`, for each kind of signed entity
+enum InspectedClaims {
+    Actor(Token<Actor>),
+    /// provider claims, with the targets of the archive they were read from (if any)
+    Provider {
+        token: Token<CapabilityProvider>,
+        targets: Vec<String>,
+    },
+    Account(Token<Account>),
+    Operator(Token<Operator>),
+}
+
+impl InspectedClaims {
+    fn jwt(&self) -> &str {
+        match self {
+            InspectedClaims::Actor(token) => &token.jwt,
+            InspectedClaims::Provider { token, .. } => &token.jwt,
+            InspectedClaims::Account(token) => &token.jwt,
+            InspectedClaims::Operator(token) => &token.jwt,
+        }
+    }
+}
+
+/// Reads the claims from a wasm module, provider archive, or JWT. The input may be
+/// a file, a JWT string, or the OCI URL of a module or archive
+async fn get_claims(
     cmd: &InspectCommand,
-) -> Result<Option<Token<Actor>>, Box<dyn ::std::error::Error>> {
-    let module_bytes = match File::open(&cmd.module) {
+) -> Result<Option<InspectedClaims>, Box<dyn ::std::error::Error>> {
+    let bytes = load_artifact(
+        &cmd.module,
+        cmd.digest.clone(),
+        cmd.allow_latest,
+        cmd.user.clone(),
+        cmd.password.clone(),
+        cmd.insecure,
+    )
+    .await?;
+    inspect_bytes(&bytes)
+}
+
+/// Reads a file, or pulls an OCI artifact if no such file exists. A JWT string is returned as-is
+async fn load_artifact(
+    module: &str,
+    digest: Option<String>,
+    allow_latest: bool,
+    user: Option<String>,
+    password: Option<String>,
+    insecure: bool,
+) -> Result<Vec<u8>, Box<dyn ::std::error::Error>> {
+    match File::open(module) {
         Ok(mut f) => {
             let mut buf = Vec::new();
-            f.read_to_end(&mut buf).unwrap();
-            buf
+            f.read_to_end(&mut buf)?;
+            Ok(buf)
         }
+        Err(_) if looks_like_jwt(module) => Ok(module.as_bytes().to_vec()),
         Err(_) => {
             crate::reg::pull_artifact(
-                cmd.module.to_string(),
-                cmd.digest.clone(),
-                cmd.allow_latest,
-                cmd.user.clone(),
-                cmd.password.clone(),
-                cmd.insecure,
+                module.to_string(),
+                digest,
+                allow_latest,
+                user,
+                password,
+                insecure,
             )
-            .await?
+            .await
         }
-    };
+    }
+}
 
-    // Extract will return an error if it encounters an invalid hash in the claims
-    let claims = wascap::wasm::extract_claims(&module_bytes);
-    match claims {
-        Ok(token) => Ok(token),
-        Err(e) => Err(Box::new(e)),
+/// Detects whether the bytes are a wasm module, a provider archive (.par or .par.gz),
+/// or a JWT, and extracts the claims accordingly
+fn inspect_bytes(bytes: &[u8]) -> Result<Option<InspectedClaims>, Box<dyn ::std::error::Error>> {
+    if bytes.starts_with(WASM_MAGIC) {
+        // Extract will return an error if it encounters an invalid hash in the claims
+        Ok(wascap::wasm::extract_claims(bytes)?.map(InspectedClaims::Actor))
+    } else if is_provider_archive(bytes) {
+        // Loading validates the archive's claims and library hashes
+        let archive = ProviderArchive::try_load(bytes).map_err(|e| format!("{}", e))?;
+        let claims = archive
+            .claims()
+            .ok_or("No claims found in provider archive")?;
+        Ok(Some(InspectedClaims::Provider {
+            token: Token {
+                jwt: archive_jwt(bytes)?,
+                claims,
+            },
+            targets: archive.targets(),
+        }))
+    } else {
+        let jwt = std::str::from_utf8(bytes)
+            .ok()
+            .map(str::trim)
+            .filter(|s| looks_like_jwt(s))
+            .ok_or("Input is not a wasm module, provider archive, or JWT")?;
+        decode_jwt(jwt).map(Some)
     }
 }
 
-async fn render_caps(cmd: InspectCommand) -> Result<String, Box<dyn ::std::error::Error>> {
-    let caps = get_caps(&cmd).await?;
+pub(crate) const WASM_MAGIC: &[u8] = b"\0asm";
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
 
-    let out = match caps {
-        Some(token) => {
-            if cmd.jwt_only {
-                token.jwt
-            } else {
-                let validation = wascap::jwt::validate_token::<Actor>(&token.jwt)?;
-                render_actor_claims(token.claims, validation, &cmd.output)
-            }
+/// Provider archives are tar files, optionally gzipped
+fn is_provider_archive(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC) || bytes.get(257..262) == Some(&b"ustar"[..])
+}
+
+/// Reads the raw claims JWT embedded in a provider archive
+fn archive_jwt(bytes: &[u8]) -> Result<String, Box<dyn ::std::error::Error>> {
+    let reader: Box<dyn Read> = if bytes.starts_with(&GZIP_MAGIC) {
+        Box::new(flate2::read::GzDecoder::new(bytes))
+    } else {
+        Box::new(bytes)
+    };
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.file_stem() == Some("claims".as_ref()) {
+            let mut jwt = String::new();
+            entry.read_to_string(&mut jwt)?;
+            return Ok(jwt);
         }
+    }
+    Err("No claims found in provider archive".into())
+}
+
+/// A JWT is three base64url segments separated by periods
+fn looks_like_jwt(s: &str) -> bool {
+    let segments = s.split('.').collect::<Vec<_>>();
+    segments.len() == 3
+        && segments.iter().all(|seg| {
+            !seg.is_empty()
+                && seg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+/// Decodes a JWT as the claims type matching its subject's key type
+fn decode_jwt(jwt: &str) -> Result<InspectedClaims, Box<dyn ::std::error::Error>> {
+    let payload = jwt.split('.').nth(1).unwrap_or_default();
+    let payload: serde_json::Value =
+        serde_json::from_slice(&data_encoding::BASE64URL_NOPAD.decode(payload.as_bytes())?)?;
+    let subject = payload["sub"].as_str().unwrap_or_default();
+    let jwt = jwt.to_string();
+    Ok(match subject.chars().next() {
+        Some('M') => InspectedClaims::Actor(Token {
+            claims: Claims::<Actor>::decode(&jwt)?,
+            jwt,
+        }),
+        Some('V') => InspectedClaims::Provider {
+            token: Token {
+                claims: Claims::<CapabilityProvider>::decode(&jwt)?,
+                jwt,
+            },
+            targets: Vec::new(),
+        },
+        Some('A') => InspectedClaims::Account(Token {
+            claims: Claims::<Account>::decode(&jwt)?,
+            jwt,
+        }),
+        Some('O') => InspectedClaims::Operator(Token {
+            claims: Claims::<Operator>::decode(&jwt)?,
+            jwt,
+        }),
+        _ => return Err(format!("Unsupported JWT subject '{}'", subject).into()),
+    })
+}
+
+async fn render_caps(cmd: InspectCommand) -> Result<String, Box<dyn ::std::error::Error>> {
+    let claims = get_claims(&cmd).await?;
+
+    let out = match claims {
         None => format!("No capabilities discovered in : {}", &cmd.module),
+        Some(claims) if cmd.jwt_only => claims.jwt().to_string(),
+        Some(InspectedClaims::Actor(token)) => {
+            let validation = wascap::jwt::validate_token::<Actor>(&token.jwt)?;
+            render_actor_claims(token.claims, validation, &cmd.output)
+        }
+        Some(InspectedClaims::Provider { token, targets }) => {
+            let validation = wascap::jwt::validate_token::<CapabilityProvider>(&token.jwt)?;
+            render_provider_claims(token.claims, validation, &targets, &cmd.output)
+        }
+        Some(InspectedClaims::Account(token)) => {
+            let validation = wascap::jwt::validate_token::<Account>(&token.jwt)?;
+            let signers = token
+                .claims
+                .metadata
+                .as_ref()
+                .and_then(|md| md.valid_signers.clone());
+            render_signer_claims(&token.claims, validation, signers, &cmd.output)
+        }
+        Some(InspectedClaims::Operator(token)) => {
+            let validation = wascap::jwt::validate_token::<Operator>(&token.jwt)?;
+            let signers = token
+                .claims
+                .metadata
+                .as_ref()
+                .and_then(|md| md.valid_signers.clone());
+            render_signer_claims(&token.claims, validation, signers, &cmd.output)
+        }
     };
     Ok(out)
 }
@@ -819,8 +1062,123 @@ pub(crate) fn render_actor_claims(
     }
 }
 
-// * - we don't need render impls for Operator or Account because those tokens are never embedded into a module,
-// only actors.
+/// Renders capability provider claims into provided output format. Targets are listed
+/// when the claims were read from a provider archive
+fn render_provider_claims(
+    claims: Claims<CapabilityProvider>,
+    validation: TokenValidation,
+    targets: &[String],
+    output: &Output,
+) -> String {
+    let md = claims.metadata.clone().unwrap_or_default();
+    let friendly_ver = md.ver.unwrap_or_else(|| "None".to_string());
+    let friendly_rev = md.rev.unwrap_or(0);
+
+    match output.kind {
+        OutputKind::Json => {
+            let iss_label = token_label(&claims.issuer).to_ascii_lowercase();
+            let sub_label = token_label(&claims.subject).to_ascii_lowercase();
+            format!(
+                "{}",
+                json!({ iss_label: claims.issuer,
+                sub_label: claims.subject,
+                "expires": validation.expires_human,
+                "can_be_used": validation.not_before_human,
+                "capability_contract_id": md.capid,
+                "vendor": md.vendor,
+                "version": friendly_ver,
+                "revision": friendly_rev,
+                "targets": targets,
+                })
+            )
+        }
+        OutputKind::Text => {
+            let mut table = render_core(&claims, validation);
+
+            table.add_row(Row::new(vec![
+                TableCell::new("Capability Contract ID"),
+                TableCell::new_with_alignment(md.capid, 1, Alignment::Right),
+            ]));
+            table.add_row(Row::new(vec![
+                TableCell::new("Vendor"),
+                TableCell::new_with_alignment(md.vendor, 1, Alignment::Right),
+            ]));
+            table.add_row(Row::new(vec![
+                TableCell::new("Version"),
+                TableCell::new_with_alignment(
+                    format!("{} ({})", friendly_ver, friendly_rev),
+                    1,
+                    Alignment::Right,
+                ),
+            ]));
+
+            if !targets.is_empty() {
+                table.add_row(Row::new(vec![TableCell::new_with_alignment(
+                    "Supported Architecture Targets",
+                    2,
+                    Alignment::Center,
+                )]));
+                table.add_row(Row::new(vec![TableCell::new_with_alignment(
+                    targets.join("\n"),
+                    2,
+                    Alignment::Left,
+                )]));
+            }
+
+            table.render()
+        }
+    }
+}
+
+/// Renders account or operator claims, whose metadata is the list of additional
+/// keys allowed to sign on their behalf
+fn render_signer_claims<T>(
+    claims: &Claims<T>,
+    validation: TokenValidation,
+    valid_signers: Option<Vec<String>>,
+    output: &Output,
+) -> String
+where
+    T: serde::Serialize + DeserializeOwned + WascapEntity,
+{
+    let signers = valid_signers.unwrap_or_default();
+    match output.kind {
+        OutputKind::Json => {
+            let iss_label = token_label(&claims.issuer).to_ascii_lowercase();
+            let sub_label = token_label(&claims.subject).to_ascii_lowercase();
+            format!(
+                "{}",
+                json!({ iss_label: claims.issuer,
+                sub_label: claims.subject,
+                "name": claims.name(),
+                "expires": validation.expires_human,
+                "can_be_used": validation.not_before_human,
+                "valid_signers": signers,
+                })
+            )
+        }
+        OutputKind::Text => {
+            let mut table = render_core(claims, validation);
+
+            table.add_row(Row::new(vec![TableCell::new_with_alignment(
+                "Valid Signers",
+                2,
+                Alignment::Center,
+            )]));
+            table.add_row(Row::new(vec![TableCell::new_with_alignment(
+                if signers.is_empty() {
+                    "None".to_string()
+                } else {
+                    signers.join("\n")
+                },
+                2,
+                Alignment::Left,
+            )]));
+
+            table.render()
+        }
+    }
+}
 
 fn token_label(pk: &str) -> String {
     match pk.chars().next().unwrap() {
@@ -829,6 +1187,7 @@ fn token_label(pk: &str) -> String {
         'O' => "Operator".to_string(),
         'S' => "Server".to_string(),
         'U' => "User".to_string(),
+        'V' => "Service".to_string(),
         _ => "<Unknown>".to_string(),
     }
 }
@@ -952,6 +1311,122 @@ mod test {
         assert!(sanitize_alias(None).unwrap().is_none());
     }
 
+    #[test]
+    fn test_claims_inspect_jwts() -> Result<(), Box<dyn ::std::error::Error>> {
+        let operator = KeyPair::new_operator();
+        let account = KeyPair::new_account();
+        let signer = KeyPair::new_account();
+        let jwt = Claims::<Account>::new(
+            "acme".to_string(),
+            operator.public_key(),
+            account.public_key(),
+            vec![signer.public_key()],
+        )
+        .encode(&operator)?;
+        assert!(looks_like_jwt(&jwt));
+        assert!(!looks_like_jwt(SUBSCRIBER_OCI));
+
+        match inspect_bytes(format!("{}\n", jwt).as_bytes())? {
+            Some(InspectedClaims::Account(token)) => {
+                assert_eq!(token.jwt, jwt);
+                assert_eq!(token.claims.subject, account.public_key());
+                let validation = wascap::jwt::validate_token::<Account>(&token.jwt)?;
+                let signers = token.claims.metadata.clone().unwrap().valid_signers;
+                let json = render_signer_claims(
+                    &token.claims,
+                    validation,
+                    signers,
+                    &Output {
+                        kind: OutputKind::Json,
+                    },
+                );
+                let json: serde_json::Value = serde_json::from_str(&json)?;
+                assert_eq!(json["operator"], operator.public_key());
+                assert_eq!(json["valid_signers"][0], signer.public_key());
+            }
+            _ => panic!("expected account claims"),
+        }
+
+        let jwt = Claims::<Operator>::new(
+            "ops".to_string(),
+            operator.public_key(),
+            operator.public_key(),
+            vec![],
+        )
+        .encode(&operator)?;
+        assert!(matches!(
+            inspect_bytes(jwt.as_bytes())?,
+            Some(InspectedClaims::Operator(_))
+        ));
+        assert!(inspect_bytes(b"not a jwt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_claims_inspect_provider_archive() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let account = KeyPair::new_account();
+        let service = KeyPair::new_service();
+        for compress in [false, true] {
+            let path = dir.path().join("provider.par");
+            let mut par = ProviderArchive::new("wasmcloud:test", "Test", "acme", Some(1), None);
+            par.add_library("x86_64-linux", b"not really a library")
+                .map_err(|e| e.to_string())?;
+            par.write(path.to_str().unwrap(), &account, &service, compress)
+                .map_err(|e| e.to_string())?;
+            let path = if compress {
+                dir.path().join("provider.par.gz")
+            } else {
+                path
+            };
+
+            match inspect_bytes(&std::fs::read(path)?)? {
+                Some(InspectedClaims::Provider { token, targets }) => {
+                    assert_eq!(token.claims.subject, service.public_key());
+                    assert_eq!(targets, vec!["x86_64-linux".to_string()]);
+                    assert_eq!(
+                        Claims::<CapabilityProvider>::decode(&token.jwt)?,
+                        token.claims
+                    );
+                }
+                _ => panic!("expected provider claims"),
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_claims_sign_external_signer() -> Result<(), Box<dyn ::std::error::Error>> {
+        let account = KeyPair::new_account();
+        let (dir, signer) = crate::signer::test_agent(KeyPair::from_seed(&account.seed()?)?);
+        let source = dir.path().join("echo.wasm");
+        std::fs::write(&source, b"\0asm\x01\0\0\0")?;
+        let subject = KeyPair::new_module().seed()?;
+
+        let cmd = ClaimsCli::from_iter_safe(&[
+            "claims",
+            "sign",
+            source.to_str().unwrap(),
+            "--name",
+            "Echo",
+            "--subject",
+            &subject,
+            "--signer",
+            &signer,
+            "--disable-keygen",
+        ])?;
+        match cmd.command {
+            ClaimsCliCommand::Sign(cmd) => sign_file(cmd)?,
+            cmd => panic!("claims sign constructed incorrect command {:?}", cmd),
+        };
+
+        let token = extract_claims(std::fs::read(dir.path().join("echo_s.wasm"))?)?.unwrap();
+        assert_eq!(token.claims.issuer, account.public_key());
+        assert!(wascap::jwt::validate_token::<Actor>(&token.jwt)?.signature_valid);
+        Ok(())
+    }
+
     #[test]
     /// Enumerates all options and flags of the `claims inspect` command
     /// to ensure command line arguments do not change between versions
@@ -1090,6 +1565,13 @@ mod test {
             "--msg",
             "--prov",
             "--disable-keygen",
+            "--profile",
+            "./wasmcloud.toml",
+            "--caps-file",
+            "./capabilities.toml",
+            "--deny-unknown-caps",
+            "--signer",
+            "exec:/usr/local/bin/signer --key account",
         ])
         .unwrap();
 
@@ -1105,6 +1587,10 @@ mod test {
                 assert_eq!(metadata.common.expires_in_days.unwrap(), 3);
                 assert_eq!(metadata.common.not_before_days.unwrap(), 1);
                 assert!(metadata.common.disable_keygen);
+                assert_eq!(
+                    metadata.common.signer.as_deref(),
+                    Some("exec:/usr/local/bin/signer --key account")
+                );
                 assert_eq!(metadata.common.output.kind, OutputKind::Json);
                 assert!(metadata.keyvalue);
                 assert!(metadata.msg_broker);
@@ -1114,7 +1600,7 @@ mod test {
                 assert!(metadata.extras);
                 assert!(metadata.logging);
                 assert!(metadata.eventstream);
-                assert_eq!(metadata.name, "MyActor");
+                assert_eq!(metadata.name.unwrap(), "MyActor");
                 assert!(!metadata.custom_caps.is_empty());
                 assert_eq!(metadata.custom_caps[0], "test:custom");
                 assert!(!metadata.tags.is_empty());
@@ -1122,6 +1608,12 @@ mod test {
                 assert!(metadata.provider);
                 assert_eq!(metadata.rev.unwrap(), 2);
                 assert_eq!(metadata.ver.unwrap(), "0.0.1");
+                assert_eq!(metadata.profile.unwrap(), PathBuf::from("./wasmcloud.toml"));
+                assert_eq!(
+                    metadata.caps_file.unwrap(),
+                    PathBuf::from("./capabilities.toml")
+                );
+                assert!(metadata.deny_unknown_caps);
             }
             cmd => panic!("claims constructed incorrect command: {:?}", cmd),
         }
@@ -1187,7 +1679,7 @@ mod test {
                 assert!(metadata.extras);
                 assert!(metadata.logging);
                 assert!(metadata.eventstream);
-                assert_eq!(metadata.name, "MyActor");
+                assert_eq!(metadata.name.unwrap(), "MyActor");
                 assert!(!metadata.custom_caps.is_empty());
                 assert_eq!(metadata.custom_caps[0], "test:custom");
                 assert!(!metadata.tags.is_empty());
@@ -1323,7 +1815,7 @@ mod test {
                 ver,
                 ..
             })) => {
-                assert_eq!(name, "TokenName");
+                assert_eq!(name.unwrap(), "TokenName");
                 assert_eq!(common.directory.unwrap(), PathBuf::from(DIR));
                 assert_eq!(
                     common.expires_in_days.unwrap(),
@@ -0,0 +1,282 @@
+//! Signing profiles, which provide the claims for `claims sign` and `claims token actor`
+//! from the `[claims]` table of a project's wasmcloud.toml, e.g.
+//!
+//! ```toml
+//! [claims]
+//! name = "Echo"
+//! capabilities = ["wasmcloud:httpserver"]
+//! tags = ["example"]
+//! rev = 1
+//! ver = "0.1.0"
+//! call_alias = "echo"
+//! issuer = "./keys/account.nk"
+//! subject = "./keys/echo_module.nk"
+//! expires_in_days = 365
+//! ```
+//!
+use super::ActorMetadata;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Profile used when `--profile` isn't provided, if it exists in the current directory
+pub(crate) const PROFILE_FILE_NAME: &str = "wasmcloud.toml";
+
+/// top-level data structure for a wasmcloud.toml file. Only the `[claims]` table is read
+#[derive(Deserialize, Debug, Default)]
+struct ProjectConfig {
+    claims: Option<SigningProfile>,
+}
+
+/// Claims read from a signing profile. Paths are relative to the profile file
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SigningProfile {
+    name: Option<String>,
+    /// capability contract IDs, e.g. wasmcloud:keyvalue
+    #[serde(default)]
+    capabilities: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    provider: bool,
+    rev: Option<i32>,
+    ver: Option<String>,
+    call_alias: Option<String>,
+    /// issuer (account) seed, or path to the seed file
+    issuer: Option<String>,
+    /// external signer holding the issuer key, e.g. agent:/run/wash-signer.sock
+    signer: Option<String>,
+    /// subject (module) seed, or path to the seed file
+    subject: Option<String>,
+    /// location of key files for signing
+    directory: Option<PathBuf>,
+    expires_in_days: Option<u64>,
+    not_before_days: Option<u64>,
+}
+
+impl SigningProfile {
+    /// Reads the `[claims]` table of the profile file, resolving relative paths
+    /// against the directory of the file
+    pub(crate) fn from_path(path: &Path) -> Result<Self, Box<dyn ::std::error::Error>> {
+        Self::read(path)?
+            .ok_or_else(|| format!("No [claims] table found in {}", path.display()).into())
+    }
+
+    /// Like `from_path`, but a file without a `[claims]` table has no profile
+    fn read(path: &Path) -> Result<Option<Self>, Box<dyn ::std::error::Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read signing profile {}: {}", path.display(), e))?;
+        let config: ProjectConfig = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid signing profile {}: {}", path.display(), e))?;
+        let mut profile = match config.claims {
+            Some(profile) => profile,
+            None => return Ok(None),
+        };
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        // keys may be seeds, so only existing files are resolved to paths
+        let resolve_key = |key: Option<String>| {
+            key.map(|key| {
+                let resolved = base.join(&key);
+                if resolved.is_file() {
+                    resolved.to_string_lossy().to_string()
+                } else {
+                    key
+                }
+            })
+        };
+        profile.issuer = resolve_key(profile.issuer);
+        profile.subject = resolve_key(profile.subject);
+        profile.directory = profile.directory.map(|dir| base.join(dir));
+        Ok(Some(profile))
+    }
+}
+
+impl ActorMetadata {
+    /// Fills in the claims that weren't provided as flags from the signing profile given
+    /// with --profile, or from wasmcloud.toml in the current directory if it has a `[claims]`
+    /// table. Capabilities and tags from both are combined
+    pub(crate) fn with_profile(self) -> Result<Self, Box<dyn ::std::error::Error>> {
+        self.with_default_profile(Path::new(PROFILE_FILE_NAME))
+    }
+
+    fn with_default_profile(self, default: &Path) -> Result<Self, Box<dyn ::std::error::Error>> {
+        let profile = match self.profile {
+            Some(ref path) => SigningProfile::from_path(path)?,
+            // other tools share wasmcloud.toml, so it doesn't have to be a signing profile
+            None if default.is_file() => match SigningProfile::read(default)? {
+                Some(profile) => profile,
+                None => return Ok(self),
+            },
+            None => return Ok(self),
+        };
+        Ok(self.merge(profile))
+    }
+
+    fn merge(mut self, profile: SigningProfile) -> Self {
+        self.name = self.name.or(profile.name);
+        for cap in profile.capabilities {
+            if !self.custom_caps.contains(&cap) {
+                self.custom_caps.push(cap);
+            }
+        }
+        for tag in profile.tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+        self.provider = self.provider || profile.provider;
+        self.rev = self.rev.or(profile.rev);
+        self.ver = self.ver.or(profile.ver);
+        self.call_alias = self.call_alias.or(profile.call_alias);
+        // an issuer given as a flag also replaces the profile's signer, and vice versa
+        if self.issuer.is_none() && self.common.signer.is_none() {
+            self.issuer = profile.issuer;
+            self.common.signer = profile.signer;
+        }
+        self.subject = self.subject.or(profile.subject);
+        self.common.directory = self.common.directory.or(profile.directory);
+        self.common.expires_in_days = self.common.expires_in_days.or(profile.expires_in_days);
+        self.common.not_before_days = self.common.not_before_days.or(profile.not_before_days);
+        self
+    }
+
+    /// The name of the actor, which must be provided as a flag or in the signing profile
+    pub(crate) fn name(&self) -> Result<String, Box<dyn ::std::error::Error>> {
+        self.name.clone().ok_or_else(|| {
+            format!(
+                "A name is required, use --name or set it in the [claims] table of {}",
+                PROFILE_FILE_NAME
+            )
+            .into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::claims::{ClaimsCli, ClaimsCliCommand, SignCommand};
+    use structopt::StructOpt;
+
+    const PROFILE: &str = r#"
+[claims]
+name = "Echo"
+capabilities = ["wasmcloud:httpserver", "wasmcloud:keyvalue"]
+tags = ["example"]
+rev = 1
+ver = "0.1.0"
+call_alias = "echo"
+issuer = "account.nk"
+subject = "SMAMA4ABHIJUYQR54BDFHEMXIIGQATUXK6RYU6XLTFHDNCRVWT4KSDDSVE"
+directory = "keys"
+expires_in_days = 365
+
+[other]
+ignored = true
+"#;
+
+    fn sign_metadata(args: &[&str]) -> ActorMetadata {
+        let args = ["claims", "sign", "echo.wasm"]
+            .iter()
+            .chain(args.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+        match ClaimsCli::from_iter_safe(&args).unwrap().command {
+            ClaimsCliCommand::Sign(SignCommand { metadata, .. }) => metadata,
+            cmd => panic!("claims sign constructed incorrect command {:?}", cmd),
+        }
+    }
+
+    #[test]
+    fn test_signing_profile() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("wasmcloud.toml");
+        fs::write(&path, PROFILE)?;
+        fs::write(dir.path().join("account.nk"), "not really a seed")?;
+        let path_arg = path.to_str().unwrap();
+
+        let md = sign_metadata(&["--profile", path_arg]).with_profile()?;
+        assert_eq!(md.name()?, "Echo");
+        assert_eq!(
+            md.custom_caps,
+            vec!["wasmcloud:httpserver", "wasmcloud:keyvalue"]
+        );
+        assert_eq!(md.tags, vec!["example"]);
+        assert_eq!(md.rev, Some(1));
+        assert_eq!(md.call_alias.as_deref(), Some("echo"));
+        // key files and directories are relative to the profile, seeds are used as-is
+        assert_eq!(
+            md.issuer.as_deref(),
+            Some(dir.path().join("account.nk").to_str().unwrap())
+        );
+        assert_eq!(
+            md.subject.as_deref(),
+            Some("SMAMA4ABHIJUYQR54BDFHEMXIIGQATUXK6RYU6XLTFHDNCRVWT4KSDDSVE")
+        );
+        assert_eq!(md.common.directory, Some(dir.path().join("keys")));
+        assert_eq!(md.common.expires_in_days, Some(365));
+
+        // flags override the profile, and capabilities and tags are combined
+        let md = sign_metadata(&[
+            "--profile",
+            path_arg,
+            "--name",
+            "Other",
+            "--rev",
+            "2",
+            "--cap",
+            "wasmcloud:keyvalue",
+            "--cap",
+            "wasmcloud:messaging",
+            "--tag",
+            "prod",
+        ])
+        .with_profile()?;
+        assert_eq!(md.name()?, "Other");
+        assert_eq!(md.rev, Some(2));
+        assert_eq!(
+            md.custom_caps,
+            vec![
+                "wasmcloud:keyvalue",
+                "wasmcloud:messaging",
+                "wasmcloud:httpserver"
+            ]
+        );
+        assert_eq!(md.tags, vec!["prod", "example"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_signing_profile() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("wasmcloud.toml");
+
+        fs::write(&path, "[claims]\nnmae = \"Echo\"\n")?;
+        assert!(SigningProfile::from_path(&path).is_err());
+
+        fs::write(&path, "[other]\nname = \"Echo\"\n")?;
+        assert!(SigningProfile::from_path(&path).is_err());
+
+        assert!(SigningProfile::from_path(&dir.path().join("missing.toml")).is_err());
+
+        // a wasmcloud.toml without a [claims] table is only an error when given explicitly
+        fs::write(&path, "[actor]\nname = \"Echo\"\n")?;
+        let md = sign_metadata(&["--name", "Echo"]).with_default_profile(&path)?;
+        assert_eq!(md.name()?, "Echo");
+        assert!(md.custom_caps.is_empty());
+        assert!(sign_metadata(&["--profile", path.to_str().unwrap()])
+            .with_profile()
+            .is_err());
+        fs::write(&path, "[claims]\nname = \"Echo\"\n")?;
+        assert_eq!(
+            sign_metadata(&[]).with_default_profile(&path)?.name()?,
+            "Echo"
+        );
+
+        // without a profile, the name is required
+        assert!(sign_metadata(&[]).name().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use super::{inspect_bytes, WASM_MAGIC};
+use crate::util::{format_output, Output};
+use serde_json::json;
+use std::path::Path;
+use structopt::StructOpt;
+use wascap::wasm::extract_claims;
+
+/// Name of the custom section that holds the claims of a signed module
+const JWT_SECTION: &str = "jwt";
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) struct StripCommand {
+    /// Signed module to strip the claims from
+    pub(crate) module: String,
+
+    /// Destination for the unsigned module. If this flag is not provided, the unsigned module will be placed in the same directory as the source with a "_unsigned" suffix
+    #[structopt(short = "d", long = "destination")]
+    destination: Option<String>,
+
+    #[structopt(flatten)]
+    pub(crate) output: Output,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) struct ExtractCommand {
+    /// Signed module or provider archive to extract the claims JWT from
+    pub(crate) module: String,
+
+    /// Destination for the JWT. If this flag is not provided, the JWT will be placed in the same directory as the source with a ".jwt" extension
+    #[structopt(short = "d", long = "destination")]
+    destination: Option<String>,
+
+    #[structopt(flatten)]
+    pub(crate) output: Output,
+}
+
+pub(crate) fn strip_file(cmd: StripCommand) -> Result<String, Box<dyn ::std::error::Error>> {
+    let module = std::fs::read(&cmd.module)
+        .map_err(|e| format!("Failed to open module '{}': {}", cmd.module, e))?;
+    // the claims are only reported, so a module modified after signing can still be stripped
+    let claims = extract_claims(&module).ok().flatten().map(|t| t.claims);
+    let stripped = strip_claims(&module)?;
+
+    let destination = cmd
+        .destination
+        .clone()
+        .unwrap_or_else(|| sibling_path(&cmd.module, "_unsigned", "wasm"));
+    std::fs::write(&destination, stripped)?;
+
+    let subject = claims.map(|c| c.subject).unwrap_or_default();
+    Ok(format_output(
+        format!(
+            "Successfully removed the claims of {} and wrote the unsigned module to {}",
+            &subject, destination
+        ),
+        json!({"result": "success", "destination": destination, "subject": subject}),
+        &cmd.output.kind,
+    ))
+}
+
+pub(crate) fn extract_file(cmd: ExtractCommand) -> Result<String, Box<dyn ::std::error::Error>> {
+    let bytes = std::fs::read(&cmd.module)
+        .map_err(|e| format!("Failed to open module '{}': {}", cmd.module, e))?;
+    let jwt = match inspect_bytes(&bytes)? {
+        Some(claims) => claims.jwt().to_string(),
+        None => return Err(format!("No claims found in {}", cmd.module).into()),
+    };
+
+    let destination = cmd
+        .destination
+        .clone()
+        .unwrap_or_else(|| sibling_path(&cmd.module, "", "jwt"));
+    std::fs::write(&destination, &jwt)?;
+
+    Ok(format_output(
+        format!("Successfully extracted the claims JWT to {}", destination),
+        json!({"result": "success", "destination": destination, "token": jwt}),
+        &cmd.output.kind,
+    ))
+}
+
+/// A path next to the source, with a suffix added to its file stem and a new extension.
+/// Any extensions of the source, e.g. .par.gz, are replaced
+fn sibling_path(source: &str, suffix: &str, extension: &str) -> String {
+    let path = Path::new(source);
+    let file_name = path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = file_name.split('.').next().unwrap_or_default();
+    path.with_file_name(format!("{}{}.{}", stem, suffix, extension))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Removes the custom sections holding the claims from a wasm module, leaving all other
+/// sections untouched
+fn strip_claims(module: &[u8]) -> Result<Vec<u8>, Box<dyn ::std::error::Error>> {
+    match split_claims(module)? {
+        (stripped, Some(_)) => Ok(stripped),
+        (_, None) => Err("Module is not signed, no claims found".into()),
+    }
+}
+
+/// The claims JWT embedded in a wasm module, if it's signed
+pub(super) fn embedded_jwt(module: &[u8]) -> Result<Option<String>, Box<dyn ::std::error::Error>> {
+    Ok(split_claims(module)?.1)
+}
+
+/// Splits a wasm module into the module without its claims sections and the JWT of the first
+/// claims section, which is the one wascap reads
+fn split_claims(module: &[u8]) -> Result<(Vec<u8>, Option<String>), Box<dyn ::std::error::Error>> {
+    const HEADER_LEN: usize = 8;
+    if !module.starts_with(WASM_MAGIC) || module.len() < HEADER_LEN {
+        return Err("Input is not a wasm module".into());
+    }
+    let mut stripped = module[..HEADER_LEN].to_vec();
+    let mut jwt = None;
+    let mut rest = &module[HEADER_LEN..];
+    while let Some((&id, after_id)) = rest.split_first() {
+        let (size, size_len) = read_leb128(after_id)?;
+        let start = 1 + size_len;
+        let end = start
+            .checked_add(size)
+            .filter(|end| *end <= rest.len())
+            .ok_or("Invalid wasm module, a section is truncated")?;
+        let section = &rest[start..end];
+        if id == 0 && custom_section_name(section)? == JWT_SECTION.as_bytes() {
+            if jwt.is_none() {
+                let (name_len, name_len_bytes) = read_leb128(section)?;
+                let payload = &section[name_len_bytes + name_len..];
+                jwt = Some(String::from_utf8_lossy(payload).to_string());
+            }
+        } else {
+            stripped.extend_from_slice(&rest[..end]);
+        }
+        rest = &rest[end..];
+    }
+    Ok((stripped, jwt))
+}
+
+/// Replaces the claims JWT of a signed module, which is moved to a custom section at the end
+pub(crate) fn replace_claims(
+    module: &[u8],
+    jwt: &str,
+) -> Result<Vec<u8>, Box<dyn ::std::error::Error>> {
+    let mut payload = write_leb128(JWT_SECTION.len());
+    payload.extend_from_slice(JWT_SECTION.as_bytes());
+    payload.extend_from_slice(jwt.as_bytes());
+
+    let mut replaced = strip_claims(module)?;
+    replaced.push(0);
+    replaced.extend(write_leb128(payload.len()));
+    replaced.extend(payload);
+    Ok(replaced)
+}
+
+fn custom_section_name(section: &[u8]) -> Result<&[u8], Box<dyn ::std::error::Error>> {
+    let (len, len_bytes) = read_leb128(section)?;
+    section
+        .get(len_bytes..len_bytes + len)
+        .ok_or_else(|| "Invalid wasm module, a custom section name is truncated".into())
+}
+
+/// Reads an unsigned LEB128 u32, returning the value and the number of bytes read
+fn read_leb128(bytes: &[u8]) -> Result<(usize, usize), Box<dyn ::std::error::Error>> {
+    let mut value: usize = 0;
+    for (i, byte) in bytes.iter().take(5).enumerate() {
+        value |= ((byte & 0x7f) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    Err("Invalid wasm module, bad section length".into())
+}
+
+/// Encodes an unsigned LEB128 value
+fn write_leb128(mut value: usize) -> Vec<u8> {
+    let mut bytes = vec![];
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::claims::{ClaimsCli, ClaimsCliCommand};
+    use crate::util::OutputKind;
+    use wascap::prelude::KeyPair;
+
+    // an empty module with a custom section named "name"
+    const MODULE: &[u8] = b"\0asm\x01\0\0\0\0\x05\x04name";
+
+    fn sign(module: &[u8]) -> Vec<u8> {
+        wascap::wasm::sign_buffer_with_claims(
+            "Test".to_string(),
+            module,
+            KeyPair::new_module(),
+            KeyPair::new_account(),
+            None,
+            None,
+            vec![],
+            vec![],
+            false,
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_strip_claims() {
+        let signed = sign(MODULE);
+        assert!(extract_claims(&signed).unwrap().is_some());
+
+        let stripped = strip_claims(&signed).unwrap();
+        assert_eq!(stripped, MODULE);
+        assert!(extract_claims(&stripped).unwrap().is_none());
+        // the stripped module can be signed again
+        assert!(extract_claims(sign(&stripped)).unwrap().is_some());
+
+        assert_eq!(
+            embedded_jwt(&signed).unwrap(),
+            Some(extract_claims(&signed).unwrap().unwrap().jwt)
+        );
+        assert_eq!(embedded_jwt(MODULE).unwrap(), None);
+
+        assert!(strip_claims(MODULE).is_err());
+        assert!(strip_claims(b"not wasm").is_err());
+        assert!(strip_claims(&signed[..signed.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_read_leb128() {
+        assert_eq!(read_leb128(&[0x05]).unwrap(), (5, 1));
+        assert_eq!(read_leb128(&[0xe5, 0x8e, 0x26]).unwrap(), (624_485, 3));
+        assert!(read_leb128(&[0x80, 0x80]).is_err());
+        assert_eq!(write_leb128(5), vec![0x05]);
+        assert_eq!(write_leb128(624_485), vec![0xe5, 0x8e, 0x26]);
+    }
+
+    #[test]
+    fn test_replace_claims() {
+        let signed = sign(MODULE);
+        let token = extract_claims(&signed).unwrap().unwrap();
+        let other = extract_claims(sign(MODULE)).unwrap().unwrap();
+
+        // the module hash is unchanged, so any JWT for the same module can replace the claims
+        let replaced = replace_claims(&signed, &other.jwt).unwrap();
+        assert_eq!(extract_claims(&replaced).unwrap().unwrap().jwt, other.jwt);
+        assert_ne!(token.jwt, other.jwt);
+        assert!(replace_claims(MODULE, &token.jwt).is_err());
+    }
+
+    #[test]
+    fn test_strip_and_extract_files() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("echo_s.wasm");
+        let signed = sign(MODULE);
+        std::fs::write(&path, &signed)?;
+        let source = path.to_str().unwrap().to_string();
+
+        extract_file(ExtractCommand {
+            module: source.clone(),
+            destination: None,
+            output: Output::default(),
+        })?;
+        let jwt = std::fs::read_to_string(dir.path().join("echo_s.jwt"))?;
+        assert_eq!(jwt, extract_claims(&signed)?.unwrap().jwt);
+
+        strip_file(StripCommand {
+            module: source,
+            destination: None,
+            output: Output::default(),
+        })?;
+        assert_eq!(
+            std::fs::read(dir.path().join("echo_s_unsigned.wasm"))?,
+            MODULE
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sibling_path() {
+        assert_eq!(
+            sibling_path("./build/echo_s.wasm", "_unsigned", "wasm"),
+            "./build/echo_s_unsigned.wasm"
+        );
+        assert_eq!(sibling_path("provider.par.gz", "", "jwt"), "provider.jwt");
+    }
+
+    #[test]
+    /// Enumerates all options and flags of the `claims strip` and `claims extract` commands
+    /// to ensure command line arguments do not change between versions
+    fn test_claims_strip_extract_comprehensive() {
+        let strip = ClaimsCli::from_iter_safe(&[
+            "claims",
+            "strip",
+            "echo_s.wasm",
+            "--destination",
+            "echo.wasm",
+            "--output",
+            "json",
+        ])
+        .unwrap();
+        match strip.command {
+            ClaimsCliCommand::Strip(cmd) => {
+                assert_eq!(cmd.module, "echo_s.wasm");
+                assert_eq!(cmd.destination.unwrap(), "echo.wasm");
+                assert_eq!(cmd.output.kind, OutputKind::Json);
+            }
+            cmd => panic!("claims strip constructed incorrect command {:?}", cmd),
+        }
+        let extract = ClaimsCli::from_iter_safe(&[
+            "claims",
+            "extract",
+            "echo_s.wasm",
+            "-d",
+            "echo.jwt",
+            "-o",
+            "json",
+        ])
+        .unwrap();
+        match extract.command {
+            ClaimsCliCommand::Extract(cmd) => {
+                assert_eq!(cmd.module, "echo_s.wasm");
+                assert_eq!(cmd.destination.unwrap(), "echo.jwt");
+                assert_eq!(cmd.output.kind, OutputKind::Json);
+            }
+            cmd => panic!("claims extract constructed incorrect command {:?}", cmd),
+        }
+    }
+}
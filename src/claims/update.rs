@@ -0,0 +1,488 @@
+use super::caps::CapabilityRegistry;
+use super::{sanitize_alias, strip};
+use crate::signer::extract_signer;
+use crate::util::{format_output, Output};
+use nkeys::KeyPairType;
+use serde_json::json;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use wascap::jwt::{Actor, Claims};
+use wascap::wasm::{days_from_now_to_jwt_time, embed_claims, extract_claims};
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) struct UpdateCommand {
+    /// Signed module to update
+    pub(crate) source: String,
+
+    /// Destination for the re-signed module. If this flag is not provided, the source module is updated in place
+    #[structopt(short = "d", long = "destination")]
+    destination: Option<String>,
+
+    /// Replace the name of the module
+    #[structopt(short = "n", long = "name")]
+    name: Option<String>,
+
+    /// Add a capability contract ID, e.g. wasmcloud:keyvalue. Can be repeated
+    #[structopt(short = "c", long = "add-cap", number_of_values = 1)]
+    add_caps: Vec<String>,
+
+    /// Remove a capability contract ID. Can be repeated
+    #[structopt(long = "remove-cap", number_of_values = 1)]
+    remove_caps: Vec<String>,
+
+    /// File defining additional capability contracts. Defaults to $WASH_CAPS ($HOME/.wash/capabilities.toml)
+    #[structopt(long = "caps-file", env = "WASH_CAPS", hide_env_values = true)]
+    caps_file: Option<PathBuf>,
+
+    /// Fail instead of warning when an added capability ID isn't a known contract
+    #[structopt(long = "deny-unknown-caps")]
+    deny_unknown_caps: bool,
+
+    /// Add a tag. Can be repeated
+    #[structopt(short = "t", long = "add-tag", number_of_values = 1)]
+    add_tags: Vec<String>,
+
+    /// Remove a tag. Can be repeated
+    #[structopt(long = "remove-tag", number_of_values = 1)]
+    remove_tags: Vec<String>,
+
+    /// Replace the revision number
+    #[structopt(short = "r", long = "rev", conflicts_with = "bump-rev")]
+    rev: Option<i32>,
+
+    /// Increment the revision number
+    #[structopt(long = "bump-rev")]
+    bump_rev: bool,
+
+    /// Replace the human-readable version string
+    #[structopt(short = "v", long = "ver")]
+    ver: Option<String>,
+
+    /// Replace the call alias, consisting of lowercase alphanumeric characters, underscores '_' and slashes '/'
+    #[structopt(short = "a", long = "call-alias", conflicts_with = "remove-call-alias")]
+    call_alias: Option<String>,
+
+    /// Remove the call alias
+    #[structopt(long = "remove-call-alias")]
+    remove_call_alias: bool,
+
+    /// Path to issuer seed key (account) to re-sign with. If this flag is not provided, the key will be sourced from $WASH_KEYS ($HOME/.wash/keys)
+    #[structopt(
+        short = "i",
+        long = "issuer",
+        env = "WASH_ISSUER_KEY",
+        hide_env_values = true
+    )]
+    issuer: Option<String>,
+
+    /// Allow re-signing with an issuer other than the one that signed the module
+    #[structopt(long = "rotate-issuer")]
+    rotate_issuer: bool,
+
+    /// Location of key files for signing. Defaults to $WASH_KEYS ($HOME/.wash/keys)
+    #[structopt(long = "directory", env = "WASH_KEYS", hide_env_values = true)]
+    directory: Option<PathBuf>,
+
+    /// External signer holding the issuer key, either exec:<command> or agent:<socket path>.
+    /// Used instead of the issuer seed, which then never needs to be on disk
+    #[structopt(long = "signer", env = "WASH_SIGNER", hide_env_values = true)]
+    signer: Option<String>,
+
+    /// Replace the expiry, so the token expires in the given amount of days from now
+    #[structopt(short = "x", long = "expires")]
+    expires_in_days: Option<u64>,
+
+    /// Replace the not before date, so the token is valid in the given amount of days from now
+    #[structopt(short = "b", long = "nbf")]
+    not_before_days: Option<u64>,
+
+    #[structopt(flatten)]
+    pub(crate) output: Output,
+}
+
+pub(crate) fn update_file(cmd: UpdateCommand) -> Result<String, Box<dyn ::std::error::Error>> {
+    let mut sfile = File::open(&cmd.source)
+        .map_err(|e| format!("Failed to open file for updating '{}': {}", &cmd.source, e))?;
+    let mut buf = Vec::new();
+    sfile.read_to_end(&mut buf)?;
+    let existing = extract_claims(&buf)?
+        .ok_or_else(|| format!("No claims found in {}, use `wash claims sign`", cmd.source))?
+        .claims;
+
+    CapabilityRegistry::load(cmd.caps_file.clone())?.check(&cmd.add_caps, cmd.deny_unknown_caps)?;
+
+    let issuer = extract_signer(
+        cmd.signer.clone(),
+        cmd.issuer.clone(),
+        Some(cmd.source.clone()),
+        cmd.directory.clone(),
+        KeyPairType::Account,
+        true,
+    )?;
+    if issuer.public_key() != existing.issuer && !cmd.rotate_issuer {
+        return Err(format!(
+            "{} was signed by {}, not {}. Use --rotate-issuer to re-sign with a different issuer",
+            cmd.source,
+            existing.issuer,
+            issuer.public_key()
+        )
+        .into());
+    }
+
+    let claims = updated_claims(&existing, &cmd, issuer.public_key())?;
+    let mut signed = embed_claims(&buf, &claims, &issuer.keypair()?)?;
+    if issuer.is_external() {
+        // as with `claims sign`, the claims embedded with a throwaway key are signed again
+        let token = extract_claims(&signed)?.ok_or("Failed to embed claims in module")?;
+        signed = strip::replace_claims(&signed, &issuer.resign::<Actor>(&token.jwt)?)?;
+    }
+
+    let destination = cmd
+        .destination
+        .clone()
+        .unwrap_or_else(|| cmd.source.clone());
+    let mut outfile = File::create(&destination)?;
+    outfile.write_all(&signed)?;
+
+    let caps = claims.metadata.and_then(|md| md.caps).unwrap_or_default();
+    Ok(format_output(
+        format!(
+            "Successfully updated {} with capabilities: {}",
+            destination,
+            caps.join(",")
+        ),
+        json!({
+            "result": "success",
+            "destination": destination,
+            "issuer": claims.issuer,
+            "capabilities": caps,
+        }),
+        &cmd.output.kind,
+    ))
+}
+
+/// Applies the changes to the existing claims, preserving everything that wasn't changed.
+/// The claims get a new ID and issue time, as they are a new token
+fn updated_claims(
+    existing: &Claims<Actor>,
+    cmd: &UpdateCommand,
+    issuer: String,
+) -> Result<Claims<Actor>, Box<dyn ::std::error::Error>> {
+    let md = existing.metadata.clone().unwrap_or_default();
+
+    let mut caps = md.caps.unwrap_or_default();
+    caps.retain(|c| !cmd.remove_caps.contains(c));
+    for cap in cmd.add_caps.iter() {
+        if !caps.contains(cap) {
+            caps.push(cap.clone());
+        }
+    }
+    if md.provider && caps.len() > 1 {
+        return Err("Capability providers cannot provide multiple capabilities at once.".into());
+    }
+
+    let mut tags = md.tags.unwrap_or_default();
+    tags.retain(|t| !cmd.remove_tags.contains(t));
+    for tag in cmd.add_tags.iter() {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+
+    let rev = if cmd.bump_rev {
+        Some(md.rev.unwrap_or_default() + 1)
+    } else {
+        cmd.rev.or(md.rev)
+    };
+    let call_alias = if cmd.remove_call_alias {
+        None
+    } else {
+        match cmd.call_alias {
+            Some(_) => sanitize_alias(cmd.call_alias.clone())?,
+            None => md.call_alias,
+        }
+    };
+    let not_before = match cmd.not_before_days {
+        Some(_) => days_from_now_to_jwt_time(cmd.not_before_days),
+        None => existing.not_before,
+    };
+    let expires = match cmd.expires_in_days {
+        Some(_) => days_from_now_to_jwt_time(cmd.expires_in_days),
+        None => existing.expires,
+    };
+
+    Ok(Claims::<Actor>::with_dates(
+        cmd.name
+            .clone()
+            .or(md.name)
+            .unwrap_or_else(|| existing.name()),
+        issuer,
+        existing.subject.clone(),
+        Some(caps),
+        Some(tags),
+        not_before,
+        expires,
+        md.provider,
+        rev,
+        cmd.ver.clone().or(md.ver),
+        call_alias,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::claims::{ClaimsCli, ClaimsCliCommand};
+    use crate::util::OutputKind;
+    use wascap::prelude::KeyPair;
+
+    fn update_cmd(source: &str, args: &[&str]) -> UpdateCommand {
+        let args = ["claims", "update", source]
+            .iter()
+            .chain(args.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+        match ClaimsCli::from_iter_safe(&args).unwrap().command {
+            ClaimsCliCommand::Update(cmd) => cmd,
+            cmd => panic!("claims update constructed incorrect command {:?}", cmd),
+        }
+    }
+
+    fn existing() -> Claims<Actor> {
+        Claims::<Actor>::with_dates(
+            "Echo".to_string(),
+            "ACOJJN6WUP4ODD75XEBKKTCCUJJCY5ZKQ56XVKYK4BEJWGVAOOQHZMCW".to_string(),
+            "MDPDJEYIAK6MACO67PRFGOSSLODBISK4SCEYDY3HEOY4P5CVJN6UCWUK".to_string(),
+            Some(vec![
+                "wasmcloud:httpserver".to_string(),
+                "wasmcloud:keyvalue".to_string(),
+            ]),
+            Some(vec!["test".to_string()]),
+            None,
+            Some(1_000),
+            false,
+            Some(3),
+            Some("0.1.0".to_string()),
+            Some("echo".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_updated_claims_preserves_unchanged() {
+        let existing = existing();
+        let cmd = update_cmd(
+            "echo.wasm",
+            &[
+                "--add-cap",
+                "wasmcloud:messaging",
+                "--remove-cap",
+                "wasmcloud:keyvalue",
+                "--add-tag",
+                "prod",
+                "--bump-rev",
+                "--remove-call-alias",
+            ],
+        );
+        let claims = updated_claims(&existing, &cmd, existing.issuer.clone()).unwrap();
+        let md = claims.metadata.clone().unwrap();
+        assert_eq!(
+            md.caps.unwrap(),
+            vec!["wasmcloud:httpserver", "wasmcloud:messaging"]
+        );
+        assert_eq!(md.tags.unwrap(), vec!["test", "prod"]);
+        assert_eq!(md.rev, Some(4));
+        assert_eq!(md.call_alias, None);
+        // unchanged claims are preserved
+        assert_eq!(md.name.unwrap(), "Echo");
+        assert_eq!(md.ver.unwrap(), "0.1.0");
+        assert_eq!(claims.subject, existing.subject);
+        assert_eq!(claims.expires, Some(1_000));
+        // it's a new token
+        assert_ne!(claims.id, existing.id);
+
+        let cmd = update_cmd("echo.wasm", &["--rev", "10", "--ver", "0.2.0", "-x", "30"]);
+        let claims = updated_claims(&existing, &cmd, existing.issuer.clone()).unwrap();
+        assert_eq!(claims.metadata.as_ref().unwrap().rev, Some(10));
+        assert!(claims.expires.unwrap() > 1_000);
+
+        let cmd = update_cmd("echo.wasm", &["--call-alias", "Not Valid"]);
+        assert!(updated_claims(&existing, &cmd, existing.issuer.clone()).is_err());
+    }
+
+    #[test]
+    fn test_update_file() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("echo.wasm");
+        let account = KeyPair::new_account();
+        let signed = wascap::wasm::sign_buffer_with_claims(
+            "Echo".to_string(),
+            b"\0asm\x01\0\0\0",
+            KeyPair::new_module(),
+            KeyPair::from_seed(&account.seed()?)?,
+            None,
+            None,
+            vec!["wasmcloud:httpserver".to_string()],
+            vec![],
+            false,
+            Some(1),
+            None,
+            None,
+        )?;
+        std::fs::write(&path, signed)?;
+        let source = path.to_str().unwrap();
+        let seed = account.seed()?;
+
+        update_file(update_cmd(
+            source,
+            &["--issuer", &seed, "--add-cap", "wasmcloud:keyvalue"],
+        ))?;
+        let claims = extract_claims(&std::fs::read(&path)?)?.unwrap().claims;
+        let md = claims.metadata.unwrap();
+        assert_eq!(
+            md.caps.unwrap(),
+            vec!["wasmcloud:httpserver", "wasmcloud:keyvalue"]
+        );
+        assert_eq!(md.rev, Some(1));
+        assert_eq!(claims.issuer, account.public_key());
+
+        // a different issuer must be explicitly allowed
+        let rotated = KeyPair::new_account();
+        let rotated_seed = rotated.seed()?;
+        assert!(update_file(update_cmd(source, &["--issuer", &rotated_seed])).is_err());
+        update_file(update_cmd(
+            source,
+            &["--issuer", &rotated_seed, "--rotate-issuer"],
+        ))?;
+        let claims = extract_claims(&std::fs::read(&path)?)?.unwrap().claims;
+        assert_eq!(claims.issuer, rotated.public_key());
+
+        // added capabilities are checked against the known contracts
+        assert!(update_file(update_cmd(
+            source,
+            &[
+                "--issuer",
+                &rotated_seed,
+                "--add-cap",
+                "wasmcloud:keyvalu",
+                "--deny-unknown-caps"
+            ],
+        ))
+        .is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_update_file_external_signer() -> Result<(), Box<dyn ::std::error::Error>> {
+        let account = KeyPair::new_account();
+        let (dir, signer) = crate::signer::test_agent(KeyPair::from_seed(&account.seed()?)?);
+        let path = dir.path().join("echo.wasm");
+        let signed = wascap::wasm::sign_buffer_with_claims(
+            "Echo".to_string(),
+            b"\0asm\x01\0\0\0",
+            KeyPair::new_module(),
+            KeyPair::from_seed(&account.seed()?)?,
+            None,
+            None,
+            vec![],
+            vec![],
+            false,
+            None,
+            None,
+            None,
+        )?;
+        std::fs::write(&path, signed)?;
+
+        update_file(update_cmd(
+            path.to_str().unwrap(),
+            &["--signer", &signer, "--add-tag", "prod"],
+        ))?;
+        let token = extract_claims(&std::fs::read(&path)?)?.unwrap();
+        assert_eq!(token.claims.issuer, account.public_key());
+        assert_eq!(token.claims.metadata.unwrap().tags.unwrap(), vec!["prod"]);
+        assert!(wascap::jwt::validate_token::<Actor>(&token.jwt)?.signature_valid);
+        Ok(())
+    }
+
+    #[test]
+    /// Enumerates all options and flags of the `claims update` command
+    /// to ensure command line arguments do not change between versions
+    fn test_claims_update_comprehensive() {
+        let cmd = update_cmd(
+            "echo.wasm",
+            &[
+                "--destination",
+                "echo_s.wasm",
+                "--name",
+                "Echo",
+                "--add-cap",
+                "wasmcloud:keyvalue",
+                "--remove-cap",
+                "wasmcloud:httpserver",
+                "--add-tag",
+                "prod",
+                "--remove-tag",
+                "test",
+                "--rev",
+                "2",
+                "--ver",
+                "0.2.0",
+                "--call-alias",
+                "echo",
+                "--issuer",
+                "./account.nk",
+                "--rotate-issuer",
+                "--directory",
+                "./keys",
+                "--signer",
+                "exec:/usr/local/bin/signer --key account",
+                "--caps-file",
+                "./capabilities.toml",
+                "--deny-unknown-caps",
+                "--expires",
+                "30",
+                "--nbf",
+                "1",
+                "--output",
+                "json",
+            ],
+        );
+        assert_eq!(cmd.source, "echo.wasm");
+        assert_eq!(cmd.destination.unwrap(), "echo_s.wasm");
+        assert_eq!(cmd.name.unwrap(), "Echo");
+        assert_eq!(cmd.add_caps, vec!["wasmcloud:keyvalue"]);
+        assert_eq!(cmd.remove_caps, vec!["wasmcloud:httpserver"]);
+        assert_eq!(cmd.add_tags, vec!["prod"]);
+        assert_eq!(cmd.remove_tags, vec!["test"]);
+        assert_eq!(cmd.rev, Some(2));
+        assert_eq!(cmd.ver.unwrap(), "0.2.0");
+        assert_eq!(cmd.call_alias.unwrap(), "echo");
+        assert_eq!(cmd.issuer.unwrap(), "./account.nk");
+        assert!(cmd.rotate_issuer);
+        assert_eq!(cmd.directory.unwrap(), PathBuf::from("./keys"));
+        assert_eq!(
+            cmd.signer.unwrap(),
+            "exec:/usr/local/bin/signer --key account"
+        );
+        assert_eq!(cmd.caps_file.unwrap(), PathBuf::from("./capabilities.toml"));
+        assert!(cmd.deny_unknown_caps);
+        assert_eq!(cmd.expires_in_days, Some(30));
+        assert_eq!(cmd.not_before_days, Some(1));
+        assert_eq!(cmd.output.kind, OutputKind::Json);
+
+        let cmd = update_cmd("echo.wasm", &["--bump-rev", "--remove-call-alias"]);
+        assert!(cmd.bump_rev);
+        assert!(cmd.remove_call_alias);
+        assert!(ClaimsCli::from_iter_safe(&[
+            "claims",
+            "update",
+            "echo.wasm",
+            "--rev",
+            "2",
+            "--bump-rev"
+        ])
+        .is_err());
+    }
+}
@@ -0,0 +1,555 @@
+use super::strip::embedded_jwt;
+use super::{
+    archive_jwt, decode_jwt, is_provider_archive, load_artifact, InspectedClaims, WASM_MAGIC,
+};
+use crate::util::{configure_table_style, CommandFailure, Output, OutputKind, RegistryOpts};
+use provider_archive::ProviderArchive;
+use serde::Serialize;
+use serde_json::json;
+use structopt::StructOpt;
+use term_table::{
+    row::Row,
+    table_cell::{Alignment, TableCell},
+    Table,
+};
+use wascap::jwt::{validate_token, Account, Actor, CapabilityProvider, Claims, Operator, Token};
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) struct VerifyCommand {
+    /// Path to signed actor module or provider archive, or OCI URL of either
+    pub(crate) module: String,
+
+    /// Public key of an issuer (account) trusted to sign the module. Can be repeated.
+    /// If omitted, any issuer is accepted
+    #[structopt(long = "issuer", number_of_values = 1)]
+    pub(crate) issuers: Vec<String>,
+
+    /// Capability contract ID the module must have, e.g. wasmcloud:httpserver. Can be repeated
+    #[structopt(long = "require-cap", number_of_values = 1)]
+    pub(crate) require_caps: Vec<String>,
+
+    /// Capability contract ID the module must not have. Can be repeated
+    #[structopt(long = "forbid-cap", number_of_values = 1)]
+    pub(crate) forbid_caps: Vec<String>,
+
+    /// Digest to verify artifact against (if OCI URL is provided for <module>)
+    #[structopt(short = "d", long = "digest")]
+    digest: Option<String>,
+
+    #[structopt(flatten)]
+    registry: RegistryOpts,
+
+    #[structopt(flatten)]
+    pub(crate) output: Output,
+}
+
+/// Result of a single verification check
+#[derive(Debug, Clone, Serialize)]
+struct Check {
+    name: &'static str,
+    passed: bool,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, passed: bool, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            passed,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// The parts of the claims that are verified, common to every kind of token
+struct Verifiable {
+    jwt: String,
+    issuer: String,
+    subject: String,
+    /// capabilities of an actor, or the contract of a provider. None for other tokens
+    caps: Option<Vec<String>>,
+}
+
+pub(crate) async fn handle_verify(
+    cmd: VerifyCommand,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    for issuer in cmd.issuers.iter() {
+        nkeys::KeyPair::from_public_key(issuer)
+            .map_err(|e| format!("Invalid issuer public key {}: {}", issuer, e))?;
+    }
+    let bytes = load_artifact(
+        &cmd.module,
+        cmd.digest.clone(),
+        cmd.registry.allow_latest,
+        cmd.registry.user.clone(),
+        cmd.registry.password.clone(),
+        cmd.registry.insecure,
+    )
+    .await?;
+
+    let checks = verify_bytes(&bytes, &cmd);
+    let output = verify_output(&cmd.module, &checks, &cmd.output.kind);
+    if checks.iter().all(|c| c.passed) {
+        Ok(output)
+    } else {
+        Err(CommandFailure(output).into())
+    }
+}
+
+/// Runs every check that applies to the module. Once the claims can't be read,
+/// the checks that depend on them are skipped
+fn verify_bytes(bytes: &[u8], cmd: &VerifyCommand) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let claims = match read_claims(bytes, &mut checks) {
+        Some(claims) => claims,
+        None => return checks,
+    };
+    let target = verifiable(claims);
+    let validation = match validation_for(&target) {
+        Ok(validation) => validation,
+        Err(e) => {
+            checks.push(Check::new("Signature", false, e.to_string()));
+            return checks;
+        }
+    };
+
+    checks.push(Check::new(
+        "Signature",
+        validation.signature_valid,
+        if validation.signature_valid {
+            format!("signed by {}", target.issuer)
+        } else {
+            "signature does not match the claims".to_string()
+        },
+    ));
+    checks.push(Check::new(
+        "Expiry",
+        !validation.expired,
+        format!("expires {}", validation.expires_human),
+    ));
+    checks.push(Check::new(
+        "Not Before",
+        !validation.cannot_use_yet,
+        format!("can be used {}", validation.not_before_human),
+    ));
+
+    checks.push(if cmd.issuers.is_empty() {
+        Check::new(
+            "Issuer",
+            true,
+            format!("{} (no trusted issuers required)", target.issuer),
+        )
+    } else if cmd.issuers.contains(&target.issuer) {
+        Check::new("Issuer", true, format!("{} is trusted", target.issuer))
+    } else {
+        Check::new(
+            "Issuer",
+            false,
+            format!("{} is not a trusted issuer", target.issuer),
+        )
+    });
+
+    let caps = target.caps.unwrap_or_default();
+    if !cmd.require_caps.is_empty() {
+        let missing = cmd
+            .require_caps
+            .iter()
+            .filter(|c| !caps.contains(c))
+            .cloned()
+            .collect::<Vec<_>>();
+        checks.push(if missing.is_empty() {
+            Check::new("Required Capabilities", true, cmd.require_caps.join(", "))
+        } else {
+            Check::new(
+                "Required Capabilities",
+                false,
+                format!("missing {}", missing.join(", ")),
+            )
+        });
+    }
+    if !cmd.forbid_caps.is_empty() {
+        let present = cmd
+            .forbid_caps
+            .iter()
+            .filter(|c| caps.contains(c))
+            .cloned()
+            .collect::<Vec<_>>();
+        checks.push(if present.is_empty() {
+            Check::new("Forbidden Capabilities", true, "none present")
+        } else {
+            Check::new(
+                "Forbidden Capabilities",
+                false,
+                format!("has {}", present.join(", ")),
+            )
+        });
+    }
+    checks
+}
+
+/// wascap 0.6 doesn't export its ErrorKind, so an invalid module hash can't be matched on.
+/// Extracting claims that decode fine can only fail because the module hash doesn't match
+fn has_valid_claims(module: &[u8]) -> bool {
+    match embedded_jwt(module) {
+        Ok(Some(jwt)) => Claims::<Actor>::decode(&jwt)
+            .map(|claims| claims.metadata.is_some())
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Extracts the claims, recording whether they could be read and whether the module or
+/// archive contents still match the hashes in the claims
+fn read_claims(bytes: &[u8], checks: &mut Vec<Check>) -> Option<InspectedClaims> {
+    if bytes.starts_with(WASM_MAGIC) {
+        match wascap::wasm::extract_claims(bytes) {
+            Ok(Some(token)) => {
+                checks.push(Check::new("Claims", true, "embedded in module"));
+                checks.push(Check::new(
+                    "Hash Integrity",
+                    true,
+                    "module matches the hash in its claims",
+                ));
+                Some(InspectedClaims::Actor(token))
+            }
+            Ok(None) => {
+                checks.push(Check::new("Claims", false, "module is not signed"));
+                None
+            }
+            Err(_) if has_valid_claims(bytes) => {
+                checks.push(Check::new("Claims", true, "embedded in module"));
+                checks.push(Check::new(
+                    "Hash Integrity",
+                    false,
+                    "module was modified after it was signed",
+                ));
+                None
+            }
+            Err(e) => {
+                checks.push(Check::new("Claims", false, e.to_string()));
+                None
+            }
+        }
+    } else if is_provider_archive(bytes) {
+        match ProviderArchive::try_load(bytes) {
+            Ok(archive) => {
+                checks.push(Check::new("Claims", true, "embedded in provider archive"));
+                checks.push(Check::new(
+                    "Hash Integrity",
+                    true,
+                    "libraries match the hashes in the claims",
+                ));
+                archive.claims().map(|claims| InspectedClaims::Provider {
+                    token: Token {
+                        jwt: archive_jwt(bytes).unwrap_or_default(),
+                        claims,
+                    },
+                    targets: archive.targets(),
+                })
+            }
+            // the archive has claims, so loading failed because the libraries don't match them
+            Err(e) if archive_jwt(bytes).is_ok() => {
+                checks.push(Check::new("Claims", true, "embedded in provider archive"));
+                checks.push(Check::new("Hash Integrity", false, e.to_string()));
+                None
+            }
+            Err(e) => {
+                checks.push(Check::new("Claims", false, e.to_string()));
+                None
+            }
+        }
+    } else {
+        match std::str::from_utf8(bytes)
+            .map_err(|_| "not a wasm module, provider archive, or JWT".into())
+            .and_then(|jwt| decode_jwt(jwt.trim()))
+        {
+            Ok(claims) => {
+                checks.push(Check::new("Claims", true, "JWT"));
+                Some(claims)
+            }
+            Err(e) => {
+                checks.push(Check::new("Claims", false, e.to_string()));
+                None
+            }
+        }
+    }
+}
+
+fn verifiable(claims: InspectedClaims) -> Verifiable {
+    match claims {
+        InspectedClaims::Actor(token) => Verifiable {
+            caps: Some(
+                token
+                    .claims
+                    .metadata
+                    .as_ref()
+                    .and_then(|md| md.caps.clone())
+                    .unwrap_or_default(),
+            ),
+            issuer: token.claims.issuer,
+            subject: token.claims.subject,
+            jwt: token.jwt,
+        },
+        InspectedClaims::Provider { token, .. } => Verifiable {
+            caps: Some(
+                token
+                    .claims
+                    .metadata
+                    .as_ref()
+                    .map(|md| vec![md.capid.clone()])
+                    .unwrap_or_default(),
+            ),
+            issuer: token.claims.issuer,
+            subject: token.claims.subject,
+            jwt: token.jwt,
+        },
+        InspectedClaims::Account(token) => Verifiable {
+            caps: None,
+            issuer: token.claims.issuer,
+            subject: token.claims.subject,
+            jwt: token.jwt,
+        },
+        InspectedClaims::Operator(token) => Verifiable {
+            caps: None,
+            issuer: token.claims.issuer,
+            subject: token.claims.subject,
+            jwt: token.jwt,
+        },
+    }
+}
+
+fn validation_for(
+    target: &Verifiable,
+) -> Result<wascap::jwt::TokenValidation, Box<dyn ::std::error::Error>> {
+    Ok(match target.subject.chars().next() {
+        Some('M') => validate_token::<Actor>(&target.jwt)?,
+        Some('V') => validate_token::<CapabilityProvider>(&target.jwt)?,
+        Some('A') => validate_token::<Account>(&target.jwt)?,
+        _ => validate_token::<Operator>(&target.jwt)?,
+    })
+}
+
+fn verify_output(module: &str, checks: &[Check], output_kind: &OutputKind) -> String {
+    let passed = checks.iter().all(|c| c.passed);
+    match *output_kind {
+        OutputKind::Json => format!(
+            "{}",
+            json!({
+                "module": module,
+                "passed": passed,
+                "checks": checks,
+            })
+        ),
+        OutputKind::Text => {
+            let mut table = Table::new();
+            configure_table_style(&mut table);
+            table.add_row(Row::new(vec![TableCell::new_with_alignment(
+                format!("Verification of {}", module),
+                3,
+                Alignment::Center,
+            )]));
+            for check in checks {
+                table.add_row(Row::new(vec![
+                    TableCell::new(check.name),
+                    TableCell::new(if check.passed { "PASS" } else { "FAIL" }),
+                    TableCell::new(&check.detail),
+                ]));
+            }
+            format!(
+                "{}\nVerification {}",
+                table.render(),
+                if passed { "passed" } else { "failed" }
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::claims::{ClaimsCli, ClaimsCliCommand};
+    use wascap::prelude::KeyPair;
+
+    const EMPTY_MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+    fn verify_cmd(args: &[&str]) -> VerifyCommand {
+        let args = ["claims", "verify", "module.wasm"]
+            .iter()
+            .chain(args.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+        match ClaimsCli::from_iter_safe(&args).unwrap().command {
+            ClaimsCliCommand::Verify(cmd) => cmd,
+            cmd => panic!("claims verify constructed incorrect command {:?}", cmd),
+        }
+    }
+
+    fn failed(checks: &[Check]) -> Vec<&str> {
+        checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| c.name)
+            .collect()
+    }
+
+    fn signed_actor(account: &KeyPair, not_before_days: Option<u64>) -> Vec<u8> {
+        wascap::wasm::sign_buffer_with_claims(
+            "Test".to_string(),
+            EMPTY_MODULE,
+            KeyPair::new_module(),
+            KeyPair::from_seed(&account.seed().unwrap()).unwrap(),
+            None,
+            not_before_days,
+            vec!["wasmcloud:httpserver".to_string()],
+            vec![],
+            false,
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_actor() {
+        let account = KeyPair::new_account();
+        let module = signed_actor(&account, None);
+
+        let trusted = account.public_key();
+        let checks = verify_bytes(
+            &module,
+            &verify_cmd(&[
+                "--issuer",
+                &trusted,
+                "--require-cap",
+                "wasmcloud:httpserver",
+                "--forbid-cap",
+                "wasmcloud:keyvalue",
+            ]),
+        );
+        assert!(failed(&checks).is_empty(), "{:?}", checks);
+
+        let other = KeyPair::new_account().public_key();
+        let checks = verify_bytes(
+            &module,
+            &verify_cmd(&[
+                "--issuer",
+                &other,
+                "--require-cap",
+                "wasmcloud:keyvalue",
+                "--forbid-cap",
+                "wasmcloud:httpserver",
+            ]),
+        );
+        assert_eq!(
+            failed(&checks),
+            vec!["Issuer", "Required Capabilities", "Forbidden Capabilities"]
+        );
+
+        let not_yet = signed_actor(&account, Some(1));
+        assert_eq!(
+            failed(&verify_bytes(&not_yet, &verify_cmd(&[]))),
+            vec!["Not Before"]
+        );
+    }
+
+    #[test]
+    fn test_verify_tampered() -> Result<(), Box<dyn ::std::error::Error>> {
+        let account = KeyPair::new_account();
+        // append a custom section after signing, changing the module hash
+        let mut module = signed_actor(&account, None);
+        module.extend_from_slice(&[0, 4, 3, b'x', b'y', b'z']);
+        assert_eq!(
+            failed(&verify_bytes(&module, &verify_cmd(&[]))),
+            vec!["Hash Integrity"]
+        );
+
+        assert_eq!(
+            failed(&verify_bytes(EMPTY_MODULE, &verify_cmd(&[]))),
+            vec!["Claims"]
+        );
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("provider.par");
+        let mut par = ProviderArchive::new("wasmcloud:test", "Test", "acme", None, None);
+        par.add_library("x86_64-linux", b"not really a library")
+            .map_err(|e| e.to_string())?;
+        par.write(
+            path.to_str().unwrap(),
+            &account,
+            &KeyPair::new_service(),
+            false,
+        )
+        .map_err(|e| e.to_string())?;
+        let archive = std::fs::read(&path)?;
+        let checks = verify_bytes(&archive, &verify_cmd(&["--require-cap", "wasmcloud:test"]));
+        assert!(failed(&checks).is_empty(), "{:?}", checks);
+
+        // replace the library contents, keeping the archive layout intact
+        let tampered = String::from_utf8_lossy(&archive)
+            .replace("not really a library", "not really a LIBRARY")
+            .into_bytes();
+        assert_eq!(tampered.len(), archive.len());
+        assert_eq!(
+            failed(&verify_bytes(&tampered, &verify_cmd(&[]))),
+            vec!["Hash Integrity"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_output() {
+        let checks = vec![
+            Check::new("Signature", true, "signed by ABC"),
+            Check::new("Issuer", false, "ABC is not a trusted issuer"),
+        ];
+        let json: serde_json::Value =
+            serde_json::from_str(&verify_output("echo.wasm", &checks, &OutputKind::Json)).unwrap();
+        assert_eq!(json["passed"], false);
+        assert_eq!(json["checks"][1]["name"], "Issuer");
+
+        let text = verify_output("echo.wasm", &checks, &OutputKind::Text);
+        assert!(text.contains("FAIL"));
+        assert!(text.ends_with("Verification failed"));
+    }
+
+    #[test]
+    /// Enumerates all options and flags of the `claims verify` command
+    /// to ensure command line arguments do not change between versions
+    fn test_claims_verify_comprehensive() {
+        const ISSUER: &str = "ACOJJN6WUP4ODD75XEBKKTCCUJJCY5ZKQ56XVKYK4BEJWGVAOOQHZMCW";
+        let cmd = verify_cmd(&[
+            "--issuer",
+            ISSUER,
+            "--issuer",
+            ISSUER,
+            "--require-cap",
+            "wasmcloud:httpserver",
+            "--forbid-cap",
+            "wasmcloud:keyvalue",
+            "--digest",
+            "sha256:5790f650cff526fcbc1271107a05111a6647002098b74a9a5e2e26e3c0a116b8",
+            "--allow-latest",
+            "--user",
+            "name",
+            "--password",
+            "opensesame",
+            "--insecure",
+            "--output",
+            "json",
+        ]);
+        assert_eq!(cmd.module, "module.wasm");
+        assert_eq!(cmd.issuers, vec![ISSUER, ISSUER]);
+        assert_eq!(cmd.require_caps, vec!["wasmcloud:httpserver"]);
+        assert_eq!(cmd.forbid_caps, vec!["wasmcloud:keyvalue"]);
+        assert_eq!(
+            cmd.digest.unwrap(),
+            "sha256:5790f650cff526fcbc1271107a05111a6647002098b74a9a5e2e26e3c0a116b8"
+        );
+        assert!(cmd.registry.allow_latest);
+        assert_eq!(cmd.registry.user.unwrap(), "name");
+        assert_eq!(cmd.registry.password.unwrap(), "opensesame");
+        assert!(cmd.registry.insecure);
+        assert_eq!(cmd.output.kind, OutputKind::Json);
+    }
+}
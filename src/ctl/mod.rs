@@ -20,7 +20,7 @@ use wasmcloud_control_interface::{
     LinkDefinitionList,
 };
 
-mod id;
+pub(crate) mod id;
 mod manifest;
 mod output;
 
@@ -0,0 +1,192 @@
+//! Generating keypairs with a chosen public key prefix, or deterministically from given entropy
+use super::public_key_prefix;
+use indicatif::{ProgressBar, ProgressStyle};
+use nkeys::{KeyPair, KeyPairType};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+/// Characters of the base32 alphabet used to encode keys
+const BASE32_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+/// Prefix byte of encoded seeds, which makes them start with S
+const PREFIX_BYTE_SEED: u8 = 18 << 3;
+const ENTROPY_LEN: usize = 32;
+
+/// Generates keypairs on all cores until one has a public key starting with the prefix after
+/// its type character, e.g. ACME for AACME..., showing progress while searching
+pub(crate) fn with_prefix(
+    keytype: &KeyPairType,
+    prefix: &str,
+) -> Result<KeyPair, Box<dyn ::std::error::Error>> {
+    let prefix = prefix.to_uppercase();
+    check_prefix(&prefix)?;
+
+    let found = Arc::new(AtomicBool::new(false));
+    let attempts = Arc::new(AtomicU64::new(0));
+    let (tx, rx) = mpsc::channel();
+    let workers = (0..num_cpus::get())
+        .map(|_| {
+            let (keytype, prefix, found, attempts, tx) = (
+                keytype.clone(),
+                prefix.clone(),
+                found.clone(),
+                attempts.clone(),
+                tx.clone(),
+            );
+            thread::spawn(move || {
+                while !found.load(Ordering::Relaxed) {
+                    let kp = KeyPair::new(keytype.clone());
+                    attempts.fetch_add(1, Ordering::Relaxed);
+                    if kp.public_key()[1..].starts_with(&prefix)
+                        && !found.swap(true, Ordering::SeqCst)
+                    {
+                        let _ = tx.send(kp);
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(tx);
+
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(ProgressStyle::default_spinner().template("{spinner} {msg} [{elapsed}]"));
+    let search = format!("{}{}", public_key_prefix(keytype), prefix);
+    let kp = loop {
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(kp) => break kp,
+            Err(mpsc::RecvTimeoutError::Timeout) => pb.set_message(format!(
+                "Searching for a public key starting with {}, {} keys tried",
+                search,
+                attempts.load(Ordering::Relaxed)
+            )),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err("Key generation stopped before finding a matching key".into())
+            }
+        }
+    };
+    for worker in workers {
+        let _ = worker.join();
+    }
+    pb.finish_and_clear();
+    Ok(kp)
+}
+
+/// Public keys are base32 encoded, and the character after the type character only encodes
+/// two bits of the key, so it can only be A, B, C or D
+fn check_prefix(prefix: &str) -> Result<(), Box<dyn ::std::error::Error>> {
+    if prefix.is_empty() {
+        return Err("Prefix must not be empty".into());
+    }
+    if let Some(c) = prefix.chars().find(|c| !BASE32_ALPHABET.contains(*c)) {
+        return Err(format!(
+            "Prefix cannot contain '{}', public keys only contain the characters A-Z and 2-7",
+            c
+        )
+        .into());
+    }
+    if !prefix.starts_with(|c| ('A'..='D').contains(&c)) {
+        return Err(
+            "Prefix must start with A, B, C or D, the only characters that follow the type character of a public key"
+                .into(),
+        );
+    }
+    Ok(())
+}
+
+/// Derives a keypair from 32 bytes of entropy given as hex. The same entropy always gives the
+/// same keypair, so this is only suitable for test fixtures and other keys that aren't secret
+pub(crate) fn from_entropy(
+    keytype: &KeyPairType,
+    entropy: &str,
+) -> Result<KeyPair, Box<dyn ::std::error::Error>> {
+    let entropy = data_encoding::HEXLOWER_PERMISSIVE
+        .decode(entropy.trim().as_bytes())
+        .map_err(|e| format!("Entropy is not valid hex: {}", e))?;
+    if entropy.len() != ENTROPY_LEN {
+        return Err(format!(
+            "Entropy must be {} bytes ({} hex characters), got {} bytes",
+            ENTROPY_LEN,
+            ENTROPY_LEN * 2,
+            entropy.len()
+        )
+        .into());
+    }
+    KeyPair::from_seed(&encode_seed(keytype, &entropy))
+        .map_err(|e| format!("Unable to derive keypair: {}", e).into())
+}
+
+/// Encodes a raw seed the way nkeys does: two prefix bytes for the seed and key type, the seed,
+/// and a CRC-16 checksum, all base32 encoded
+fn encode_seed(keytype: &KeyPairType, seed: &[u8]) -> String {
+    // the type character of public keys is the base32 encoding of the type prefix byte
+    let type_byte = (BASE32_ALPHABET
+        .find(public_key_prefix(keytype))
+        .expect("public key prefixes are base32 characters") as u8)
+        << 3;
+    let mut raw = vec![PREFIX_BYTE_SEED | (type_byte >> 5), (type_byte & 31) << 3];
+    raw.extend_from_slice(seed);
+    let crc = crc16(&raw);
+    raw.extend_from_slice(&crc.to_le_bytes());
+    data_encoding::BASE32_NOPAD.encode(&raw)
+}
+
+/// CRC-16/XMODEM, the checksum of encoded nkeys
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, b| {
+        (0..8).fold(crc ^ ((*b as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keys::KEY_TYPES;
+
+    #[test]
+    fn test_from_entropy() -> Result<(), Box<dyn ::std::error::Error>> {
+        const ENTROPY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+        // seeds are encoded exactly as nkeys encodes them
+        for keytype in KEY_TYPES.iter() {
+            let kp = KeyPair::new(keytype.clone());
+            let raw = data_encoding::BASE32_NOPAD.decode(kp.seed()?.as_bytes())?;
+            let derived = from_entropy(keytype, &data_encoding::HEXLOWER.encode(&raw[2..34]))?;
+            assert_eq!(derived.seed()?, kp.seed()?);
+            assert_eq!(derived.public_key(), kp.public_key());
+        }
+
+        let account = from_entropy(&KeyPairType::Account, ENTROPY)?;
+        assert_eq!(
+            account.public_key(),
+            from_entropy(&KeyPairType::Account, &ENTROPY.to_uppercase())?.public_key()
+        );
+        assert!(account.seed()?.starts_with("SA"));
+        assert!(account.public_key().starts_with('A'));
+        let module = from_entropy(&KeyPairType::Module, ENTROPY)?;
+        assert!(module.seed()?.starts_with("SM"));
+
+        assert!(from_entropy(&KeyPairType::Account, "00ff").is_err());
+        assert!(from_entropy(&KeyPairType::Account, &"zz".repeat(32)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_prefix() -> Result<(), Box<dyn ::std::error::Error>> {
+        let kp = with_prefix(&KeyPairType::Module, "ab")?;
+        assert!(kp.public_key().starts_with("MAB"));
+        assert!(kp.seed()?.starts_with("SM"));
+
+        assert!(check_prefix("").is_err());
+        assert!(check_prefix("A1").is_err());
+        assert!(check_prefix("XYZ").is_err());
+        assert!(check_prefix("D7").is_ok());
+        Ok(())
+    }
+}
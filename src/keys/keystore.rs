@@ -0,0 +1,277 @@
+//! Passphrase-encrypted seed files. An encrypted key file replaces the plaintext seed with a
+//! single line, `wash-encrypted-seed:v1:<public key>:<iterations>:<salt>:<nonce>:<ciphertext>`,
+//! where the seed is sealed with ChaCha20-Poly1305 using a key derived from the passphrase with
+//! PBKDF2-HMAC-SHA256. The public key is kept in the clear, and authenticated along with the
+//! header, so that encrypted keys can be listed without the passphrase. Encrypted and plaintext
+//! key files can be used interchangeably.
+use nkeys::KeyPair;
+use once_cell::sync::OnceCell;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::Path;
+
+const HEADER: &str = "wash-encrypted-seed:v1";
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+/// Environment variable holding the passphrase, for use without a terminal, e.g. in CI
+const PASSPHRASE_ENV: &str = "WASH_KEYS_PASSPHRASE";
+/// Environment variable that enables encryption of generated keys
+const ENCRYPT_ENV: &str = "WASH_KEYS_ENCRYPT";
+
+/// The passphrase is only asked for once per invocation of wash. There is no cache across
+/// invocations, $WASH_KEYS_PASSPHRASE is how a passphrase is reused for a whole shell session
+static PASSPHRASE: OnceCell<String> = OnceCell::new();
+
+pub(crate) fn is_encrypted(contents: &str) -> bool {
+    contents.trim_start().starts_with(HEADER)
+}
+
+/// The public key recorded in an encrypted key file, which is available without the passphrase
+pub(crate) fn public_key(contents: &str) -> Option<String> {
+    fields(contents).ok().map(|fields| fields[0].to_string())
+}
+
+/// Reads the seed from a key file, decrypting it if it's encrypted
+pub(crate) fn read_seed(path: &Path) -> Result<String, Box<dyn ::std::error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    if is_encrypted(&contents) {
+        decrypt_seed(&contents, &passphrase(false)?)
+            .map_err(|e| format!("Unable to decrypt {}: {}", path.display(), e).into())
+    } else {
+        Ok(contents.trim().to_string())
+    }
+}
+
+/// Writes a seed to a key file, encrypting it when the directory already holds encrypted keys
+/// or $WASH_KEYS_ENCRYPT is set
+pub(crate) fn write_seed(path: &Path, seed: &str) -> Result<(), Box<dyn ::std::error::Error>> {
+    let encrypt = std::env::var(ENCRYPT_ENV)
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let contents = if encrypt || has_encrypted_keys(dir) {
+        let passphrase = passphrase(true)?;
+        check_passphrase(dir, &passphrase)?;
+        encrypt_seed(seed, &passphrase)?
+    } else {
+        seed.to_string()
+    };
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Returns true if any key file in the directory is encrypted
+pub(crate) fn has_encrypted_keys(dir: &Path) -> bool {
+    encrypted_key(dir).is_some()
+}
+
+/// The contents of the first encrypted key file in the directory
+fn encrypted_key(dir: &Path) -> Option<String> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".nk"))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .find(|contents| is_encrypted(contents))
+}
+
+/// Ensures that a passphrase decrypts the existing encrypted keys of a directory, so that all
+/// keys in a directory share one passphrase
+pub(crate) fn check_passphrase(
+    dir: &Path,
+    passphrase: &str,
+) -> Result<(), Box<dyn ::std::error::Error>> {
+    match encrypted_key(dir) {
+        Some(contents) => decrypt_seed(&contents, passphrase)
+            .map(|_| ())
+            .map_err(|_| "Passphrase does not match the existing encrypted keys".into()),
+        None => Ok(()),
+    }
+}
+
+/// The passphrase for encrypted keys, from $WASH_KEYS_PASSPHRASE or prompted for once.
+/// When `confirm` is set, a prompted passphrase must be entered twice
+pub(crate) fn passphrase(confirm: bool) -> Result<String, Box<dyn ::std::error::Error>> {
+    PASSPHRASE
+        .get_or_try_init(|| {
+            if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+                return Ok(passphrase);
+            }
+            if !console::user_attended() {
+                return Err(format!(
+                    "A passphrase is required for encrypted keys, set ${} or run wash in a terminal",
+                    PASSPHRASE_ENV
+                )
+                .into());
+            }
+            let mut prompt = dialoguer::Password::new();
+            prompt.with_prompt("Passphrase for encrypted keys");
+            if confirm {
+                prompt.with_confirmation("Confirm passphrase", "Passphrases do not match");
+            }
+            prompt.interact().map_err(|e| e.into())
+        })
+        .cloned()
+}
+
+pub(crate) fn encrypt_seed(
+    seed: &str,
+    passphrase: &str,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let public_key = KeyPair::from_seed(seed.trim())
+        .map_err(|e| format!("Invalid seed: {}", e))?
+        .public_key();
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| "Unable to generate random bytes")?;
+
+    let header = format!("{}:{}", HEADER, public_key);
+    let key = derive_key(passphrase, &salt, PBKDF2_ITERATIONS)?;
+    let mut sealed = seed.trim().as_bytes().to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(header.as_bytes()),
+        &mut sealed,
+    )
+    .map_err(|_| "Unable to encrypt seed")?;
+
+    Ok(format!(
+        "{}:{}:{}:{}:{}",
+        header,
+        PBKDF2_ITERATIONS,
+        encode(&salt),
+        encode(&nonce),
+        encode(&sealed)
+    ))
+}
+
+pub(crate) fn decrypt_seed(
+    contents: &str,
+    passphrase: &str,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let fields = fields(contents)?;
+    let iterations = fields[1]
+        .parse::<u32>()
+        .map_err(|_| "Invalid encrypted key")?;
+    let salt = decode(fields[2])?;
+    let nonce = Nonce::try_assume_unique_for_key(&decode(fields[3])?)
+        .map_err(|_| "Invalid encrypted key")?;
+    let mut sealed = decode(fields[4])?;
+
+    let header = format!("{}:{}", HEADER, fields[0]);
+    let key = derive_key(passphrase, &salt, iterations)?;
+    let seed = key
+        .open_in_place(nonce, Aad::from(header.as_bytes()), &mut sealed)
+        .map_err(|_| "Incorrect passphrase")?;
+    let seed = String::from_utf8(seed.to_vec())?;
+    match KeyPair::from_seed(&seed) {
+        Ok(kp) if kp.public_key() == fields[0] => Ok(seed),
+        _ => Err("Invalid encrypted key".into()),
+    }
+}
+
+/// The public key, iterations, salt, nonce and ciphertext fields of an encrypted key file
+fn fields(contents: &str) -> Result<Vec<&str>, Box<dyn ::std::error::Error>> {
+    let fields = contents
+        .trim()
+        .strip_prefix(HEADER)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .ok_or("Not an encrypted key")?
+        .split(':')
+        .collect::<Vec<_>>();
+    if fields.len() == 5 {
+        Ok(fields)
+    } else {
+        Err("Invalid encrypted key".into())
+    }
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    iterations: u32,
+) -> Result<LessSafeKey, Box<dyn ::std::error::Error>> {
+    let iterations = NonZeroU32::new(iterations).ok_or("Invalid encrypted key")?;
+    let mut key = [0u8; KEY_LEN];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &key).map_err(|_| "Invalid key length")?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn encode(bytes: &[u8]) -> String {
+    data_encoding::BASE64URL_NOPAD.encode(bytes)
+}
+
+fn decode(field: &str) -> Result<Vec<u8>, Box<dyn ::std::error::Error>> {
+    data_encoding::BASE64URL_NOPAD
+        .decode(field.as_bytes())
+        .map_err(|_| "Invalid encrypted key".into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nkeys::KeyPair;
+
+    #[test]
+    fn test_encrypt_decrypt_seed() -> Result<(), Box<dyn ::std::error::Error>> {
+        let account = KeyPair::new_account();
+        let seed = account.seed()?;
+        let encrypted = encrypt_seed(&seed, "correct horse")?;
+        assert!(is_encrypted(&encrypted));
+        assert!(!is_encrypted(&seed));
+        assert_eq!(public_key(&encrypted), Some(account.public_key()));
+        assert_eq!(public_key(&seed), None);
+        assert!(!encrypted.contains(&seed));
+        assert_eq!(decrypt_seed(&encrypted, "correct horse")?, seed);
+        // salts and nonces are random
+        assert_ne!(encrypt_seed(&seed, "correct horse")?, encrypted);
+
+        let err = decrypt_seed(&encrypted, "battery staple").unwrap_err();
+        assert_eq!(err.to_string(), "Incorrect passphrase");
+        assert!(decrypt_seed(&encrypted[..encrypted.len() - 4], "correct horse").is_err());
+        assert!(decrypt_seed(&seed, "correct horse").is_err());
+        // the public key is authenticated, so it can't be swapped for another
+        let swapped =
+            encrypted.replace(&account.public_key(), &KeyPair::new_account().public_key());
+        assert!(decrypt_seed(&swapped, "correct horse").is_err());
+        assert!(encrypt_seed("SNOTASEED", "correct horse").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_passphrase() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        assert!(!has_encrypted_keys(dir.path()));
+        assert!(check_passphrase(dir.path(), "anything").is_ok());
+
+        fs::write(
+            dir.path().join("user_account.nk"),
+            KeyPair::new_account().seed()?,
+        )?;
+        assert!(!has_encrypted_keys(dir.path()));
+
+        fs::write(
+            dir.path().join("echo_module.nk"),
+            encrypt_seed(&KeyPair::new_module().seed()?, "correct horse")?,
+        )?;
+        assert!(has_encrypted_keys(dir.path()));
+        assert!(check_passphrase(dir.path(), "correct horse").is_ok());
+        assert!(check_passphrase(dir.path(), "battery staple").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+//! Importing, exporting, renaming and deleting the key files of a keys directory
+use super::{keypair_type_of, keypair_type_to_string, keystore, KEY_TYPES};
+use crate::util::{format_output, OutputKind};
+use nkeys::{KeyPair, KeyPairType};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Imports a seed, given directly or as a path to a file, as a key file named after the key.
+/// Names without a .nk extension follow the `<name>_<type>.nk` convention used for generated
+/// keys, so that imported keys are found by `claims sign` and `par create`
+pub(crate) fn import(
+    dir: &Path,
+    keyname: &str,
+    seed: &str,
+    key_type: Option<KeyPairType>,
+    output: &OutputKind,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let seed = if Path::new(seed).is_file() {
+        keystore::read_seed(Path::new(seed))?
+    } else {
+        seed.trim().to_string()
+    };
+    let kp = KeyPair::from_seed(&seed).map_err(|e| format!("Invalid seed: {}", e))?;
+    let seed_type = keypair_type_to_string(keypair_type_of(&kp));
+    if let Some(expected) = key_type.map(keypair_type_to_string) {
+        if expected != seed_type {
+            return Err(format!("Seed is a {} key, expected a {} key", seed_type, expected).into());
+        }
+    }
+
+    check_file_name(keyname)?;
+    let file_name = if keyname.ends_with(".nk") {
+        check_name_type(keyname, &seed_type)?;
+        keyname.to_string()
+    } else {
+        format!("{}_{}.nk", keyname, seed_type)
+    };
+    let path = dir.join(&file_name);
+    if path.exists() {
+        return Err(format!(
+            "Key {} already exists in {}, rename or delete it first",
+            file_name,
+            dir.display()
+        )
+        .into());
+    }
+    fs::create_dir_all(dir)?;
+    keystore::write_seed(&path, &seed)?;
+
+    Ok(format_output(
+        format!(
+            "Imported {} key {} as {}",
+            seed_type,
+            kp.public_key(),
+            path.display()
+        ),
+        json!({
+            "name": file_name,
+            "key_type": seed_type,
+            "public_key": kp.public_key(),
+            "path": path,
+        }),
+        output,
+    ))
+}
+
+/// Exports a key as JSON, printing it or writing it to the destination file
+pub(crate) fn export(
+    dir: &Path,
+    keyname: &str,
+    destination: Option<PathBuf>,
+    output: &OutputKind,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let path = existing_key(dir, keyname)?;
+    let seed = keystore::read_seed(&path)?;
+    let kp = KeyPair::from_seed(&seed)
+        .map_err(|e| format!("{} does not contain a valid seed: {}", path.display(), e))?;
+    let exported = json!({
+        "name": file_name(&path),
+        "key_type": keypair_type_to_string(keypair_type_of(&kp)),
+        "public_key": kp.public_key(),
+        "seed": seed,
+    });
+
+    match destination {
+        Some(destination) => {
+            fs::write(&destination, serde_json::to_string_pretty(&exported)?)?;
+            Ok(format_output(
+                format!(
+                    "Exported key {} to {}.\nRemember that the seed is private, treat it as a secret.",
+                    file_name(&path),
+                    destination.display()
+                ),
+                json!({"result": "success", "destination": destination}),
+                output,
+            ))
+        }
+        None => Ok(exported.to_string()),
+    }
+}
+
+/// Renames a key file, keeping it in the same directory
+pub(crate) fn rename(
+    dir: &Path,
+    keyname: &str,
+    new_name: &str,
+    output: &OutputKind,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let from = existing_key(dir, keyname)?;
+    check_file_name(new_name)?;
+    let to = dir.join(with_extension(new_name));
+    if to.exists() {
+        return Err(format!("Key {} already exists in {}", file_name(&to), dir.display()).into());
+    }
+    fs::rename(&from, &to)?;
+    Ok(format_output(
+        format!("Renamed key {} to {}", file_name(&from), file_name(&to)),
+        json!({"result": "success", "from": file_name(&from), "to": file_name(&to)}),
+        output,
+    ))
+}
+
+/// Deletes a key file, asking for confirmation unless `yes` is set
+pub(crate) fn delete(
+    dir: &Path,
+    keyname: &str,
+    yes: bool,
+    output: &OutputKind,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let path = existing_key(dir, keyname)?;
+    if !yes {
+        if !console::user_attended() {
+            return Err(
+                "Deleting a key requires confirmation, use --yes to delete it without one".into(),
+            );
+        }
+        let confirmed = dialoguer::Confirm::new()
+            .with_prompt(format!(
+                "Delete key {}? The key cannot be recovered unless it's backed up",
+                path.display()
+            ))
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            return Err("Key was not deleted".into());
+        }
+    }
+    fs::remove_file(&path)?;
+    Ok(format_output(
+        format!("Deleted key {}", file_name(&path)),
+        json!({"result": "success", "deleted": file_name(&path)}),
+        output,
+    ))
+}
+
+/// Rejects a key file name whose type suffix, e.g. _account.nk, doesn't match the key
+fn check_name_type(file_name: &str, key_type: &str) -> Result<(), Box<dyn ::std::error::Error>> {
+    let named_type = KEY_TYPES
+        .iter()
+        .map(|kt| keypair_type_to_string(kt.clone()))
+        .find(|kt| file_name.ends_with(&format!("_{}.nk", kt)));
+    match named_type {
+        Some(named_type) if named_type != key_type => Err(format!(
+            "Key name {} is for a {} key, but the seed is a {} key",
+            file_name, named_type, key_type
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Keys are always stored directly in the keys directory
+fn check_file_name(keyname: &str) -> Result<(), Box<dyn ::std::error::Error>> {
+    if keyname.contains(std::path::is_separator) {
+        Err(format!("Key name {} must be a file name, not a path", keyname).into())
+    } else {
+        Ok(())
+    }
+}
+
+pub(super) fn existing_key(
+    dir: &Path,
+    keyname: &str,
+) -> Result<PathBuf, Box<dyn ::std::error::Error>> {
+    let path = dir.join(with_extension(keyname));
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(format!(
+            "Key {} not found. Please ensure {} exists.",
+            keyname,
+            path.display()
+        )
+        .into())
+    }
+}
+
+/// Key names may leave off the .nk extension
+fn with_extension(keyname: &str) -> String {
+    if keyname.ends_with(".nk") {
+        keyname.to_string()
+    } else {
+        format!("{}.nk", keyname)
+    }
+}
+
+pub(super) fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_import_export() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let account = KeyPair::new_account();
+        let seed = account.seed()?;
+
+        import(
+            dir.path(),
+            "acme",
+            &seed,
+            Some(KeyPairType::Account),
+            &OutputKind::Text,
+        )?;
+        let path = dir.path().join("acme_account.nk");
+        assert_eq!(fs::read_to_string(&path)?, seed);
+        // existing keys are never overwritten
+        assert!(import(dir.path(), "acme", &seed, None, &OutputKind::Text).is_err());
+
+        // seeds can be read from files, and names with an extension are used as-is
+        import(
+            dir.path(),
+            "copy.nk",
+            path.to_str().unwrap(),
+            None,
+            &OutputKind::Text,
+        )?;
+        assert_eq!(fs::read_to_string(dir.path().join("copy.nk"))?, seed);
+
+        assert!(import(dir.path(), "bad", "SNOTASEED", None, &OutputKind::Text).is_err());
+        assert!(import(dir.path(), "../acme", &seed, None, &OutputKind::Text).is_err());
+        let module = KeyPair::new_module().seed()?;
+        assert!(import(
+            dir.path(),
+            "echo",
+            &module,
+            Some(KeyPairType::Account),
+            &OutputKind::Text
+        )
+        .is_err());
+        assert!(import(
+            dir.path(),
+            "echo_account.nk",
+            &module,
+            None,
+            &OutputKind::Text
+        )
+        .is_err());
+
+        let exported: serde_json::Value = serde_json::from_str(&export(
+            dir.path(),
+            "acme_account",
+            None,
+            &OutputKind::Text,
+        )?)?;
+        assert_eq!(exported["name"], "acme_account.nk");
+        assert_eq!(exported["key_type"], "account");
+        assert_eq!(exported["public_key"], account.public_key());
+        assert_eq!(exported["seed"], seed);
+
+        let destination = dir.path().join("acme.json");
+        export(
+            dir.path(),
+            "acme_account.nk",
+            Some(destination.clone()),
+            &OutputKind::Text,
+        )?;
+        let written: serde_json::Value = serde_json::from_str(&fs::read_to_string(destination)?)?;
+        assert_eq!(written, exported);
+        assert!(export(dir.path(), "missing", None, &OutputKind::Text).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_delete() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join("old_module.nk"),
+            KeyPair::new_module().seed()?,
+        )?;
+        fs::write(
+            dir.path().join("other_module.nk"),
+            KeyPair::new_module().seed()?,
+        )?;
+
+        rename(dir.path(), "old_module", "new_module", &OutputKind::Text)?;
+        assert!(!dir.path().join("old_module.nk").exists());
+        assert!(dir.path().join("new_module.nk").is_file());
+        assert!(rename(
+            dir.path(),
+            "new_module.nk",
+            "other_module.nk",
+            &OutputKind::Text
+        )
+        .is_err());
+        assert!(rename(
+            dir.path(),
+            "new_module.nk",
+            "../escaped.nk",
+            &OutputKind::Text
+        )
+        .is_err());
+        assert!(rename(dir.path(), "old_module", "newer_module", &OutputKind::Text).is_err());
+
+        delete(dir.path(), "new_module", true, &OutputKind::Text)?;
+        assert!(!dir.path().join("new_module.nk").exists());
+        assert!(delete(dir.path(), "new_module", true, &OutputKind::Text).is_err());
+        Ok(())
+    }
+}
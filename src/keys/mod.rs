@@ -0,0 +1,1452 @@
+use crate::util::{configure_table_style, format_output, Output, OutputKind};
+use nkeys::{KeyPair, KeyPairType};
+use serde_json::json;
+use std::fs;
+use std::fs::File;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use term_table::{
+    row::Row,
+    table_cell::{Alignment, TableCell},
+    Table,
+};
+
+mod gen;
+mod keystore;
+mod manage;
+mod rotate;
+mod signature;
+
+use signature::SignatureEncoding;
+
+/// All key types, in the order they're listed in help text
+pub(crate) const KEY_TYPES: [KeyPairType; 7] = [
+    KeyPairType::Account,
+    KeyPairType::User,
+    KeyPairType::Module,
+    KeyPairType::Service,
+    KeyPairType::Server,
+    KeyPairType::Operator,
+    KeyPairType::Cluster,
+];
+
+#[derive(Debug, Clone, StructOpt)]
+pub(crate) struct KeysCli {
+    #[structopt(flatten)]
+    command: KeysCliCommand,
+}
+
+impl KeysCli {
+    pub(crate) fn command(self) -> KeysCliCommand {
+        self.command
+    }
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum KeysCliCommand {
+    #[structopt(name = "gen", about = "Generates a keypair")]
+    GenCommand {
+        /// The type of keypair to generate. May be Account, User, Module (Actor), Service (Capability Provider), Server, Operator, Cluster
+        #[structopt(case_insensitive = true)]
+        keytype: KeyPairType,
+        /// Search for a public key with this prefix after its type character, e.g. ACME for an account key AACME...
+        #[structopt(long = "prefix", conflicts_with = "from-entropy")]
+        prefix: Option<String>,
+        /// Derive the keypair from 32 bytes of entropy, given as hex, so the same keypair is generated every time. Only for keys that aren't secret, such as test fixtures
+        #[structopt(long = "from-entropy")]
+        from_entropy: Option<String>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    #[structopt(name = "get", about = "Retrieves a keypair and prints the contents")]
+    GetCommand {
+        #[structopt(help = "The name of the key to output")]
+        keyname: String,
+        #[structopt(
+            long = "reveal",
+            help = "Output the seed of the key as well as its public key"
+        )]
+        reveal: bool,
+        #[structopt(
+            short = "d",
+            long = "directory",
+            env = "WASH_KEYS",
+            hide_env_values = true,
+            help = "Absolute path to where keypairs are stored. Defaults to `$HOME/.wash/keys`"
+        )]
+        directory: Option<PathBuf>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    #[structopt(name = "list", about = "Lists all keypairs in a directory")]
+    ListCommand {
+        #[structopt(
+            short = "d",
+            long = "directory",
+            env = "WASH_KEYS",
+            hide_env_values = true,
+            help = "Absolute path to where keypairs are stored. Defaults to `$HOME/.wash/keys`"
+        )]
+        directory: Option<PathBuf>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    #[structopt(
+        name = "encrypt",
+        about = "Encrypts the seeds of keypairs with a passphrase",
+        long_about = "Encrypts the seeds of keypairs with a passphrase. The passphrase is asked for once per command; to reuse it across commands in a shell session, set $WASH_KEYS_PASSPHRASE. Once a directory holds encrypted keys, new keys are encrypted with the same passphrase, as they are when $WASH_KEYS_ENCRYPT is set"
+    )]
+    EncryptCommand {
+        #[structopt(help = "Names of the keys to encrypt. Defaults to all keys in the directory")]
+        keynames: Vec<String>,
+        #[structopt(
+            short = "d",
+            long = "directory",
+            env = "WASH_KEYS",
+            hide_env_values = true,
+            help = "Absolute path to where keypairs are stored. Defaults to `$HOME/.wash/keys`"
+        )]
+        directory: Option<PathBuf>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    #[structopt(
+        name = "decrypt",
+        about = "Decrypts the seeds of encrypted keypairs, storing them in plaintext"
+    )]
+    DecryptCommand {
+        #[structopt(help = "Names of the keys to decrypt. Defaults to all keys in the directory")]
+        keynames: Vec<String>,
+        #[structopt(
+            short = "d",
+            long = "directory",
+            env = "WASH_KEYS",
+            hide_env_values = true,
+            help = "Absolute path to where keypairs are stored. Defaults to `$HOME/.wash/keys`"
+        )]
+        directory: Option<PathBuf>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    #[structopt(
+        name = "import",
+        about = "Imports a seed as a keypair, validating the seed and its key type"
+    )]
+    ImportCommand {
+        #[structopt(
+            help = "The name of the key. Names without a .nk extension are stored as <name>_<type>.nk"
+        )]
+        keyname: String,
+        #[structopt(help = "The seed to import, or the path to a file containing it")]
+        seed: String,
+        /// The type the key must be. May be Account, User, Module (Actor), Service (Capability Provider), Server, Operator, Cluster
+        #[structopt(short = "t", long = "type", case_insensitive = true)]
+        keytype: Option<KeyPairType>,
+        #[structopt(
+            short = "d",
+            long = "directory",
+            env = "WASH_KEYS",
+            hide_env_values = true,
+            help = "Absolute path to where keypairs are stored. Defaults to `$HOME/.wash/keys`"
+        )]
+        directory: Option<PathBuf>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    #[structopt(
+        name = "export",
+        about = "Exports a keypair as JSON, including its seed"
+    )]
+    ExportCommand {
+        #[structopt(help = "The name of the key to export")]
+        keyname: String,
+        /// File to write the exported key to. If this flag is not provided, the key is printed
+        #[structopt(long = "destination")]
+        destination: Option<PathBuf>,
+        #[structopt(
+            short = "d",
+            long = "directory",
+            env = "WASH_KEYS",
+            hide_env_values = true,
+            help = "Absolute path to where keypairs are stored. Defaults to `$HOME/.wash/keys`"
+        )]
+        directory: Option<PathBuf>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    #[structopt(name = "rename", about = "Renames a keypair")]
+    RenameCommand {
+        #[structopt(help = "The name of the key to rename")]
+        keyname: String,
+        #[structopt(help = "The new name of the key")]
+        new_name: String,
+        #[structopt(
+            short = "d",
+            long = "directory",
+            env = "WASH_KEYS",
+            hide_env_values = true,
+            help = "Absolute path to where keypairs are stored. Defaults to `$HOME/.wash/keys`"
+        )]
+        directory: Option<PathBuf>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    #[structopt(
+        name = "rotate",
+        about = "Replaces a keypair with a new one, archiving the old key and re-signing what it signed"
+    )]
+    RotateCommand {
+        #[structopt(help = "The name of the key to rotate")]
+        keyname: String,
+        /// Signed module or provider archive to re-sign with the new key. Can be repeated
+        #[structopt(long = "resign", number_of_values = 1)]
+        resign: Vec<PathBuf>,
+        /// Path to issuer seed key or seed, used to re-sign modules and archives issued for the rotated key
+        #[structopt(
+            short = "i",
+            long = "issuer",
+            env = "WASH_ISSUER_KEY",
+            hide_env_values = true
+        )]
+        issuer: Option<String>,
+        /// Operator token (JWT) whose additional keys should include the new key instead of the old one
+        #[structopt(long = "operator-token", requires = "operator-key")]
+        operator_token: Option<PathBuf>,
+        /// Path to operator seed key or seed, used to re-sign the operator token
+        #[structopt(long = "operator-key", requires = "operator-token")]
+        operator_key: Option<String>,
+        #[structopt(
+            short = "d",
+            long = "directory",
+            env = "WASH_KEYS",
+            hide_env_values = true,
+            help = "Absolute path to where keypairs are stored. Defaults to `$HOME/.wash/keys`"
+        )]
+        directory: Option<PathBuf>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    #[structopt(
+        name = "sign",
+        about = "Signs the contents of a file with a keypair, producing a detached signature"
+    )]
+    SignCommand {
+        #[structopt(help = "The name of the key to sign with")]
+        keyname: String,
+        #[structopt(help = "The file to sign, or - to read it from stdin")]
+        file: String,
+        /// Encoding of the signature, base64 or hex
+        #[structopt(short = "e", long = "encoding", default_value = "base64")]
+        encoding: SignatureEncoding,
+        /// Write the signature to this file instead of printing it
+        #[structopt(long = "destination")]
+        destination: Option<PathBuf>,
+        #[structopt(
+            short = "d",
+            long = "directory",
+            env = "WASH_KEYS",
+            hide_env_values = true,
+            help = "Absolute path to where keypairs are stored. Defaults to `$HOME/.wash/keys`"
+        )]
+        directory: Option<PathBuf>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    #[structopt(
+        name = "verify",
+        about = "Verifies a detached signature of the contents of a file"
+    )]
+    VerifyCommand {
+        #[structopt(help = "The public key of the signer")]
+        public_key: String,
+        #[structopt(help = "The signed file, or - to read it from stdin")]
+        file: String,
+        #[structopt(help = "The signature, or a file containing it")]
+        signature: String,
+        /// Encoding of the signature, base64 or hex
+        #[structopt(short = "e", long = "encoding", default_value = "base64")]
+        encoding: SignatureEncoding,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    #[structopt(name = "delete", about = "Deletes a keypair")]
+    DeleteCommand {
+        #[structopt(help = "The name of the key to delete")]
+        keyname: String,
+        /// Deletes the key without asking for confirmation
+        #[structopt(short = "y", long = "yes")]
+        yes: bool,
+        #[structopt(
+            short = "d",
+            long = "directory",
+            env = "WASH_KEYS",
+            hide_env_values = true,
+            help = "Absolute path to where keypairs are stored. Defaults to `$HOME/.wash/keys`"
+        )]
+        directory: Option<PathBuf>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+}
+
+pub(crate) fn handle_command(
+    command: KeysCliCommand,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    match command {
+        KeysCliCommand::GenCommand {
+            keytype,
+            prefix,
+            from_entropy,
+            output,
+        } => match (prefix, from_entropy) {
+            (Some(prefix), _) => Ok(keypair_output(
+                &gen::with_prefix(&keytype, &prefix)?,
+                &output.kind,
+            )),
+            (_, Some(entropy)) => Ok(keypair_output(
+                &gen::from_entropy(&keytype, &entropy)?,
+                &output.kind,
+            )),
+            (None, None) => Ok(generate(&keytype, &output.kind)),
+        },
+        KeysCliCommand::GetCommand {
+            keyname,
+            reveal,
+            directory,
+            output,
+        } => get(&keyname, reveal, directory, &output),
+        KeysCliCommand::ListCommand { directory, output } => list(directory, &output),
+        KeysCliCommand::EncryptCommand {
+            keynames,
+            directory,
+            output,
+        } => {
+            let dir = determine_directory(directory)?;
+            let passphrase = keystore::passphrase(true)?;
+            keystore::check_passphrase(&dir, &passphrase)?;
+            let keys = encrypt(&dir, &keynames, &passphrase)?;
+            Ok(key_files_output("Encrypted", &dir, keys, &output.kind))
+        }
+        KeysCliCommand::DecryptCommand {
+            keynames,
+            directory,
+            output,
+        } => {
+            let dir = determine_directory(directory)?;
+            let keys = decrypt(&dir, &keynames, &keystore::passphrase(false)?)?;
+            Ok(key_files_output("Decrypted", &dir, keys, &output.kind))
+        }
+        KeysCliCommand::ImportCommand {
+            keyname,
+            seed,
+            keytype,
+            directory,
+            output,
+        } => manage::import(
+            &determine_directory(directory)?,
+            &keyname,
+            &seed,
+            keytype,
+            &output.kind,
+        ),
+        KeysCliCommand::ExportCommand {
+            keyname,
+            destination,
+            directory,
+            output,
+        } => manage::export(
+            &determine_directory(directory)?,
+            &keyname,
+            destination,
+            &output.kind,
+        ),
+        KeysCliCommand::RenameCommand {
+            keyname,
+            new_name,
+            directory,
+            output,
+        } => manage::rename(
+            &determine_directory(directory)?,
+            &keyname,
+            &new_name,
+            &output.kind,
+        ),
+        KeysCliCommand::RotateCommand {
+            keyname,
+            resign,
+            issuer,
+            operator_token,
+            operator_key,
+            directory,
+            output,
+        } => rotate::rotate(
+            &determine_directory(directory)?,
+            &keyname,
+            &resign,
+            issuer,
+            operator_token,
+            operator_key,
+            &output.kind,
+        ),
+        KeysCliCommand::SignCommand {
+            keyname,
+            file,
+            encoding,
+            destination,
+            directory,
+            output,
+        } => signature::sign(
+            &determine_directory(directory)?,
+            &keyname,
+            &file,
+            encoding,
+            destination,
+            &output.kind,
+        ),
+        KeysCliCommand::VerifyCommand {
+            public_key,
+            file,
+            signature,
+            encoding,
+            output,
+        } => signature::verify(&public_key, &file, &signature, encoding, &output.kind),
+        KeysCliCommand::DeleteCommand {
+            keyname,
+            yes,
+            directory,
+            output,
+        } => manage::delete(
+            &determine_directory(directory)?,
+            &keyname,
+            yes,
+            &output.kind,
+        ),
+    }
+}
+
+/// Generates a keypair of the specified KeyPairType, as either Text or JSON
+pub(crate) fn generate(kt: &KeyPairType, output: &OutputKind) -> String {
+    keypair_output(&KeyPair::new(kt.clone()), output)
+}
+
+fn keypair_output(kp: &KeyPair, output: &OutputKind) -> String {
+    format_output(
+        format!(
+            "Public Key: {}\nSeed: {}\n\nRemember that the seed is private, treat it as a secret.",
+            kp.public_key(),
+            kp.seed().unwrap()
+        ),
+        json!({
+            "public_key": kp.public_key(),
+            "seed": kp.seed().unwrap(),
+        }),
+        output,
+    )
+}
+
+/// Retrieves a keypair by name in a specified directory, or $WASH_KEYS ($HOME/.wash/keys) if directory is not specified.
+/// Only the public key is output unless `reveal` is set, so encrypted keys are only decrypted to reveal their seed
+pub(crate) fn get(
+    keyname: &str,
+    reveal: bool,
+    directory: Option<PathBuf>,
+    output: &Output,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let keyfile = determine_directory(directory)?.join(keyname);
+    File::open(&keyfile)
+        .map_err(|e| format!("{}.\nPlease ensure {} exists.", e, keyfile.display()))?;
+
+    let info = KeyInfo::read(&keyfile);
+    let (key_type, public_key) = match (info.key_type, info.public_key) {
+        (Some(key_type), Some(public_key)) => (key_type, public_key),
+        _ => return Err(format!("{} does not contain a valid seed", keyfile.display()).into()),
+    };
+    let key_type = keypair_type_to_string(key_type);
+
+    if reveal {
+        let seed = keystore::read_seed(&keyfile)?;
+        Ok(format_output(
+            format!(
+                "Public Key: {}\nSeed: {}\n\nRemember that the seed is private, treat it as a secret.",
+                public_key, seed
+            ),
+            json!({ "key_type": key_type, "public_key": public_key, "seed": seed }),
+            &output.kind,
+        ))
+    } else {
+        Ok(format_output(
+            format!("Public Key: {}\nKey Type: {}", public_key, key_type),
+            json!({ "key_type": key_type, "public_key": public_key }),
+            &output.kind,
+        ))
+    }
+}
+
+/// Lists all keypairs (file extension .nk) in a specified directory or $WASH_KEYS($HOME/.wash/keys) if directory is not specified,
+/// with the type and public key of each
+pub(crate) fn list(
+    directory: Option<PathBuf>,
+    output: &Output,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let dir = determine_directory(directory)?;
+
+    let paths = fs::read_dir(dir.clone()).map_err(|e| {
+        format!(
+            "Error: {}, please ensure directory {} exists",
+            e,
+            dir.display()
+        )
+    })?;
+
+    let mut keys = paths
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".nk"))
+        .map(|entry| KeyInfo::read(&entry.path()))
+        .collect::<Vec<_>>();
+    keys.sort_by(|a, b| a.name.cmp(&b.name));
+
+    match output.kind {
+        OutputKind::Json => Ok(json!({
+            "keys": keys
+                .iter()
+                .map(|key| {
+                    json!({
+                        "name": key.name,
+                        "path": key.path,
+                        "key_type": key.key_type.clone().map(keypair_type_to_string),
+                        "public_key": key.public_key,
+                        "encrypted": key.encrypted,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .to_string()),
+        OutputKind::Text => {
+            let mut table = Table::new();
+            configure_table_style(&mut table);
+            table.add_row(Row::new(vec![
+                TableCell::new_with_alignment("Type", 1, Alignment::Left),
+                TableCell::new_with_alignment("Public Key", 1, Alignment::Left),
+                TableCell::new_with_alignment("Path", 1, Alignment::Left),
+            ]));
+            for key in keys.iter() {
+                let key_type = match (&key.key_type, key.encrypted) {
+                    (Some(kt), false) => keypair_type_to_string(kt.clone()),
+                    (Some(kt), true) => {
+                        format!("{} (encrypted)", keypair_type_to_string(kt.clone()))
+                    }
+                    (None, _) => "invalid".to_string(),
+                };
+                table.add_row(Row::new(vec![
+                    TableCell::new_with_alignment(key_type, 1, Alignment::Left),
+                    TableCell::new_with_alignment(
+                        key.public_key.as_deref().unwrap_or("-"),
+                        1,
+                        Alignment::Left,
+                    ),
+                    TableCell::new_with_alignment(key.path.display(), 1, Alignment::Left),
+                ]));
+            }
+            Ok(format!(
+                "====== Keys found in {} ======\n{}",
+                dir.display(),
+                table.render()
+            ))
+        }
+    }
+}
+
+/// What can be read from a key file without its passphrase. The type and public key are
+/// missing when the file doesn't hold a valid seed
+struct KeyInfo {
+    name: String,
+    path: PathBuf,
+    key_type: Option<KeyPairType>,
+    public_key: Option<String>,
+    encrypted: bool,
+}
+
+impl KeyInfo {
+    fn read(path: &Path) -> KeyInfo {
+        let contents = fs::read_to_string(path).unwrap_or_default();
+        let encrypted = keystore::is_encrypted(&contents);
+        let public_key = if encrypted {
+            keystore::public_key(&contents)
+        } else {
+            KeyPair::from_seed(contents.trim())
+                .ok()
+                .map(|kp| kp.public_key())
+        };
+        KeyInfo {
+            name: path
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: path.to_path_buf(),
+            key_type: public_key.as_deref().and_then(public_key_type),
+            public_key,
+            encrypted,
+        }
+    }
+}
+
+/// Encrypts the plaintext seeds of the named keys, or of all keys in the directory, returning
+/// the names of the keys that were encrypted
+fn encrypt(
+    dir: &Path,
+    keynames: &[String],
+    passphrase: &str,
+) -> Result<Vec<String>, Box<dyn ::std::error::Error>> {
+    // every seed is read before any file is written, so an invalid key changes nothing
+    let mut plaintext = vec![];
+    for (name, path) in key_files(dir, keynames)? {
+        let contents = fs::read_to_string(&path)?;
+        if keystore::is_encrypted(&contents) {
+            continue;
+        }
+        let seed = contents.trim().to_string();
+        KeyPair::from_seed(&seed)
+            .map_err(|e| format!("{} does not contain a valid seed: {}", path.display(), e))?;
+        plaintext.push((name, path, seed));
+    }
+    let mut encrypted = vec![];
+    for (name, path, seed) in plaintext {
+        fs::write(&path, keystore::encrypt_seed(&seed, passphrase)?)?;
+        encrypted.push(name);
+    }
+    Ok(encrypted)
+}
+
+/// Decrypts the encrypted seeds of the named keys, or of all keys in the directory, returning
+/// the names of the keys that were decrypted
+fn decrypt(
+    dir: &Path,
+    keynames: &[String],
+    passphrase: &str,
+) -> Result<Vec<String>, Box<dyn ::std::error::Error>> {
+    let mut seeds = vec![];
+    for (name, path) in key_files(dir, keynames)? {
+        let contents = fs::read_to_string(&path)?;
+        if keystore::is_encrypted(&contents) {
+            let seed = keystore::decrypt_seed(&contents, passphrase)
+                .map_err(|e| format!("Unable to decrypt {}: {}", path.display(), e))?;
+            seeds.push((name, path, seed));
+        }
+    }
+    let mut decrypted = vec![];
+    for (name, path, seed) in seeds {
+        fs::write(&path, seed)?;
+        decrypted.push(name);
+    }
+    Ok(decrypted)
+}
+
+/// The named key files, or all key files (file extension .nk) in the directory
+fn key_files(
+    dir: &Path,
+    keynames: &[String],
+) -> Result<Vec<(String, PathBuf)>, Box<dyn ::std::error::Error>> {
+    if !keynames.is_empty() {
+        return keynames
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                if path.is_file() {
+                    Ok((name.clone(), path))
+                } else {
+                    Err(format!(
+                        "Key {} not found. Please ensure {} exists.",
+                        name,
+                        path.display()
+                    )
+                    .into())
+                }
+            })
+            .collect();
+    }
+    let mut keys = fs::read_dir(dir)
+        .map_err(|e| {
+            format!(
+                "Error: {}, please ensure directory {} exists",
+                e,
+                dir.display()
+            )
+        })?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            (
+                entry.file_name().to_string_lossy().to_string(),
+                entry.path(),
+            )
+        })
+        .filter(|(name, _)| name.ends_with(".nk"))
+        .collect::<Vec<_>>();
+    keys.sort();
+    Ok(keys)
+}
+
+fn key_files_output(action: &str, dir: &Path, keys: Vec<String>, output: &OutputKind) -> String {
+    let text = if keys.is_empty() {
+        format!("No keys to change in {}", dir.display())
+    } else {
+        format!(
+            "{} {} keys in {}\n{}",
+            action,
+            keys.len(),
+            dir.display(),
+            keys.join("\n")
+        )
+    };
+    format_output(text, json!({ action.to_lowercase(): keys }), output)
+}
+
+fn determine_directory(directory: Option<PathBuf>) -> Result<PathBuf, Error> {
+    if let Some(d) = directory {
+        Ok(d)
+    } else {
+        let d = dirs::home_dir()
+            .ok_or_else(|| {
+                Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "$HOME not found, please set $HOME or $WASH_KEYS for autogenerated keys",
+                )
+            })?
+            .join(".wash/keys");
+        Ok(d)
+    }
+}
+
+/// Helper function to locate and extract keypair from user input
+/// Returns a tuple of the keypair and optional autogenerate message
+pub(crate) fn extract_keypair(
+    input: Option<String>,
+    module_path: Option<String>,
+    directory: Option<PathBuf>,
+    keygen_type: KeyPairType,
+    disable_keygen: bool,
+) -> Result<KeyPair, Box<dyn std::error::Error>> {
+    let seed = if let Some(input_str) = input {
+        match File::open(input_str.clone()) {
+            // User provided file path to seed as argument
+            Ok(_) => keystore::read_seed(Path::new(&input_str))?,
+            // User provided seed as an argument
+            Err(_e) => input_str,
+        }
+    } else if let Some(module) = module_path {
+        // No seed value provided, attempting to source from provided or default directory
+        let dir = determine_directory(directory)?;
+        // Account key should be re-used, and will attempt to generate based on the terminal USER
+        let module_name = match keygen_type {
+            KeyPairType::Account => std::env::var("USER").unwrap_or_else(|_| "user".to_string()),
+            _ => PathBuf::from(module)
+                .file_stem()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string(),
+        };
+        let path = dir.join(format!(
+            "{}_{}.nk",
+            module_name,
+            keypair_type_to_string(keygen_type.clone())
+        ));
+        match File::open(path.clone()) {
+            // Default key found
+            Ok(_) => keystore::read_seed(&path)?,
+            // No default key, generating for user
+            Err(_e) if !disable_keygen => {
+                println!(
+                    "{}",
+                    crate::util::format_output(
+                        format!(
+                            "No keypair found in \"{}\".
+We will generate one for you and place it there.
+If you'd like to use alternative keys, you can supply them as a flag.\n",
+                            path.display()
+                        ),
+                        json!({"status": "No keypair found", "path": path, "keygen": "true"}),
+                        &Output::default().kind,
+                    )
+                );
+
+                let kp = KeyPair::new(keygen_type);
+                let seed = kp.seed()?;
+                fs::create_dir_all(Path::new(&path).parent().unwrap())?;
+                keystore::write_seed(&path, &seed)?;
+                seed
+            }
+            _ => {
+                return Err(format!(
+                    "No keypair found in {}, please ensure key exists or supply one as a flag",
+                    path.display()
+                )
+                .into());
+            }
+        }
+    } else {
+        return Err("Keypair path or string not supplied. Ensure provided keypair is valid".into());
+    };
+
+    KeyPair::from_seed(&seed).map_err(|e| format!("{}", e).into())
+}
+
+pub(crate) fn keypair_type_to_string(keypair_type: KeyPairType) -> String {
+    use KeyPairType::*;
+    match keypair_type {
+        Account => "account".to_string(),
+        Cluster => "cluster".to_string(),
+        Service => "service".to_string(),
+        Module => "module".to_string(),
+        Server => "server".to_string(),
+        Operator => "operator".to_string(),
+        User => "user".to_string(),
+    }
+}
+
+/// The first character of encoded public keys of each type
+pub(crate) fn public_key_prefix(keypair_type: &KeyPairType) -> char {
+    use KeyPairType::*;
+    match keypair_type {
+        Account => 'A',
+        Cluster => 'C',
+        Module => 'M',
+        Operator => 'O',
+        Server => 'N',
+        Service => 'V',
+        User => 'U',
+    }
+}
+
+/// The type of a keypair, determined by the prefix of its public key
+pub(crate) fn keypair_type_of(kp: &KeyPair) -> KeyPairType {
+    public_key_type(&kp.public_key()).expect("nkeys only creates keypairs of known types")
+}
+
+/// The type of an encoded public key, determined by its prefix
+fn public_key_type(public_key: &str) -> Option<KeyPairType> {
+    KEY_TYPES
+        .iter()
+        .find(|kt| public_key.starts_with(public_key_prefix(kt)))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{
+        decrypt, encrypt, generate, get, keystore, list, KeysCli, KeysCliCommand, Output,
+        OutputKind, SignatureEncoding,
+    };
+    use nkeys::{KeyPair, KeyPairType};
+    use serde::Deserialize;
+    use structopt::StructOpt;
+
+    #[test]
+    fn test_generate_basic_test() {
+        let kt = KeyPairType::Account;
+
+        let keypair = generate(&kt, &OutputKind::Text);
+        let keypair_json = generate(&kt, &OutputKind::Json);
+
+        assert!(keypair.contains("Public Key: "));
+        assert!(keypair.contains("Seed: "));
+        assert!(keypair.contains("Remember that the seed is private, treat it as a secret."));
+        assert_ne!(keypair, "");
+        assert_ne!(keypair_json, "");
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct KeyPairJson {
+        public_key: String,
+        seed: String,
+    }
+
+    #[test]
+    fn test_generate_valid_keypair() {
+        let sample_public_key = "MBBLAHS7MCGNQ6IR4ZDSGRIAF7NVS7FCKFTKGO5JJJKN2QQRVAH7BSIO";
+        let sample_seed = "SMAH45IUULL57OSX23NOOOTLSVNQOORMDLE3Y3PQLJ4J5MY7MN2K7BIFI4";
+
+        let kt = KeyPairType::Module;
+
+        let keypair_json = generate(&kt, &OutputKind::Json);
+        let keypair: KeyPairJson = serde_json::from_str(&keypair_json).unwrap();
+
+        assert_eq!(keypair.public_key.len(), sample_public_key.len());
+        assert_eq!(keypair.seed.len(), sample_seed.len());
+        assert!(keypair.public_key.starts_with('M'));
+        assert!(keypair.seed.starts_with("SM"));
+    }
+
+    #[test]
+    fn test_generate_all_types() {
+        let sample_public_key = "MBBLAHS7MCGNQ6IR4ZDSGRIAF7NVS7FCKFTKGO5JJJKN2QQRVAH7BSIO";
+        let sample_seed = "SMAH45IUULL57OSXNOOAKOTLSVNQOORMDLE3Y3PQLJ4J5MY7MN2K7BIFI4";
+
+        let account_keypair: KeyPairJson =
+            serde_json::from_str(&generate(&KeyPairType::Account, &OutputKind::Json)).unwrap();
+        let user_keypair: KeyPairJson =
+            serde_json::from_str(&generate(&KeyPairType::User, &OutputKind::Json)).unwrap();
+        let module_keypair: KeyPairJson =
+            serde_json::from_str(&generate(&KeyPairType::Module, &OutputKind::Json)).unwrap();
+        let service_keypair: KeyPairJson =
+            serde_json::from_str(&generate(&KeyPairType::Service, &OutputKind::Json)).unwrap();
+        let server_keypair: KeyPairJson =
+            serde_json::from_str(&generate(&KeyPairType::Server, &OutputKind::Json)).unwrap();
+        let operator_keypair: KeyPairJson =
+            serde_json::from_str(&generate(&KeyPairType::Operator, &OutputKind::Json)).unwrap();
+        let cluster_keypair: KeyPairJson =
+            serde_json::from_str(&generate(&KeyPairType::Cluster, &OutputKind::Json)).unwrap();
+
+        assert!(account_keypair.public_key.starts_with('A'));
+        assert_eq!(account_keypair.public_key.len(), sample_public_key.len());
+        assert!(account_keypair.seed.starts_with("SA"));
+        assert_eq!(account_keypair.seed.len(), sample_seed.len());
+
+        assert!(user_keypair.public_key.starts_with('U'));
+        assert_eq!(user_keypair.public_key.len(), sample_public_key.len());
+        assert!(user_keypair.seed.starts_with("SU"));
+        assert_eq!(user_keypair.seed.len(), sample_seed.len());
+
+        assert!(module_keypair.public_key.starts_with('M'));
+        assert_eq!(module_keypair.public_key.len(), sample_public_key.len());
+        assert!(module_keypair.seed.starts_with("SM"));
+        assert_eq!(module_keypair.seed.len(), sample_seed.len());
+
+        assert!(service_keypair.public_key.starts_with('V'));
+        assert_eq!(service_keypair.public_key.len(), sample_public_key.len());
+        assert!(service_keypair.seed.starts_with("SV"));
+        assert_eq!(service_keypair.seed.len(), sample_seed.len());
+
+        assert!(server_keypair.public_key.starts_with('N'));
+        assert_eq!(server_keypair.public_key.len(), sample_public_key.len());
+        assert!(server_keypair.seed.starts_with("SN"));
+        assert_eq!(server_keypair.seed.len(), sample_seed.len());
+
+        assert!(operator_keypair.public_key.starts_with('O'));
+        assert_eq!(operator_keypair.public_key.len(), sample_public_key.len());
+        assert!(operator_keypair.seed.starts_with("SO"));
+        assert_eq!(operator_keypair.seed.len(), sample_seed.len());
+
+        assert!(cluster_keypair.public_key.starts_with('C'));
+        assert_eq!(cluster_keypair.public_key.len(), sample_public_key.len());
+        assert!(cluster_keypair.seed.starts_with("SC"));
+        assert_eq!(cluster_keypair.seed.len(), sample_seed.len());
+    }
+
+    #[test]
+    /// Enumerates multiple options of the `gen` command to ensure API doesn't
+    /// change between versions. This test will fail if `wash keys gen <type>`
+    /// changes syntax, ordering of required elements, or flags.
+    fn test_gen_comprehensive() {
+        let key_gen_types = vec![
+            "account", "user", "module", "service", "server", "operator", "cluster",
+        ];
+
+        key_gen_types.iter().for_each(|cmd| {
+            let gen_cmd = KeysCli::from_iter(&["keys", "gen", cmd]);
+            match gen_cmd.command {
+                KeysCliCommand::GenCommand {
+                    keytype, output, ..
+                } => {
+                    use KeyPairType::*;
+                    match keytype {
+                        Account => assert_eq!(*cmd, "account"),
+                        User => assert_eq!(*cmd, "user"),
+                        Module => assert_eq!(*cmd, "module"),
+                        Service => assert_eq!(*cmd, "service"),
+                        Server => assert_eq!(*cmd, "server"),
+                        Operator => assert_eq!(*cmd, "operator"),
+                        Cluster => assert_eq!(*cmd, "cluster"),
+                    }
+                    assert_eq!(output.kind, OutputKind::Text);
+                }
+                _ => panic!("`keys gen` constructed incorrect command"),
+            };
+        });
+
+        key_gen_types.iter().for_each(|cmd| {
+            let gen_cmd = KeysCli::from_iter(&["keys", "gen", cmd, "-o", "json"]);
+            match gen_cmd.command {
+                KeysCliCommand::GenCommand {
+                    keytype, output, ..
+                } => {
+                    use KeyPairType::*;
+                    match keytype {
+                        Account => assert_eq!(*cmd, "account"),
+                        User => assert_eq!(*cmd, "user"),
+                        Module => assert_eq!(*cmd, "module"),
+                        Service => assert_eq!(*cmd, "service"),
+                        Server => assert_eq!(*cmd, "server"),
+                        Operator => assert_eq!(*cmd, "operator"),
+                        Cluster => assert_eq!(*cmd, "cluster"),
+                    }
+                    assert_eq!(output.kind, OutputKind::Json);
+                }
+                _ => panic!("`keys gen` constructed incorrect command"),
+            };
+        });
+
+        const ENTROPY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+        let gen_cmd = KeysCli::from_iter(&["keys", "gen", "account", "--from-entropy", ENTROPY]);
+        match gen_cmd.command {
+            KeysCliCommand::GenCommand {
+                keytype,
+                prefix,
+                from_entropy,
+                ..
+            } => {
+                assert!(matches!(keytype, KeyPairType::Account));
+                assert_eq!(prefix, None);
+                assert_eq!(from_entropy.unwrap(), ENTROPY);
+            }
+            _ => panic!("`keys gen` constructed incorrect command"),
+        };
+        let gen_cmd = KeysCli::from_iter(&["keys", "gen", "module", "--prefix", "ACME"]);
+        match gen_cmd.command {
+            KeysCliCommand::GenCommand {
+                prefix,
+                from_entropy,
+                ..
+            } => {
+                assert_eq!(prefix.unwrap(), "ACME");
+                assert_eq!(from_entropy, None);
+            }
+            _ => panic!("`keys gen` constructed incorrect command"),
+        };
+        assert!(KeysCli::from_iter_safe(&[
+            "keys",
+            "gen",
+            "module",
+            "--prefix",
+            "ACME",
+            "--from-entropy",
+            ENTROPY
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_get_basic() {
+        const KEYNAME: &str = "get_basic_test.nk";
+        const KEYPATH: &str = "./tests/fixtures";
+
+        let get_basic = KeysCli::from_iter(&["keys", "get", KEYNAME, "--directory", KEYPATH]);
+        match get_basic.command {
+            KeysCliCommand::GetCommand { keyname, .. } => assert_eq!(keyname, KEYNAME),
+            other_cmd => panic!("keys get generated other command {:?}", other_cmd),
+        }
+    }
+
+    #[test]
+    /// Enumerates multiple options of the `get` command to ensure API doesn't
+    /// change between versions. This test will fail if `wash keys get`
+    /// changes syntax, ordering of required elements, or flags.
+    fn test_get_comprehensive() {
+        const KEYPATH: &str = "./tests/fixtures";
+        const KEYNAME: &str = "get_comprehensive_test.nk";
+
+        let get_all_flags =
+            KeysCli::from_iter(&["keys", "get", KEYNAME, "-d", KEYPATH, "-o", "json"]);
+        match get_all_flags.command {
+            KeysCliCommand::GetCommand {
+                keyname,
+                reveal,
+                directory,
+                output,
+            } => {
+                assert_eq!(keyname, KEYNAME);
+                assert!(!reveal);
+                assert_eq!(directory, Some(PathBuf::from(KEYPATH)));
+                assert_eq!(output.kind, OutputKind::Json);
+            }
+            other_cmd => panic!("keys get generated other command {:?}", other_cmd),
+        }
+
+        let get_reveal = KeysCli::from_iter(&["keys", "get", KEYNAME, "--reveal"]);
+        match get_reveal.command {
+            KeysCliCommand::GetCommand {
+                keyname, reveal, ..
+            } => {
+                assert_eq!(keyname, KEYNAME);
+                assert!(reveal);
+            }
+            other_cmd => panic!("keys get generated other command {:?}", other_cmd),
+        }
+    }
+
+    #[test]
+    /// Enumerates multiple options of the `list` command to ensure API doesn't
+    /// change between versions. This test will fail if `wash keys list`
+    /// changes syntax, ordering of required elements, or flags.
+    fn test_list_comprehensive() {
+        const KEYPATH: &str = "./";
+
+        let list_basic = KeysCli::from_iter(&["keys", "list", "-d", KEYPATH]);
+        match list_basic.command {
+            KeysCliCommand::ListCommand { .. } => (),
+            other_cmd => panic!("keys get generated other command {:?}", other_cmd),
+        }
+
+        let list_all_flags = KeysCli::from_iter(&["keys", "list", "-d", KEYPATH, "-o", "json"]);
+        match list_all_flags.command {
+            KeysCliCommand::ListCommand { directory, output } => {
+                assert_eq!(directory, Some(PathBuf::from(KEYPATH)));
+                assert_eq!(output.kind, OutputKind::Json);
+            }
+            other_cmd => panic!("keys get generated other command {:?}", other_cmd),
+        }
+    }
+
+    #[test]
+    fn test_get_list() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let account = KeyPair::new_account();
+        let module = KeyPair::new_module();
+        std::fs::write(dir.path().join("user_account.nk"), account.seed()?)?;
+        std::fs::write(
+            dir.path().join("echo_module.nk"),
+            keystore::encrypt_seed(&module.seed()?, "hunter2")?,
+        )?;
+        std::fs::write(dir.path().join("broken.nk"), "not a seed")?;
+        let json = Output {
+            kind: OutputKind::Json,
+        };
+
+        let got: serde_json::Value = serde_json::from_str(&get(
+            "user_account.nk",
+            false,
+            Some(dir.path().to_path_buf()),
+            &json,
+        )?)?;
+        assert_eq!(got["public_key"], account.public_key());
+        assert_eq!(got["key_type"], "account");
+        assert!(got.get("seed").is_none());
+        let got: serde_json::Value = serde_json::from_str(&get(
+            "user_account.nk",
+            true,
+            Some(dir.path().to_path_buf()),
+            &json,
+        )?)?;
+        assert_eq!(got["seed"], account.seed()?);
+        // the public key of an encrypted key is available without the passphrase
+        let got: serde_json::Value = serde_json::from_str(&get(
+            "echo_module.nk",
+            false,
+            Some(dir.path().to_path_buf()),
+            &json,
+        )?)?;
+        assert_eq!(got["public_key"], module.public_key());
+        assert!(get("broken.nk", false, Some(dir.path().to_path_buf()), &json).is_err());
+
+        let listed: serde_json::Value =
+            serde_json::from_str(&list(Some(dir.path().to_path_buf()), &json)?)?;
+        let keys = listed["keys"].as_array().unwrap();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[0]["name"], "broken.nk");
+        assert!(keys[0]["public_key"].is_null());
+        assert_eq!(keys[1]["name"], "echo_module.nk");
+        assert_eq!(keys[1]["key_type"], "module");
+        assert_eq!(keys[1]["public_key"], module.public_key());
+        assert_eq!(keys[1]["encrypted"], true);
+        assert_eq!(keys[2]["name"], "user_account.nk");
+        assert_eq!(keys[2]["public_key"], account.public_key());
+        assert_eq!(keys[2]["encrypted"], false);
+
+        let text = list(
+            Some(dir.path().to_path_buf()),
+            &Output {
+                kind: OutputKind::Text,
+            },
+        )?;
+        assert!(text.contains("module (encrypted)"));
+        assert!(text.contains(&account.public_key()));
+        assert!(text.contains("invalid"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let account = KeyPair::new_account().seed()?;
+        let module = KeyPair::new_module().seed()?;
+        std::fs::write(dir.path().join("user_account.nk"), &account)?;
+        std::fs::write(dir.path().join("echo_module.nk"), format!("{}\n", module))?;
+        std::fs::write(dir.path().join("notes.txt"), "not a key")?;
+
+        assert_eq!(
+            encrypt(dir.path(), &["echo_module.nk".to_string()], "hunter2")?,
+            vec!["echo_module.nk"]
+        );
+        // keys that are already encrypted are skipped
+        assert_eq!(
+            encrypt(dir.path(), &[], "hunter2")?,
+            vec!["user_account.nk"]
+        );
+        assert!(encrypt(dir.path(), &[], "hunter2")?.is_empty());
+        let contents = std::fs::read_to_string(dir.path().join("echo_module.nk"))?;
+        assert!(keystore::is_encrypted(&contents));
+        assert_eq!(keystore::decrypt_seed(&contents, "hunter2")?, module);
+
+        // nothing is decrypted with the wrong passphrase
+        assert!(decrypt(dir.path(), &[], "hunter3").is_err());
+        assert_eq!(
+            decrypt(dir.path(), &[], "hunter2")?,
+            vec!["echo_module.nk", "user_account.nk"]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("user_account.nk"))?,
+            account
+        );
+
+        assert!(encrypt(dir.path(), &["missing.nk".to_string()], "hunter2").is_err());
+        std::fs::write(dir.path().join("broken.nk"), "not a seed")?;
+        assert!(encrypt(dir.path(), &[], "hunter2").is_err());
+        // an invalid key leaves the other keys untouched
+        assert!(!keystore::has_encrypted_keys(dir.path()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_export_rename_delete_comprehensive() {
+        const KEYPATH: &str = "./";
+        const SEED: &str = "SMAH45IUULL57OSX23NOOOTLSVNQOORMDLE3Y3PQLJ4J5MY7MN2K7BIFI4";
+
+        let import = KeysCli::from_iter(&[
+            "keys", "import", "echo", SEED, "--type", "module", "-d", KEYPATH, "-o", "json",
+        ]);
+        match import.command {
+            KeysCliCommand::ImportCommand {
+                keyname,
+                seed,
+                keytype,
+                directory,
+                output,
+            } => {
+                assert_eq!(keyname, "echo");
+                assert_eq!(seed, SEED);
+                assert!(matches!(keytype, Some(KeyPairType::Module)));
+                assert_eq!(directory, Some(PathBuf::from(KEYPATH)));
+                assert_eq!(output.kind, OutputKind::Json);
+            }
+            other_cmd => panic!("keys import generated other command {:?}", other_cmd),
+        }
+
+        let export = KeysCli::from_iter(&[
+            "keys",
+            "export",
+            "echo_module",
+            "--destination",
+            "./echo.json",
+            "-d",
+            KEYPATH,
+        ]);
+        match export.command {
+            KeysCliCommand::ExportCommand {
+                keyname,
+                destination,
+                directory,
+                ..
+            } => {
+                assert_eq!(keyname, "echo_module");
+                assert_eq!(destination, Some(PathBuf::from("./echo.json")));
+                assert_eq!(directory, Some(PathBuf::from(KEYPATH)));
+            }
+            other_cmd => panic!("keys export generated other command {:?}", other_cmd),
+        }
+
+        let rename = KeysCli::from_iter(&["keys", "rename", "echo_module", "hello_module"]);
+        match rename.command {
+            KeysCliCommand::RenameCommand {
+                keyname, new_name, ..
+            } => {
+                assert_eq!(keyname, "echo_module");
+                assert_eq!(new_name, "hello_module");
+            }
+            other_cmd => panic!("keys rename generated other command {:?}", other_cmd),
+        }
+
+        let delete = KeysCli::from_iter(&["keys", "delete", "hello_module", "--yes"]);
+        match delete.command {
+            KeysCliCommand::DeleteCommand { keyname, yes, .. } => {
+                assert_eq!(keyname, "hello_module");
+                assert!(yes);
+            }
+            other_cmd => panic!("keys delete generated other command {:?}", other_cmd),
+        }
+    }
+
+    #[test]
+    /// Enumerates multiple options of the `rotate` command to ensure API doesn't
+    /// change between versions. This test will fail if `wash keys rotate`
+    /// changes syntax, ordering of required elements, or flags.
+    fn test_rotate_comprehensive() {
+        const KEYPATH: &str = "./";
+
+        let rotate = KeysCli::from_iter(&[
+            "keys",
+            "rotate",
+            "acme_account",
+            "--resign",
+            "./echo_s.wasm",
+            "--resign",
+            "./httpserver.par.gz",
+            "--issuer",
+            "./acme_account.nk",
+            "--operator-token",
+            "./operator.jwt",
+            "--operator-key",
+            "./ops_operator.nk",
+            "-d",
+            KEYPATH,
+            "-o",
+            "json",
+        ]);
+        match rotate.command {
+            KeysCliCommand::RotateCommand {
+                keyname,
+                resign,
+                issuer,
+                operator_token,
+                operator_key,
+                directory,
+                output,
+            } => {
+                assert_eq!(keyname, "acme_account");
+                assert_eq!(
+                    resign,
+                    vec![
+                        PathBuf::from("./echo_s.wasm"),
+                        PathBuf::from("./httpserver.par.gz")
+                    ]
+                );
+                assert_eq!(issuer.unwrap(), "./acme_account.nk");
+                assert_eq!(operator_token, Some(PathBuf::from("./operator.jwt")));
+                assert_eq!(operator_key.unwrap(), "./ops_operator.nk");
+                assert_eq!(directory, Some(PathBuf::from(KEYPATH)));
+                assert_eq!(output.kind, OutputKind::Json);
+            }
+            other_cmd => panic!("keys rotate generated other command {:?}", other_cmd),
+        }
+
+        // the operator token can only be updated with the operator key
+        assert!(KeysCli::from_iter_safe(&[
+            "keys",
+            "rotate",
+            "acme_account",
+            "--operator-token",
+            "./operator.jwt"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    /// Enumerates multiple options of the `sign` and `verify` commands to ensure API doesn't
+    /// change between versions. This test will fail if `wash keys sign` or `wash keys verify`
+    /// changes syntax, ordering of required elements, or flags.
+    fn test_sign_verify_comprehensive() {
+        const KEYPATH: &str = "./";
+        const PUBLIC_KEY: &str = "MCAYXRPJ3Z6NWUWFOIAB3CD26VDWONPKU374RJ2PJIVZNQXAIGWKWWNO";
+
+        let sign = KeysCli::from_iter(&[
+            "keys",
+            "sign",
+            "acme_account",
+            "config.json",
+            "--encoding",
+            "hex",
+            "--destination",
+            "config.json.sig",
+            "-d",
+            KEYPATH,
+            "-o",
+            "json",
+        ]);
+        match sign.command {
+            KeysCliCommand::SignCommand {
+                keyname,
+                file,
+                encoding,
+                destination,
+                directory,
+                output,
+            } => {
+                assert_eq!(keyname, "acme_account");
+                assert_eq!(file, "config.json");
+                assert_eq!(encoding, SignatureEncoding::Hex);
+                assert_eq!(destination, Some(PathBuf::from("config.json.sig")));
+                assert_eq!(directory, Some(PathBuf::from(KEYPATH)));
+                assert_eq!(output.kind, OutputKind::Json);
+            }
+            other_cmd => panic!("keys sign generated other command {:?}", other_cmd),
+        }
+
+        let verify = KeysCli::from_iter(&[
+            "keys",
+            "verify",
+            PUBLIC_KEY,
+            "-",
+            "config.json.sig",
+            "-e",
+            "base64",
+        ]);
+        match verify.command {
+            KeysCliCommand::VerifyCommand {
+                public_key,
+                file,
+                signature,
+                encoding,
+                output,
+            } => {
+                assert_eq!(public_key, PUBLIC_KEY);
+                assert_eq!(file, "-");
+                assert_eq!(signature, "config.json.sig");
+                assert_eq!(encoding, SignatureEncoding::Base64);
+                assert_eq!(output.kind, OutputKind::Text);
+            }
+            other_cmd => panic!("keys verify generated other command {:?}", other_cmd),
+        }
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_comprehensive() {
+        const KEYPATH: &str = "./";
+
+        let encrypt_all = KeysCli::from_iter(&["keys", "encrypt", "-d", KEYPATH, "-o", "json"]);
+        match encrypt_all.command {
+            KeysCliCommand::EncryptCommand {
+                keynames,
+                directory,
+                output,
+            } => {
+                assert!(keynames.is_empty());
+                assert_eq!(directory, Some(PathBuf::from(KEYPATH)));
+                assert_eq!(output.kind, OutputKind::Json);
+            }
+            other_cmd => panic!("keys encrypt generated other command {:?}", other_cmd),
+        }
+
+        let decrypt_named = KeysCli::from_iter(&[
+            "keys",
+            "decrypt",
+            "user_account.nk",
+            "echo_module.nk",
+            "--directory",
+            KEYPATH,
+        ]);
+        match decrypt_named.command {
+            KeysCliCommand::DecryptCommand {
+                keynames,
+                directory,
+                output,
+            } => {
+                assert_eq!(keynames, vec!["user_account.nk", "echo_module.nk"]);
+                assert_eq!(directory, Some(PathBuf::from(KEYPATH)));
+                assert_eq!(output.kind, OutputKind::Text);
+            }
+            other_cmd => panic!("keys decrypt generated other command {:?}", other_cmd),
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Rotating a key: replacing it with a new keypair of the same type, archiving the old key, and
+//! updating the artifacts and operator token that refer to it
+use super::manage::{existing_key, file_name};
+use super::{extract_keypair, keypair_type_to_string, keystore, public_key_prefix, KeyInfo};
+use crate::claims::WASM_MAGIC;
+use crate::util::{configure_table_style, OutputKind};
+use nkeys::{KeyPair, KeyPairType};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use term_table::{
+    row::Row,
+    table_cell::{Alignment, TableCell},
+    Table,
+};
+use wascap::jwt::{CapabilityProvider, Claims, Operator};
+use wascap::wasm::{embed_claims, extract_claims};
+
+/// Old keys are kept in this subdirectory of the keys directory
+const ARCHIVE_DIR: &str = "archive";
+
+/// Replaces a key with a new keypair of the same type, archiving the old key. The listed
+/// modules and provider archives are re-signed, with the new key as their issuer or subject,
+/// and the new key replaces the old one among the additional keys of the operator token.
+/// Everything is checked before anything is written, so a failed rotation changes nothing
+pub(crate) fn rotate(
+    dir: &Path,
+    keyname: &str,
+    artifacts: &[PathBuf],
+    issuer: Option<String>,
+    operator_token: Option<PathBuf>,
+    operator_key: Option<String>,
+    output: &OutputKind,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let path = existing_key(dir, keyname)?;
+    let info = KeyInfo::read(&path);
+    let (key_type, old_key) = match (info.key_type, info.public_key) {
+        (Some(key_type), Some(public_key)) => (key_type, public_key),
+        _ => return Err(format!("{} does not contain a valid seed", path.display()).into()),
+    };
+    let new_kp = KeyPair::new(key_type.clone());
+    let issuer = issuer
+        .map(|i| extract_keypair(Some(i), None, None, KeyPairType::Account, true))
+        .transpose()?;
+
+    let mut changes = vec![];
+    for artifact in artifacts {
+        let bytes = fs::read(artifact)
+            .map_err(|e| format!("Failed to read {}: {}", artifact.display(), e))?;
+        let resigned = if bytes.starts_with(WASM_MAGIC) {
+            resign_module(&bytes, &old_key, &new_kp, issuer.as_ref())
+        } else {
+            let mut change = "";
+            crate::par::replace_archive_claims(&bytes, |jwt| {
+                let mut claims = Claims::<CapabilityProvider>::decode(jwt)?;
+                let (kp, c) = rotate_claims(&mut claims, &old_key, &new_kp, issuer.as_ref())?;
+                change = c;
+                Ok(claims.encode(kp)?)
+            })
+            .map(|resigned| (resigned, change))
+        };
+        let (resigned, change) =
+            resigned.map_err(|e| format!("Unable to re-sign {}: {}", artifact.display(), e))?;
+        changes.push((artifact.clone(), resigned, change.to_string()));
+    }
+    if let (Some(token_path), Some(operator_key)) = (operator_token, operator_key) {
+        let (token, change) = update_operator_token(
+            &fs::read_to_string(&token_path)?,
+            &old_key,
+            &new_kp.public_key(),
+            &extract_keypair(Some(operator_key), None, None, KeyPairType::Operator, true)?,
+        )
+        .map_err(|e| format!("Unable to update {}: {}", token_path.display(), e))?;
+        changes.push((token_path, token.into_bytes(), change));
+    }
+
+    let archived = archive(dir, &path)?;
+    keystore::write_seed(&path, &new_kp.seed()?)?;
+    for (artifact, contents, _) in changes.iter() {
+        fs::write(artifact, contents)?;
+    }
+
+    let key_type = keypair_type_to_string(key_type);
+    Ok(match output {
+        OutputKind::Json => json!({
+            "result": "success",
+            "key": file_name(&path),
+            "key_type": key_type,
+            "old_public_key": old_key,
+            "new_public_key": new_kp.public_key(),
+            "archived": archived,
+            "changes": changes
+                .iter()
+                .map(|(path, _, change)| json!({ "path": path, "change": change }))
+                .collect::<Vec<_>>(),
+        })
+        .to_string(),
+        OutputKind::Text => {
+            let mut report = format!(
+                "Rotated {} key {}\nOld Public Key: {}\nNew Public Key: {}\nArchived old key to {}",
+                key_type,
+                file_name(&path),
+                old_key,
+                new_kp.public_key(),
+                archived.display()
+            );
+            if !changes.is_empty() {
+                let mut table = Table::new();
+                configure_table_style(&mut table);
+                table.add_row(Row::new(vec![
+                    TableCell::new_with_alignment("Updated", 1, Alignment::Left),
+                    TableCell::new_with_alignment("Change", 1, Alignment::Left),
+                ]));
+                for (path, _, change) in changes.iter() {
+                    table.add_row(Row::new(vec![
+                        TableCell::new_with_alignment(path.display(), 1, Alignment::Left),
+                        TableCell::new_with_alignment(change, 1, Alignment::Left),
+                    ]));
+                }
+                report.push('\n');
+                report.push_str(&table.render());
+            }
+            report
+        }
+    })
+}
+
+fn resign_module(
+    bytes: &[u8],
+    old_key: &str,
+    new_kp: &KeyPair,
+    issuer: Option<&KeyPair>,
+) -> Result<(Vec<u8>, &'static str), Box<dyn ::std::error::Error>> {
+    let mut claims = extract_claims(bytes)?
+        .ok_or("no claims found in module")?
+        .claims;
+    let (kp, change) = rotate_claims(&mut claims, old_key, new_kp, issuer)?;
+    Ok((embed_claims(bytes, &claims, kp)?, change))
+}
+
+/// Replaces the rotated key as the issuer or subject of the claims, returning the key that
+/// must sign them: the new key when it's the issuer, and the existing issuer otherwise
+fn rotate_claims<'a, T>(
+    claims: &mut Claims<T>,
+    old_key: &str,
+    new_kp: &'a KeyPair,
+    issuer: Option<&'a KeyPair>,
+) -> Result<(&'a KeyPair, &'static str), Box<dyn ::std::error::Error>> {
+    let rotated = if claims.issuer == old_key {
+        claims.issuer = new_kp.public_key();
+        (new_kp, "re-signed by the new key")
+    } else if claims.subject == old_key {
+        let issuer = issuer.ok_or_else(|| {
+            format!(
+                "it was issued for the rotated key, use --issuer to re-sign it with the key of its issuer {}",
+                claims.issuer
+            )
+        })?;
+        if issuer.public_key() != claims.issuer {
+            return Err(format!(
+                "it was signed by {}, not {}",
+                claims.issuer,
+                issuer.public_key()
+            )
+            .into());
+        }
+        claims.subject = new_kp.public_key();
+        (issuer, "re-issued for the new key")
+    } else {
+        return Err(format!("it was not signed by or issued for {}", old_key).into());
+    };
+    claims.issued_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok(rotated)
+}
+
+/// Replaces the old key with the new one among the additional keys of an operator token,
+/// adding the new key if the old one isn't listed, and signs the token again
+fn update_operator_token(
+    token: &str,
+    old_key: &str,
+    new_key: &str,
+    operator_kp: &KeyPair,
+) -> Result<(String, String), Box<dyn ::std::error::Error>> {
+    let mut claims = Claims::<Operator>::decode(token.trim())?;
+    if !claims
+        .subject
+        .starts_with(public_key_prefix(&KeyPairType::Operator))
+    {
+        return Err("token is not an operator token".into());
+    }
+    if claims.subject == old_key || claims.issuer == old_key {
+        return Err(
+            "The rotated key is the operator's own key, use `wash claims token operator` to generate a new token"
+                .into(),
+        );
+    }
+    if operator_kp.public_key() != claims.issuer {
+        return Err(format!(
+            "token was signed by {}, not {}",
+            claims.issuer,
+            operator_kp.public_key()
+        )
+        .into());
+    }
+
+    let mut metadata = claims.metadata.clone().unwrap_or_default();
+    let mut keys = metadata.valid_signers.unwrap_or_default();
+    let change = match keys.iter().position(|k| k == old_key) {
+        Some(i) => {
+            keys[i] = new_key.to_string();
+            "replaced the old key among the additional keys"
+        }
+        None => {
+            keys.push(new_key.to_string());
+            "added the new key to the additional keys"
+        }
+    };
+    metadata.valid_signers = Some(keys);
+    claims.metadata = Some(metadata);
+    claims.issued_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok((claims.encode(operator_kp)?, change.to_string()))
+}
+
+/// Copies a key file, as-is, to the archive directory, naming it after the time it was rotated
+fn archive(dir: &Path, path: &Path) -> Result<PathBuf, Box<dyn ::std::error::Error>> {
+    let archive_dir = dir.join(ARCHIVE_DIR);
+    fs::create_dir_all(&archive_dir)?;
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let archived = archive_dir.join(format!(
+        "{}.{}.nk",
+        stem,
+        SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
+    ));
+    if archived.exists() {
+        return Err(format!(
+            "{} already exists, a key can only be rotated once per second",
+            archived.display()
+        )
+        .into());
+    }
+    fs::copy(path, &archived)?;
+    Ok(archived)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use wascap::jwt::{Account, Actor};
+
+    fn signed_module(account: &KeyPair, module: &KeyPair) -> Vec<u8> {
+        // the smallest valid module: the magic number and version
+        let wasm = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        let claims = Claims::<Actor>::new(
+            "Test".to_string(),
+            account.public_key(),
+            module.public_key(),
+            Some(vec!["wasmcloud:test".to_string()]),
+            None,
+            false,
+            None,
+            None,
+            None,
+        );
+        embed_claims(&wasm, &claims, account).unwrap()
+    }
+
+    fn module_claims(path: &Path) -> Claims<Actor> {
+        extract_claims(fs::read(path).unwrap())
+            .unwrap()
+            .unwrap()
+            .claims
+    }
+
+    #[test]
+    fn test_rotate_account() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let account = KeyPair::new_account();
+        let module = KeyPair::new_module();
+        let key = dir.path().join("acme_account.nk");
+        fs::write(&key, account.seed()?)?;
+        let artifact = dir.path().join("echo_s.wasm");
+        fs::write(&artifact, signed_module(&account, &module))?;
+
+        let operator = KeyPair::new_operator();
+        let token_path = dir.path().join("operator.jwt");
+        let token = Claims::<Operator>::new(
+            "Ops".to_string(),
+            operator.public_key(),
+            operator.public_key(),
+            vec![account.public_key()],
+        );
+        fs::write(&token_path, token.encode(&operator)?)?;
+
+        let report: serde_json::Value = serde_json::from_str(&rotate(
+            dir.path(),
+            "acme_account",
+            std::slice::from_ref(&artifact),
+            None,
+            Some(token_path.clone()),
+            Some(operator.seed()?),
+            &OutputKind::Json,
+        )?)?;
+        let new_key = report["new_public_key"].as_str().unwrap().to_string();
+        assert_eq!(report["old_public_key"], account.public_key());
+        assert_eq!(report["changes"].as_array().unwrap().len(), 2);
+
+        let rotated = KeyPair::from_seed(fs::read_to_string(&key)?.trim())?;
+        assert_eq!(rotated.public_key(), new_key);
+        assert!(new_key.starts_with('A'));
+        let archived = PathBuf::from(report["archived"].as_str().unwrap());
+        assert_eq!(fs::read_to_string(archived)?, account.seed()?);
+
+        let claims = module_claims(&artifact);
+        assert_eq!(claims.issuer, new_key);
+        assert_eq!(claims.subject, module.public_key());
+        let token = Claims::<Operator>::decode(&fs::read_to_string(&token_path)?)?;
+        assert_eq!(token.metadata.unwrap().valid_signers, Some(vec![new_key]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotate_provider_archive() -> Result<(), Box<dyn ::std::error::Error>> {
+        use crate::util::convert_error;
+        use provider_archive::ProviderArchive;
+
+        let dir = tempfile::tempdir()?;
+        let account = KeyPair::new_account();
+        let service = KeyPair::new_service();
+        fs::write(dir.path().join("acme_account.nk"), account.seed()?)?;
+        let mut par = ProviderArchive::new("wasmcloud:test", "Test", "Acme", None, None);
+        par.add_library("x86_64-linux", b"not really a library")
+            .map_err(convert_error)?;
+        let artifact = dir.path().join("test.par.gz");
+        par.write(artifact.to_str().unwrap(), &account, &service, true)
+            .map_err(convert_error)?;
+
+        rotate(
+            dir.path(),
+            "acme_account",
+            std::slice::from_ref(&artifact),
+            None,
+            None,
+            None,
+            &OutputKind::Text,
+        )?;
+        let rotated =
+            KeyPair::from_seed(fs::read_to_string(dir.path().join("acme_account.nk"))?.trim())?;
+        // the archive is still compressed, and its claims still match its libraries
+        let par = ProviderArchive::try_load(&fs::read(&artifact)?).map_err(convert_error)?;
+        let claims = par.claims().unwrap();
+        assert_eq!(claims.issuer, rotated.public_key());
+        assert_eq!(claims.subject, service.public_key());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotate_module() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let account = KeyPair::new_account();
+        let module = KeyPair::new_module();
+        let key = dir.path().join("echo_module.nk");
+        fs::write(&key, module.seed()?)?;
+        let artifact = dir.path().join("echo_s.wasm");
+        let signed = signed_module(&account, &module);
+        fs::write(&artifact, &signed)?;
+
+        // modules issued for the rotated key are re-signed by their issuer
+        assert!(rotate(
+            dir.path(),
+            "echo_module",
+            std::slice::from_ref(&artifact),
+            None,
+            None,
+            None,
+            &OutputKind::Text
+        )
+        .is_err());
+        assert!(rotate(
+            dir.path(),
+            "echo_module",
+            std::slice::from_ref(&artifact),
+            Some(KeyPair::new_account().seed()?),
+            None,
+            None,
+            &OutputKind::Text
+        )
+        .is_err());
+        // nothing changes when a rotation fails
+        assert_eq!(fs::read_to_string(&key)?, module.seed()?);
+        assert_eq!(fs::read(&artifact)?, signed);
+        assert!(!dir.path().join(ARCHIVE_DIR).exists());
+
+        let report = rotate(
+            dir.path(),
+            "echo_module.nk",
+            std::slice::from_ref(&artifact),
+            Some(account.seed()?),
+            None,
+            None,
+            &OutputKind::Text,
+        )?;
+        assert!(report.contains("re-issued for the new key"));
+        let rotated = KeyPair::from_seed(fs::read_to_string(&key)?.trim())?;
+        let claims = module_claims(&artifact);
+        assert_eq!(claims.issuer, account.public_key());
+        assert_eq!(claims.subject, rotated.public_key());
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_operator_token() -> Result<(), Box<dyn ::std::error::Error>> {
+        let operator = KeyPair::new_operator();
+        let signer = KeyPair::new_operator();
+        let token = Claims::<Operator>::new(
+            "Ops".to_string(),
+            operator.public_key(),
+            operator.public_key(),
+            vec![],
+        )
+        .encode(&operator)?;
+
+        let (updated, change) =
+            update_operator_token(&token, &signer.public_key(), "ONEW", &operator)?;
+        assert_eq!(change, "added the new key to the additional keys");
+        let claims = Claims::<Operator>::decode(&updated)?;
+        assert_eq!(
+            claims.metadata.unwrap().valid_signers,
+            Some(vec!["ONEW".to_string()])
+        );
+
+        // the operator's own key can't be rotated into its token
+        assert!(update_operator_token(&token, &operator.public_key(), "ONEW", &operator).is_err());
+        // the token must be signed again by its issuer
+        assert!(update_operator_token(&token, &signer.public_key(), "ONEW", &signer).is_err());
+        // accounts aren't operator tokens
+        let account = KeyPair::new_account();
+        let account_token = Claims::<Account>::new(
+            "Acme".to_string(),
+            operator.public_key(),
+            account.public_key(),
+            vec![],
+        )
+        .encode(&operator)?;
+        assert!(
+            update_operator_token(&account_token, &signer.public_key(), "ONEW", &operator).is_err()
+        );
+        Ok(())
+    }
+}
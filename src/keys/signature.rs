@@ -0,0 +1,266 @@
+//! Detached Ed25519 signatures of arbitrary payloads, such as configuration files
+use super::keystore;
+use super::manage::{existing_key, file_name};
+use crate::signer::{decode_base64, ED25519_SIGNATURE_LEN};
+use crate::util::{format_output, CommandFailure, OutputKind};
+use nkeys::KeyPair;
+use serde_json::json;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Payloads are read from stdin when the file is `-`
+const STDIN: &str = "-";
+
+/// Text encodings of signatures
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum SignatureEncoding {
+    Base64,
+    Hex,
+}
+
+impl FromStr for SignatureEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "base64" => Ok(SignatureEncoding::Base64),
+            "hex" => Ok(SignatureEncoding::Hex),
+            _ => Err(format!(
+                "Unknown signature encoding {}, expected base64 or hex",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SignatureEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureEncoding::Base64 => write!(f, "base64"),
+            SignatureEncoding::Hex => write!(f, "hex"),
+        }
+    }
+}
+
+impl SignatureEncoding {
+    fn encode(&self, sig: &[u8]) -> String {
+        match self {
+            SignatureEncoding::Base64 => data_encoding::BASE64.encode(sig),
+            SignatureEncoding::Hex => data_encoding::HEXLOWER.encode(sig),
+        }
+    }
+
+    /// Decodes a signature, accepting either base64 alphabet and hex in either case
+    fn decode(&self, sig: &str) -> Result<Vec<u8>, Box<dyn ::std::error::Error>> {
+        let sig = sig.trim();
+        let decoded = match self {
+            SignatureEncoding::Base64 => decode_base64(sig).map_err(|e| e.to_string()),
+            SignatureEncoding::Hex => data_encoding::HEXLOWER_PERMISSIVE
+                .decode(sig.as_bytes())
+                .map_err(|e| e.to_string()),
+        }
+        .map_err(|e| format!("Signature is not valid {}: {}", self, e))?;
+        if decoded.len() != ED25519_SIGNATURE_LEN {
+            return Err(format!(
+                "Signature is {} bytes long, expected a {} byte Ed25519 signature",
+                decoded.len(),
+                ED25519_SIGNATURE_LEN
+            )
+            .into());
+        }
+        Ok(decoded)
+    }
+}
+
+/// Signs the contents of a file with a key from the keys directory, printing the signature or
+/// writing it to the destination file
+pub(crate) fn sign(
+    dir: &Path,
+    keyname: &str,
+    file: &str,
+    encoding: SignatureEncoding,
+    destination: Option<PathBuf>,
+    output: &OutputKind,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let path = existing_key(dir, keyname)?;
+    let kp = KeyPair::from_seed(&keystore::read_seed(&path)?)
+        .map_err(|e| format!("{} does not contain a valid seed: {}", path.display(), e))?;
+    let sig = encoding.encode(&kp.sign(&read_payload(file)?)?);
+
+    let json = json!({
+        "signature": sig,
+        "encoding": encoding.to_string(),
+        "public_key": kp.public_key(),
+    });
+    match destination {
+        Some(destination) => {
+            fs::write(&destination, &sig)?;
+            Ok(format_output(
+                format!(
+                    "Signed {} with key {} ({}), signature written to {}",
+                    file,
+                    file_name(&path),
+                    kp.public_key(),
+                    destination.display()
+                ),
+                json,
+                output,
+            ))
+        }
+        None => Ok(format_output(sig, json, output)),
+    }
+}
+
+/// Checks a signature of the contents of a file. The signature can be given directly or as a
+/// file containing it. An invalid signature is reported as a command failure
+pub(crate) fn verify(
+    public_key: &str,
+    file: &str,
+    signature: &str,
+    encoding: SignatureEncoding,
+    output: &OutputKind,
+) -> Result<String, Box<dyn ::std::error::Error>> {
+    let kp = KeyPair::from_public_key(public_key)
+        .map_err(|e| format!("Invalid public key {}: {}", public_key, e))?;
+    let sig = if Path::new(signature).is_file() {
+        fs::read_to_string(signature)?
+    } else {
+        signature.to_string()
+    };
+    let sig = encoding.decode(&sig)?;
+    let valid = kp.verify(&read_payload(file)?, &sig).is_ok();
+
+    let result = format_output(
+        if valid {
+            format!("Signature of {} by {} is valid", file, public_key)
+        } else {
+            format!("Signature of {} by {} is not valid", file, public_key)
+        },
+        json!({ "valid": valid, "public_key": public_key }),
+        output,
+    );
+    if valid {
+        Ok(result)
+    } else {
+        Err(CommandFailure(result).into())
+    }
+}
+
+fn read_payload(file: &str) -> Result<Vec<u8>, Box<dyn ::std::error::Error>> {
+    if file == STDIN {
+        let mut payload = Vec::new();
+        std::io::stdin().read_to_end(&mut payload)?;
+        Ok(payload)
+    } else {
+        fs::read(file).map_err(|e| format!("Failed to read {}: {}", file, e).into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sign_verify() -> Result<(), Box<dyn ::std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let account = KeyPair::new_account();
+        fs::write(dir.path().join("acme_account.nk"), account.seed()?)?;
+        let payload = dir.path().join("config.json");
+        fs::write(&payload, r#"{"replicas": 3}"#)?;
+        let payload = payload.to_str().unwrap();
+
+        for encoding in [SignatureEncoding::Base64, SignatureEncoding::Hex] {
+            let sig = sign(
+                dir.path(),
+                "acme_account",
+                payload,
+                encoding,
+                None,
+                &OutputKind::Text,
+            )?;
+            let public_key = account.public_key();
+            assert!(verify(&public_key, payload, &sig, encoding, &OutputKind::Text).is_ok());
+            // hex is case-insensitive, and base64 may use either alphabet
+            let converted = match encoding {
+                SignatureEncoding::Base64 => sig.replace('+', "-").replace('/', "_"),
+                SignatureEncoding::Hex => sig.to_uppercase(),
+            };
+            assert!(verify(
+                &public_key,
+                payload,
+                &converted,
+                encoding,
+                &OutputKind::Text
+            )
+            .is_ok());
+
+            let other = KeyPair::new_account().public_key();
+            let err = verify(&other, payload, &sig, encoding, &OutputKind::Text).unwrap_err();
+            assert!(err.downcast_ref::<CommandFailure>().is_some());
+        }
+
+        // signatures can be written to and read from files
+        let sig_file = dir.path().join("config.json.sig");
+        sign(
+            dir.path(),
+            "acme_account.nk",
+            payload,
+            SignatureEncoding::Hex,
+            Some(sig_file.clone()),
+            &OutputKind::Json,
+        )?;
+        let sig_file = sig_file.to_str().unwrap();
+        assert!(verify(
+            &account.public_key(),
+            payload,
+            sig_file,
+            SignatureEncoding::Hex,
+            &OutputKind::Text
+        )
+        .is_ok());
+
+        // the signature covers the whole payload
+        fs::write(payload, r#"{"replicas": 30}"#)?;
+        assert!(verify(
+            &account.public_key(),
+            payload,
+            sig_file,
+            SignatureEncoding::Hex,
+            &OutputKind::Text
+        )
+        .unwrap_err()
+        .downcast_ref::<CommandFailure>()
+        .is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_signature() {
+        let sig = [7u8; ED25519_SIGNATURE_LEN];
+        assert_eq!(
+            SignatureEncoding::Base64
+                .decode(&data_encoding::BASE64.encode(&sig))
+                .unwrap(),
+            sig
+        );
+        assert_eq!(
+            SignatureEncoding::Hex
+                .decode(&data_encoding::HEXLOWER.encode(&sig))
+                .unwrap(),
+            sig
+        );
+        // malformed signatures are errors rather than invalid signatures
+        assert!(SignatureEncoding::Hex.decode("not hex").is_err());
+        assert!(SignatureEncoding::Base64
+            .decode(&data_encoding::BASE64.encode(&sig[..32]))
+            .is_err());
+        assert!("pem".parse::<SignatureEncoding>().is_err());
+        assert_eq!(
+            "HEX".parse::<SignatureEncoding>(),
+            Ok(SignatureEncoding::Hex)
+        );
+    }
+}
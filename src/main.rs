@@ -15,6 +15,7 @@ mod par;
 use par::ParCli;
 mod reg;
 use reg::RegCli;
+mod signer;
 mod smithy;
 use smithy::{GenerateCli, LintCli, ValidateCli};
 mod call;
@@ -110,7 +111,10 @@ async fn main() {
             0
         }
         Err(e) => {
-            eprintln!("Error: {}", e);
+            match e.downcast_ref::<util::CommandFailure>() {
+                Some(failure) => println!("{}", failure),
+                None => eprintln!("Error: {}", e),
+            }
             1
         }
     })
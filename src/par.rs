@@ -1,6 +1,10 @@
 extern crate provider_archive;
 use crate::keys::extract_keypair;
+use crate::signer::{extract_signer, Signer};
 use crate::util::{convert_error, format_output, Output, OutputKind, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use nkeys::KeyPairType;
 use provider_archive::*;
 use serde_json::json;
@@ -9,8 +13,11 @@ use std::io::prelude::*;
 use std::path::PathBuf;
 use structopt::clap::AppSettings;
 use structopt::StructOpt;
+use wascap::jwt::CapabilityProvider;
 
 const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Name of the file holding the claims in a provider archive
+const CLAIMS_JWT_FILE: &str = "claims.jwt";
 
 #[derive(Debug, StructOpt, Clone)]
 #[structopt(
@@ -109,6 +116,11 @@ pub(crate) struct CreateCommand {
     #[structopt(long = "disable-keygen")]
     disable_keygen: bool,
 
+    /// External signer holding the issuer key, either exec:<command> or agent:<socket path>.
+    /// Used instead of the issuer seed, which then never needs to be on disk
+    #[structopt(long = "signer", env = "WASH_SIGNER", hide_env_values = true)]
+    signer: Option<String>,
+
     #[structopt(flatten)]
     pub(crate) output: Output,
 }
@@ -198,6 +210,11 @@ pub(crate) struct InsertCommand {
     #[structopt(long = "disable-keygen")]
     disable_keygen: bool,
 
+    /// External signer holding the issuer key, either exec:<command> or agent:<socket path>.
+    /// Used instead of the issuer seed, which then never needs to be on disk
+    #[structopt(long = "signer", env = "WASH_SIGNER", hide_env_values = true)]
+    signer: Option<String>,
+
     #[structopt(flatten)]
     pub(crate) output: Output,
 }
@@ -224,7 +241,8 @@ pub(crate) fn handle_create(cmd: CreateCommand) -> Result<String> {
     let mut lib = Vec::new();
     f.read_to_end(&mut lib)?;
 
-    let issuer = extract_keypair(
+    let issuer = extract_signer(
+        cmd.signer,
         cmd.issuer,
         Some(cmd.binary.clone()),
         cmd.directory.clone(),
@@ -258,7 +276,7 @@ pub(crate) fn handle_create(cmd: CreateCommand) -> Result<String> {
 
     Ok(
         if par
-            .write(&outfile, &issuer, &subject, cmd.compress)
+            .write(&outfile, &issuer.keypair()?, &subject, cmd.compress)
             .is_err()
         {
             format!(
@@ -266,6 +284,9 @@ pub(crate) fn handle_create(cmd: CreateCommand) -> Result<String> {
                 PathBuf::from(outfile).parent().unwrap(),
             )
         } else {
+            if issuer.is_external() {
+                resign_archive(&written_path(&outfile, cmd.compress), &issuer)?;
+            }
             format_output(
                 format!("Successfully created archive {}", outfile),
                 json!({"result": "success", "file": outfile}),
@@ -391,7 +412,8 @@ pub(crate) fn handle_insert(cmd: InsertCommand) -> Result<String> {
 
     let mut par = ProviderArchive::try_load(&buf).map_err(convert_error)?;
 
-    let issuer = extract_keypair(
+    let issuer = extract_signer(
+        cmd.signer,
         cmd.issuer,
         Some(cmd.binary.clone()),
         cmd.directory.clone(),
@@ -412,8 +434,12 @@ pub(crate) fn handle_insert(cmd: InsertCommand) -> Result<String> {
 
     par.add_library(&cmd.arch, &lib).map_err(convert_error)?;
 
-    par.write(&cmd.archive, &issuer, &subject, is_compressed(&buf)?)
+    let compressed = is_compressed(&buf)?;
+    par.write(&cmd.archive, &issuer.keypair()?, &subject, compressed)
         .map_err(convert_error)?;
+    if issuer.is_external() {
+        resign_archive(&written_path(&cmd.archive, compressed), &issuer)?;
+    }
 
     Ok(format_output(
         format!(
@@ -433,9 +459,134 @@ fn is_compressed(input: &[u8]) -> Result<bool> {
     Ok(input[0..2] == GZIP_MAGIC)
 }
 
+/// The file written by `ProviderArchive::write`, which adds a .gz extension to compressed archives
+fn written_path(destination: &str, compressed: bool) -> String {
+    if compressed && !destination.ends_with(".gz") {
+        format!("{}.gz", destination)
+    } else {
+        destination.to_string()
+    }
+}
+
+/// Signs the claims of a written archive again with an external signer. Archives can only be
+/// written with a local key, so the claims are first signed with a throwaway key
+fn resign_archive(path: &str, issuer: &Signer) -> Result<()> {
+    let bytes = std::fs::read(path)?;
+    let resigned = replace_archive_claims(&bytes, |jwt| issuer.resign::<CapabilityProvider>(jwt))?;
+    std::fs::write(path, resigned)?;
+    Ok(())
+}
+
+/// Replaces the claims JWT of an archive with the result of `update`, keeping the other
+/// entries and the compression of the archive
+pub(crate) fn replace_archive_claims(
+    bytes: &[u8],
+    update: impl FnOnce(&str) -> Result<String>,
+) -> Result<Vec<u8>> {
+    let compressed = is_compressed(bytes)?;
+    let tarball = if compressed {
+        let mut tarball = Vec::new();
+        GzDecoder::new(bytes).read_to_end(&mut tarball)?;
+        tarball
+    } else {
+        bytes.to_vec()
+    };
+
+    let mut update = Some(update);
+    let mut entries = Vec::new();
+    for entry in tar::Archive::new(&tarball[..]).entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().to_string();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        if name == CLAIMS_JWT_FILE {
+            let update = update
+                .take()
+                .ok_or("Archive contains more than one claims JWT")?;
+            contents = update(&String::from_utf8(contents)?)?.into_bytes();
+        }
+        entries.push((name, contents));
+    }
+    if update.is_some() {
+        return Err("No claims found in archive".into());
+    }
+
+    let mut par = tar::Builder::new(Vec::new());
+    for (name, contents) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_path(&name)?;
+        header.set_size(contents.len() as u64);
+        header.set_cksum();
+        par.append_data(&mut header, &name, &contents[..])?;
+    }
+    let tarball = par.into_inner()?;
+    if compressed {
+        let mut gz = GzEncoder::new(Vec::new(), Compression::best());
+        gz.write_all(&tarball)?;
+        Ok(gz.finish()?)
+    } else {
+        Ok(tarball)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use nkeys::KeyPair;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_par_create_external_signer() -> Result<()> {
+        let account = KeyPair::new_account();
+        let (dir, signer) = crate::signer::test_agent(KeyPair::from_seed(&account.seed()?)?);
+        let binary = dir.path().join("provider.so");
+        std::fs::write(&binary, b"not really a library")?;
+        let destination = dir.path().join("provider.par");
+
+        let cmd = ParCli::from_iter_safe(&[
+            "par",
+            "create",
+            "--arch",
+            "x86_64-linux",
+            "--binary",
+            binary.to_str().unwrap(),
+            "--capid",
+            "wasmcloud:test",
+            "--name",
+            "Test",
+            "--vendor",
+            "acme",
+            "--destination",
+            destination.to_str().unwrap(),
+            "--subject",
+            &KeyPair::new_service().seed()?,
+            "--signer",
+            &signer,
+            "--disable-keygen",
+            "--compress",
+        ])?;
+        match cmd.command {
+            ParCliCommand::Create(cmd) => handle_create(cmd)?,
+            cmd => panic!("par create constructed incorrect command {:?}", cmd),
+        };
+
+        let bytes = std::fs::read(dir.path().join("provider.par.gz"))?;
+        let archive = ProviderArchive::try_load(&bytes).map_err(convert_error)?;
+        assert_eq!(archive.claims().unwrap().issuer, account.public_key());
+        assert_eq!(archive.targets(), vec!["x86_64-linux".to_string()]);
+
+        let mut tarball = Vec::new();
+        GzDecoder::new(&bytes[..]).read_to_end(&mut tarball)?;
+        let mut jwt = String::new();
+        for entry in tar::Archive::new(&tarball[..]).entries()? {
+            let mut entry = entry?;
+            if entry.path()?.to_str() == Some(CLAIMS_JWT_FILE) {
+                entry.read_to_string(&mut jwt)?;
+            }
+        }
+        assert!(wascap::jwt::validate_token::<CapabilityProvider>(&jwt)?.signature_valid);
+        Ok(())
+    }
 
     // Uses all flags and options of the `par create` command
     // to ensure API does not change between versions
@@ -472,6 +623,8 @@ mod test {
             "text",
             "--disable-keygen",
             "--compress",
+            "--signer",
+            "agent:/run/wash-signer.sock",
         ])
         .unwrap();
         match create_long.command {
@@ -489,6 +642,7 @@ mod test {
                 destination,
                 compress,
                 disable_keygen,
+                signer,
                 output,
             }) => {
                 assert_eq!(capid, "wasmcloud:test");
@@ -505,6 +659,7 @@ mod test {
                 assert_eq!(version.unwrap(), "1.11.111");
                 assert!(disable_keygen);
                 assert!(compress);
+                assert_eq!(signer.unwrap(), "agent:/run/wash-signer.sock");
             }
             cmd => panic!("par insert constructed incorrect command {:?}", cmd),
         }
@@ -552,6 +707,7 @@ mod test {
                 destination,
                 compress,
                 disable_keygen,
+                signer,
                 output,
             }) => {
                 assert_eq!(capid, "wasmcloud:test");
@@ -568,6 +724,7 @@ mod test {
                 assert_eq!(version.unwrap(), "1.11.111");
                 assert!(!disable_keygen);
                 assert!(!compress);
+                assert!(signer.is_none());
             }
             cmd => panic!("par insert constructed incorrect command {:?}", cmd),
         }
@@ -596,6 +753,8 @@ mod test {
             "-o",
             "text",
             "--disable-keygen",
+            "--signer",
+            "agent:/run/wash-signer.sock",
         ])
         .unwrap();
         match insert_short.command {
@@ -608,6 +767,7 @@ mod test {
                 subject,
                 output,
                 disable_keygen,
+                signer,
             }) => {
                 assert_eq!(archive, "libtest.par.gz");
                 assert_eq!(arch, "x86_64-testrunner");
@@ -617,6 +777,7 @@ mod test {
                 assert_eq!(subject.unwrap(), SUBJECT);
                 assert_eq!(output.kind, OutputKind::Text);
                 assert!(disable_keygen);
+                assert_eq!(signer.unwrap(), "agent:/run/wash-signer.sock");
             }
             cmd => panic!("par insert constructed incorrect command {:?}", cmd),
         }
@@ -648,6 +809,7 @@ mod test {
                 subject,
                 output,
                 disable_keygen,
+                signer,
             }) => {
                 assert_eq!(archive, "libtest.par.gz");
                 assert_eq!(arch, "x86_64-testrunner");
@@ -657,6 +819,7 @@ mod test {
                 assert_eq!(subject.unwrap(), SUBJECT);
                 assert_eq!(output.kind, OutputKind::Text);
                 assert!(!disable_keygen);
+                assert!(signer.is_none());
             }
             cmd => panic!("par insert constructed incorrect command {:?}", cmd),
         }
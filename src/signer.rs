@@ -0,0 +1,425 @@
+//! Signers for the issuer of claims. Issuer keys are normally read by `extract_keypair`, but
+//! can instead be held by an external signer so that their seeds never touch the disk:
+//!
+//! - `exec:<command>` runs the command as `<command> public-key <key type>` to get the public
+//!   key, and as `<command> sign <key type>` with the bytes to sign on stdin. The command
+//!   prints the public key, or the base64 encoded Ed25519 signature, on stdout.
+//! - `agent:<socket>` connects to a local agent on a Unix socket, sending one JSON request
+//!   per connection and reading one JSON response, e.g.
+//!   `{"op": "public_key", "key_type": "account"}` answered by `{"public_key": "A..."}`, and
+//!   `{"op": "sign", "key_type": "account", "payload": "<base64>"}` answered by
+//!   `{"signature": "<base64>"}`. Failures are answered by `{"error": "<message>"}`.
+//!
+//! Key types are the lowercase names used for key files, e.g. account or operator.
+use crate::keys::{extract_keypair, keypair_type_to_string, public_key_prefix};
+use crate::util::Result;
+use data_encoding::{DecodeError, BASE64URL_NOPAD};
+use nkeys::{KeyPair, KeyPairType};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use wascap::jwt::{Claims, WascapEntity};
+
+/// JWT header used by wascap, encoded as a JWT segment
+const JWT_HEADER: &str = r#"{"typ":"jwt","alg":"Ed25519"}"#;
+pub(crate) const ED25519_SIGNATURE_LEN: usize = 64;
+
+/// The key that signs claims as their issuer
+pub(crate) enum Signer {
+    Local(KeyPair),
+    External(ExternalSigner),
+}
+
+/// Uses the external signer if one was given, otherwise extracts the issuer keypair the same way
+/// as `extract_keypair`
+pub(crate) fn extract_signer(
+    signer: Option<String>,
+    input: Option<String>,
+    module_path: Option<String>,
+    directory: Option<PathBuf>,
+    keygen_type: KeyPairType,
+    disable_keygen: bool,
+) -> Result<Signer> {
+    match signer {
+        Some(spec) => Ok(Signer::External(ExternalSigner::connect(
+            &spec,
+            keygen_type,
+        )?)),
+        None => Ok(Signer::Local(extract_keypair(
+            input,
+            module_path,
+            directory,
+            keygen_type,
+            disable_keygen,
+        )?)),
+    }
+}
+
+impl Signer {
+    pub(crate) fn public_key(&self) -> String {
+        match self {
+            Signer::Local(kp) => kp.public_key(),
+            Signer::External(signer) => signer.public_key.clone(),
+        }
+    }
+
+    pub(crate) fn is_external(&self) -> bool {
+        matches!(self, Signer::External(_))
+    }
+
+    /// A keypair for functions that can only sign with a local key. For external signers
+    /// this is a throwaway key, and anything it signed must be signed again with `resign`
+    pub(crate) fn keypair(&self) -> Result<KeyPair> {
+        match self {
+            Signer::Local(kp) => Ok(KeyPair::from_seed(&kp.seed()?)?),
+            Signer::External(signer) => Ok(KeyPair::new(signer.key_type.clone())),
+        }
+    }
+
+    /// Encodes the claims as a JWT signed by this signer
+    pub(crate) fn encode<T>(&self, claims: &Claims<T>) -> Result<String>
+    where
+        T: Serialize + DeserializeOwned + WascapEntity,
+    {
+        match self {
+            Signer::Local(kp) => Ok(claims.encode(kp)?),
+            Signer::External(signer) => {
+                let head_and_claims = format!(
+                    "{}.{}",
+                    BASE64URL_NOPAD.encode(JWT_HEADER.as_bytes()),
+                    BASE64URL_NOPAD.encode(serde_json::to_string(claims)?.as_bytes())
+                );
+                let sig = signer.sign(head_and_claims.as_bytes())?;
+                Ok(format!(
+                    "{}.{}",
+                    head_and_claims,
+                    BASE64URL_NOPAD.encode(&sig)
+                ))
+            }
+        }
+    }
+
+    /// Replaces the issuer of a JWT with this signer and signs it again
+    pub(crate) fn resign<T>(&self, jwt: &str) -> Result<String>
+    where
+        T: Serialize + DeserializeOwned + WascapEntity,
+    {
+        let mut claims = Claims::<T>::decode(jwt)?;
+        claims.issuer = self.public_key();
+        self.encode(&claims)
+    }
+}
+
+/// A signer that holds the issuer key outside of wash
+pub(crate) struct ExternalSigner {
+    backend: Backend,
+    key_type: KeyPairType,
+    public_key: String,
+}
+
+enum Backend {
+    Exec(Vec<String>),
+    Agent(PathBuf),
+}
+
+impl ExternalSigner {
+    /// Parses a signer specification and asks the signer for the public key of the given type
+    pub(crate) fn connect(spec: &str, key_type: KeyPairType) -> Result<Self> {
+        let backend = if let Some(command) = spec.strip_prefix("exec:") {
+            let args = command
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>();
+            if args.is_empty() {
+                return Err(
+                    "The exec signer requires a command, e.g. exec:/usr/local/bin/signer".into(),
+                );
+            }
+            Backend::Exec(args)
+        } else if let Some(socket) = spec.strip_prefix("agent:") {
+            Backend::Agent(PathBuf::from(socket))
+        } else {
+            return Err(format!(
+                "Invalid signer '{}', expected exec:<command> or agent:<socket path>",
+                spec
+            )
+            .into());
+        };
+
+        let mut signer = ExternalSigner {
+            backend,
+            key_type,
+            public_key: String::new(),
+        };
+        let public_key = signer.request("public_key", None)?;
+        KeyPair::from_public_key(&public_key)
+            .map_err(|e| format!("External signer returned an invalid public key: {}", e))?;
+        if !public_key.starts_with(public_key_prefix(&signer.key_type)) {
+            return Err(format!(
+                "External signer returned {}, expected a key of type {}",
+                public_key,
+                keypair_type_to_string(signer.key_type.clone())
+            )
+            .into());
+        }
+        signer.public_key = public_key;
+        Ok(signer)
+    }
+
+    /// Signs the input, checking the signature against the signer's public key
+    fn sign(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let encoded = self.request("sign", Some(input))?;
+        let sig = decode_base64(&encoded)
+            .map_err(|e| format!("External signer returned an invalid signature: {}", e))?;
+        // nkeys panics when verifying a signature of the wrong length
+        if sig.len() != ED25519_SIGNATURE_LEN {
+            return Err(format!(
+                "External signer returned a {} byte signature, expected an Ed25519 signature",
+                sig.len()
+            )
+            .into());
+        }
+        KeyPair::from_public_key(&self.public_key)?
+            .verify(input, &sig)
+            .map_err(|_| {
+                format!(
+                    "External signer returned a signature that does not match {}",
+                    self.public_key
+                )
+            })?;
+        Ok(sig)
+    }
+
+    fn request(&self, op: &str, payload: Option<&[u8]>) -> Result<String> {
+        let key_type = keypair_type_to_string(self.key_type.clone());
+        let response = match self.backend {
+            Backend::Exec(ref args) => exec_request(args, op, &key_type, payload)?,
+            Backend::Agent(ref socket) => agent_request(socket, op, &key_type, payload)?,
+        };
+        Ok(response.trim().to_string())
+    }
+}
+
+fn exec_request(
+    args: &[String],
+    op: &str,
+    key_type: &str,
+    payload: Option<&[u8]>,
+) -> Result<String> {
+    // the command line uses a dash, e.g. `signer public-key account`
+    let mut child = Command::new(&args[0])
+        .args(&args[1..])
+        .arg(op.replace('_', "-"))
+        .arg(key_type)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Unable to run external signer '{}': {}", args[0], e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(payload.unwrap_or_default())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "External signer '{}' failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[cfg(unix)]
+fn agent_request(
+    socket: &std::path::Path,
+    op: &str,
+    key_type: &str,
+    payload: Option<&[u8]>,
+) -> Result<String> {
+    use std::io::Read;
+    use std::os::unix::net::UnixStream;
+
+    let mut request = serde_json::json!({ "op": op, "key_type": key_type });
+    if let Some(payload) = payload {
+        request["payload"] = BASE64URL_NOPAD.encode(payload).into();
+    }
+    let mut stream = UnixStream::connect(socket).map_err(|e| {
+        format!(
+            "Unable to connect to signing agent at {}: {}",
+            socket.display(),
+            e
+        )
+    })?;
+    writeln!(stream, "{}", request)?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    let response: serde_json::Value = serde_json::from_str(&response)
+        .map_err(|e| format!("Invalid response from signing agent: {}", e))?;
+    if let Some(error) = response["error"].as_str() {
+        return Err(format!("Signing agent failed: {}", error).into());
+    }
+    let field = if op == "sign" { "signature" } else { op };
+    response[field]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| format!("Signing agent response is missing '{}'", field).into())
+}
+
+#[cfg(not(unix))]
+fn agent_request(
+    _socket: &std::path::Path,
+    _op: &str,
+    _key_type: &str,
+    _payload: Option<&[u8]>,
+) -> Result<String> {
+    Err("Signing agents are only supported on Unix platforms".into())
+}
+
+/// Decodes base64 with either alphabet, with or without padding
+pub(crate) fn decode_base64(input: &str) -> ::std::result::Result<Vec<u8>, DecodeError> {
+    let normalized = input
+        .trim_end_matches('=')
+        .replace('+', "-")
+        .replace('/', "_");
+    BASE64URL_NOPAD.decode(normalized.as_bytes())
+}
+
+/// Serves a signing agent for `kp` on a socket in a temporary directory, returning the
+/// directory (which must be kept alive) and the signer specification
+#[cfg(all(test, unix))]
+pub(crate) fn test_agent(kp: KeyPair) -> (tempfile::TempDir, String) {
+    use data_encoding::BASE64;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixListener;
+
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("agent.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+            let response = match request["op"].as_str() {
+                Some("public_key") => serde_json::json!({ "public_key": kp.public_key() }),
+                Some("sign") => {
+                    let payload = decode_base64(request["payload"].as_str().unwrap()).unwrap();
+                    let sig = kp.sign(&payload).unwrap();
+                    serde_json::json!({ "signature": BASE64.encode(&sig) })
+                }
+                _ => serde_json::json!({ "error": "unknown op" }),
+            };
+            writeln!(stream, "{}", response).unwrap();
+        }
+    });
+    (dir, format!("agent:{}", socket.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data_encoding::BASE64;
+    use wascap::jwt::{validate_token, Account, Operator};
+
+    #[cfg(unix)]
+    #[test]
+    fn test_agent_signer() -> Result<()> {
+        let operator = KeyPair::new_operator();
+        let (_dir, spec) = test_agent(KeyPair::from_seed(&operator.seed()?)?);
+
+        let signer = extract_signer(
+            Some(spec.clone()),
+            None,
+            None,
+            None,
+            KeyPairType::Operator,
+            true,
+        )?;
+        assert!(signer.is_external());
+        assert_eq!(signer.public_key(), operator.public_key());
+
+        let claims = Claims::<Operator>::new(
+            "Operator".to_string(),
+            signer.public_key(),
+            signer.public_key(),
+            vec![],
+        );
+        let jwt = signer.encode(&claims)?;
+        // externally signed tokens are identical to locally signed ones
+        assert_eq!(jwt, claims.encode(&operator)?);
+        assert!(validate_token::<Operator>(&jwt)?.signature_valid);
+
+        let account = KeyPair::new_account();
+        let throwaway = Claims::<Account>::new(
+            "Account".to_string(),
+            account.public_key(),
+            account.public_key(),
+            vec![],
+        )
+        .encode(&account)?;
+        let resigned = signer.resign::<Account>(&throwaway)?;
+        assert!(validate_token::<Account>(&resigned)?.signature_valid);
+        assert_eq!(
+            Claims::<Account>::decode(&resigned)?.issuer,
+            operator.public_key()
+        );
+
+        // the agent holds an operator key, not an account key
+        assert!(ExternalSigner::connect(&spec, KeyPairType::Account).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_signer() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let account = KeyPair::new_account();
+        let dir = tempfile::tempdir()?;
+        let script = dir.path().join("signer.sh");
+        // prints the public key, but signs with the wrong key
+        std::fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\ncase \"$1 $2\" in\n  \"public-key account\") echo {} ;;\n  \"sign account\") cat > /dev/null; echo {} ;;\n  *) echo unsupported >&2; exit 1 ;;\nesac\n",
+                account.public_key(),
+                BASE64.encode(&[7u8; ED25519_SIGNATURE_LEN])
+            ),
+        )?;
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+        let spec = format!("exec:{}", script.display());
+
+        let signer = ExternalSigner::connect(&spec, KeyPairType::Account)?;
+        assert_eq!(signer.public_key, account.public_key());
+        let err = signer.sign(b"claims").unwrap_err().to_string();
+        assert!(err.contains("does not match"));
+
+        let err = ExternalSigner::connect(&spec, KeyPairType::Operator)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("unsupported"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_signer() {
+        assert!(ExternalSigner::connect("vault://keys/account", KeyPairType::Account).is_err());
+        assert!(ExternalSigner::connect("exec:", KeyPairType::Account).is_err());
+        assert!(ExternalSigner::connect("exec:/nonexistent/signer", KeyPairType::Account).is_err());
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        let sig = [0xfbu8, 0xff, 0x01];
+        assert_eq!(decode_base64(&BASE64.encode(&sig)).unwrap(), sig);
+        assert_eq!(decode_base64(&BASE64URL_NOPAD.encode(&sig)).unwrap(), sig);
+    }
+}
@@ -174,7 +174,7 @@ pub(crate) async fn handle_validate_command(
 
 /// build model from input files and/or files listed in codegen.toml.
 /// Dependent models may be downloaded by a background thread.
-fn build_model(
+pub(crate) fn build_model(
     input: Vec<String>,
     models: Vec<ModelSource>,
     base_dir: PathBuf,
@@ -209,7 +209,7 @@ fn inputs_to_model(inputs: &[String], verbose: u8) -> Result<Model, anyhow::Erro
 
 /// identify config file from command-line, current-directory, or built-in default
 /// Returns the configuration, and whether default was used.
-fn select_config(opt_config: &Option<PathBuf>) -> Result<CodegenConfig, anyhow::Error> {
+pub(crate) fn select_config(opt_config: &Option<PathBuf>) -> Result<CodegenConfig, anyhow::Error> {
     // if --config is not specified in the command-line, try the current directory.
     // if it's not found use the default
     let (cfile, folder) = if let Some(path) = &opt_config {
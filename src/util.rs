@@ -28,6 +28,27 @@ pub(crate) struct Output {
     pub(crate) kind: OutputKind,
 }
 
+/// Flags for pulling artifacts from an OCI registry. Unlike those of `reg` and `par`, they have
+/// no -u/-p short forms, since `wash call` already uses -p for the RPC port
+#[derive(StructOpt, Debug, Clone, Default)]
+pub(crate) struct RegistryOpts {
+    /// Allow latest artifact tags (if an OCI reference is provided)
+    #[structopt(long = "allow-latest")]
+    pub(crate) allow_latest: bool,
+
+    /// OCI username, if omitted anonymous authentication will be used
+    #[structopt(long = "user", env = "WASH_REG_USER", hide_env_values = true)]
+    pub(crate) user: Option<String>,
+
+    /// OCI password, if omitted anonymous authentication will be used
+    #[structopt(long = "password", env = "WASH_REG_PASSWORD", hide_env_values = true)]
+    pub(crate) password: Option<String>,
+
+    /// Allow insecure (HTTP) registry connections
+    #[structopt(long = "insecure")]
+    pub(crate) insecure: bool,
+}
+
 /// Used for displaying human-readable output vs JSON format
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
 pub(crate) enum OutputKind {
@@ -70,6 +91,19 @@ impl fmt::Display for OutputParseErr {
     }
 }
 
+/// Output of a command that completed but should exit with a failure status,
+/// such as a report containing failed checks. The output is printed to stdout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CommandFailure(pub(crate) String);
+
+impl Error for CommandFailure {}
+
+impl fmt::Display for CommandFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 /// Returns string output for provided output kind
 pub(crate) fn format_output(
     text: String,
@@ -133,63 +167,6 @@ pub(crate) fn json_str_to_msgpack_bytes(payload: &str) -> Result<Vec<u8>> {
     Ok(payload)
 }
 
-use once_cell::sync::OnceCell;
-static BIN_STR: OnceCell<char> = OnceCell::new();
-
-fn msgpack_to_json(mval: rmpv::Value) -> serde_json::Value {
-    use rmpv::Value as RV;
-    use serde_json::Value as JV;
-    match mval {
-        RV::String(s) => JV::String(s.to_string()),
-        RV::Boolean(b) => JV::Bool(b),
-        RV::Array(v) => JV::Array(v.into_iter().map(msgpack_to_json).collect::<Vec<_>>()),
-        RV::F64(f) => JV::from(f),
-        RV::F32(f) => JV::from(f),
-        RV::Integer(i) => match (i.is_u64(), i.is_i64()) {
-            (true, _) => JV::from(i.as_u64().unwrap()),
-            (_, true) => JV::from(i.as_i64().unwrap()),
-            _ => JV::from(0u64),
-        },
-        RV::Map(vkv) => JV::Object(
-            vkv.into_iter()
-                .map(|(k, v)| {
-                    (
-                        k.as_str().unwrap_or_default().to_string(),
-                        msgpack_to_json(v),
-                    )
-                })
-                .collect::<serde_json::Map<_, _>>(),
-        ),
-        RV::Binary(v) => match BIN_STR.get().unwrap() {
-            's' => JV::String(String::from_utf8_lossy(&v).into_owned()),
-            '2' => serde_json::json!({
-                "str": String::from_utf8_lossy(&v),
-                "bin": v,
-            }),
-            /*'b'|*/ _ => JV::Array(v.into_iter().map(JV::from).collect::<Vec<_>>()),
-        },
-        RV::Ext(i, v) => serde_json::json!({
-            "type": i,
-            "data": v
-        }),
-        RV::Nil => JV::Bool(false),
-    }
-}
-
-/// transform msgpack bytes into json
-pub(crate) fn msgpack_to_json_val(msg: Vec<u8>, bin_str: char) -> serde_json::Value {
-    use bytes::Buf;
-
-    BIN_STR.set(bin_str).unwrap();
-
-    let bytes = bytes::Bytes::from(msg);
-    if let Ok(v) = rmpv::decode::value::read_value(&mut bytes.reader()) {
-        msgpack_to_json(v)
-    } else {
-        serde_json::json!({ "error": "Could not decode data" })
-    }
-}
-
 pub(crate) fn configure_table_style(table: &mut Table<'_>) {
     table.style = empty_table_style();
     table.separate_rows = false;
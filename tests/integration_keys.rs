@@ -6,7 +6,7 @@ use std::io::prelude::*;
 #[test]
 fn integration_keys_gen_basic() {
     let keys_gen_account = wash()
-        .args(&["keys", "gen", "account"])
+        .args(["keys", "gen", "account"])
         .output()
         .expect("failed to generate account key");
 
@@ -21,7 +21,7 @@ fn integration_keys_gen_comprehensive() {
 
     key_gen_types.iter().for_each(|cmd| {
         let key_gen_command = wash()
-            .args(&["keys", "gen", cmd])
+            .args(["keys", "gen", cmd])
             .output()
             .unwrap_or_else(|_| panic!("failed to generate key type {} with text output", cmd));
         assert!(key_gen_command.status.success());
@@ -33,7 +33,7 @@ fn integration_keys_gen_comprehensive() {
 
     key_gen_types.iter().for_each(|cmd| {
         let key_gen_command = wash()
-            .args(&["keys", "gen", cmd, "-o", "json"])
+            .args(["keys", "gen", cmd, "-o", "json"])
             .output()
             .unwrap_or_else(|_| panic!("failed to generate key type {} with json output", cmd));
         assert!(key_gen_command.status.success());
@@ -55,7 +55,7 @@ fn integration_keys_get_basic() {
     file.write_all(KEYCONTENTS).unwrap();
 
     let key_output = wash()
-        .args(&[
+        .args([
             "keys",
             "get",
             KEYNAME,
@@ -65,10 +65,28 @@ fn integration_keys_get_basic() {
         .output()
         .expect("failed to read key with keys get");
     assert!(key_output.status.success());
-    assert_eq!(
-        output_to_string(key_output).trim(),
-        String::from_utf8(KEYCONTENTS.to_vec()).unwrap()
-    );
+    let output = output_to_string(key_output);
+    assert!(output.contains("Public Key: M"));
+    assert!(output.contains("Key Type: module"));
+    // the seed is only output with --reveal
+    assert!(!output.contains(std::str::from_utf8(KEYCONTENTS).unwrap()));
+
+    let key_output = wash()
+        .args([
+            "keys",
+            "get",
+            KEYNAME,
+            "-d",
+            get_basic_dir.to_str().unwrap(),
+            "--reveal",
+        ])
+        .output()
+        .expect("failed to read key with keys get");
+    assert!(key_output.status.success());
+    assert!(output_to_string(key_output).contains(&format!(
+        "Seed: {}",
+        std::str::from_utf8(KEYCONTENTS).unwrap()
+    )));
 
     remove_dir_all(get_basic_dir).unwrap();
 }
@@ -85,7 +103,7 @@ fn integration_keys_get_comprehensive() {
     file.write_all(KEYCONTENTS).unwrap();
 
     let key_output = wash()
-        .args(&[
+        .args([
             "keys",
             "get",
             KEYNAME,
@@ -93,17 +111,15 @@ fn integration_keys_get_comprehensive() {
             get_comprehensive_dir.to_str().unwrap(),
             "-o",
             "json",
+            "--reveal",
         ])
         .output()
         .expect("failed to read key with keys get");
     assert!(key_output.status.success());
-    assert_eq!(
-        output_to_string(key_output).trim(),
-        format!(
-            "{{\"seed\":\"{}\"}}",
-            String::from_utf8(KEYCONTENTS.to_vec()).unwrap()
-        )
-    );
+    let output: serde_json::Value = serde_json::from_str(&output_to_string(key_output)).unwrap();
+    assert_eq!(output["seed"], std::str::from_utf8(KEYCONTENTS).unwrap());
+    assert_eq!(output["key_type"], "module");
+    assert!(output["public_key"].as_str().unwrap().starts_with('M'));
 
     remove_dir_all(get_comprehensive_dir).unwrap();
 }
@@ -131,7 +147,7 @@ fn integration_list_comprehensive() {
     file.write_all(KEYTHREECONTENTS).unwrap();
 
     let list_output = wash()
-        .args(&[
+        .args([
             "keys",
             "list",
             "-d",
@@ -150,7 +166,7 @@ fn integration_list_comprehensive() {
     assert!(output.contains(KEYTHREE));
 
     let list_output_json = wash()
-        .args(&[
+        .args([
             "keys",
             "list",
             "-d",
@@ -163,9 +179,311 @@ fn integration_list_comprehensive() {
 
     assert!(list_output_json.status.success());
     let output_json = output_to_string(list_output_json);
+    assert!(output_json.contains("\"key_type\":\"module\""));
     assert!(output_json.contains(KEYONE));
     assert!(output_json.contains(KEYTWO));
     assert!(output_json.contains(KEYTHREE));
 
     remove_dir_all(list_comprehensive_dir).unwrap();
 }
+
+#[test]
+fn integration_keys_encrypt_decrypt() {
+    const KEYNAME: &str = "encrypt_test_operator.nk";
+    const SEED: &str = "SOANB5TLF6EB2KQREP3ZPF3BQZ6U6SAR7GSQLXHD423YJNXKUW3FGMWMD4";
+    const PASSPHRASE: &str = "correct horse battery staple";
+    const TESTDIR: &str = "integration_keys_encrypt_decrypt";
+
+    let encrypt_dir = test_dir_with_subfolder(TESTDIR);
+    let keyfile = test_dir_file(TESTDIR, KEYNAME);
+    let mut file = File::create(&keyfile).unwrap();
+    file.write_all(SEED.as_bytes()).unwrap();
+
+    let encrypt_output = wash()
+        .args(["keys", "encrypt", "-d", encrypt_dir.to_str().unwrap()])
+        .env("WASH_KEYS_PASSPHRASE", PASSPHRASE)
+        .output()
+        .expect("failed to encrypt keys with keys encrypt");
+    assert!(encrypt_output.status.success());
+    assert!(output_to_string(encrypt_output).contains(KEYNAME));
+    let contents = std::fs::read_to_string(&keyfile).unwrap();
+    assert!(contents.starts_with("wash-encrypted-seed:v1:"));
+    assert!(!contents.contains(SEED));
+
+    // the public key of an encrypted key is shown without the passphrase
+    let get_output = wash()
+        .args(["keys", "get", KEYNAME, "-d", encrypt_dir.to_str().unwrap()])
+        .output()
+        .expect("failed to read key with keys get");
+    assert!(get_output.status.success());
+    assert!(output_to_string(get_output).contains("Key Type: operator"));
+
+    // encrypted keys are decrypted wherever keys are read
+    let get_output = wash()
+        .args([
+            "keys",
+            "get",
+            KEYNAME,
+            "-d",
+            encrypt_dir.to_str().unwrap(),
+            "--reveal",
+        ])
+        .env("WASH_KEYS_PASSPHRASE", PASSPHRASE)
+        .output()
+        .expect("failed to read key with keys get");
+    assert!(get_output.status.success());
+    assert!(output_to_string(get_output).contains(&format!("Seed: {}", SEED)));
+
+    let token_output = wash()
+        .args([
+            "claims",
+            "token",
+            "operator",
+            "--name",
+            "Encrypted",
+            "--issuer",
+            keyfile.to_str().unwrap(),
+            "--disable-keygen",
+        ])
+        .env("WASH_KEYS_PASSPHRASE", PASSPHRASE)
+        .output()
+        .expect("failed to generate token with an encrypted key");
+    assert!(token_output.status.success());
+
+    let wrong_passphrase = wash()
+        .args([
+            "keys",
+            "get",
+            KEYNAME,
+            "-d",
+            encrypt_dir.to_str().unwrap(),
+            "--reveal",
+        ])
+        .env("WASH_KEYS_PASSPHRASE", "wrong")
+        .output()
+        .expect("failed to run keys get");
+    assert!(!wrong_passphrase.status.success());
+
+    let decrypt_output = wash()
+        .args(["keys", "decrypt", "-d", encrypt_dir.to_str().unwrap()])
+        .env("WASH_KEYS_PASSPHRASE", PASSPHRASE)
+        .output()
+        .expect("failed to decrypt keys with keys decrypt");
+    assert!(decrypt_output.status.success());
+    assert_eq!(std::fs::read_to_string(&keyfile).unwrap(), SEED);
+
+    remove_dir_all(encrypt_dir).unwrap();
+}
+
+#[test]
+fn integration_keys_import_export_rename_delete() {
+    const SEED: &str = "SMAH45IUULL57OSX23NOOOTLSVNQOORMDLE3Y3PQLJ4J5MY7MN2K7BIFI4";
+    const PUBLIC_KEY: &str = "MCAYXRPJ3Z6NWUWFOIAB3CD26VDWONPKU374RJ2PJIVZNQXAIGWKWWNO";
+    const TESTDIR: &str = "integration_keys_import_export";
+
+    let keys_dir = test_dir_with_subfolder(TESTDIR);
+    let dir = keys_dir.to_str().unwrap();
+
+    let import_output = wash()
+        .args([
+            "keys", "import", "echo", SEED, "--type", "module", "-d", dir,
+        ])
+        .output()
+        .expect("failed to import key with keys import");
+    assert!(import_output.status.success());
+    assert!(test_dir_file(TESTDIR, "echo_module.nk").is_file());
+
+    let wrong_type = wash()
+        .args([
+            "keys", "import", "other", SEED, "--type", "account", "-d", dir,
+        ])
+        .output()
+        .expect("failed to run keys import");
+    assert!(!wrong_type.status.success());
+
+    let export_output = wash()
+        .args(["keys", "export", "echo_module", "-d", dir])
+        .output()
+        .expect("failed to export key with keys export");
+    assert!(export_output.status.success());
+    let exported: serde_json::Value =
+        serde_json::from_str(&output_to_string(export_output)).unwrap();
+    assert_eq!(exported["public_key"], PUBLIC_KEY);
+    assert_eq!(exported["seed"], SEED);
+    assert_eq!(exported["key_type"], "module");
+
+    let rename_output = wash()
+        .args(["keys", "rename", "echo_module", "hello_module", "-d", dir])
+        .output()
+        .expect("failed to rename key with keys rename");
+    assert!(rename_output.status.success());
+    assert!(test_dir_file(TESTDIR, "hello_module.nk").is_file());
+    assert!(!test_dir_file(TESTDIR, "echo_module.nk").exists());
+
+    // without a terminal, deleting requires --yes
+    let unconfirmed = wash()
+        .args(["keys", "delete", "hello_module", "-d", dir])
+        .output()
+        .expect("failed to run keys delete");
+    assert!(!unconfirmed.status.success());
+    assert!(test_dir_file(TESTDIR, "hello_module.nk").is_file());
+
+    let delete_output = wash()
+        .args(["keys", "delete", "hello_module", "--yes", "-d", dir])
+        .output()
+        .expect("failed to delete key with keys delete");
+    assert!(delete_output.status.success());
+    assert!(!test_dir_file(TESTDIR, "hello_module.nk").exists());
+
+    remove_dir_all(keys_dir).unwrap();
+}
+
+#[test]
+fn integration_keys_rotate() {
+    const SEED: &str = "SMAH45IUULL57OSX23NOOOTLSVNQOORMDLE3Y3PQLJ4J5MY7MN2K7BIFI4";
+    const PUBLIC_KEY: &str = "MCAYXRPJ3Z6NWUWFOIAB3CD26VDWONPKU374RJ2PJIVZNQXAIGWKWWNO";
+    const TESTDIR: &str = "integration_keys_rotate";
+
+    let keys_dir = test_dir_with_subfolder(TESTDIR);
+    let dir = keys_dir.to_str().unwrap();
+    let mut file = File::create(test_dir_file(TESTDIR, "echo_module.nk")).unwrap();
+    file.write_all(SEED.as_bytes()).unwrap();
+
+    let rotate_output = wash()
+        .args(["keys", "rotate", "echo_module", "-d", dir, "-o", "json"])
+        .output()
+        .expect("failed to rotate key with keys rotate");
+    assert!(rotate_output.status.success());
+    let report: serde_json::Value = serde_json::from_str(&output_to_string(rotate_output)).unwrap();
+    assert_eq!(report["key_type"], "module");
+    assert_eq!(report["old_public_key"], PUBLIC_KEY);
+    let new_key = report["new_public_key"].as_str().unwrap();
+    assert_ne!(new_key, PUBLIC_KEY);
+    assert!(new_key.starts_with('M'));
+    let archived = report["archived"].as_str().unwrap();
+    assert_eq!(std::fs::read_to_string(archived).unwrap(), SEED);
+
+    let get_output = wash()
+        .args(["keys", "get", "echo_module.nk", "-d", dir])
+        .output()
+        .expect("failed to read key with keys get");
+    assert!(output_to_string(get_output).contains(new_key));
+
+    // modules that weren't signed by or for the key can't be re-signed
+    let missing = wash()
+        .args([
+            "keys",
+            "rotate",
+            "echo_module",
+            "--resign",
+            test_dir_file(TESTDIR, "missing.wasm").to_str().unwrap(),
+            "-d",
+            dir,
+        ])
+        .output()
+        .expect("failed to run keys rotate");
+    assert!(!missing.status.success());
+
+    remove_dir_all(keys_dir).unwrap();
+}
+
+#[test]
+fn integration_keys_sign_verify() {
+    const SEED: &str = "SMAH45IUULL57OSX23NOOOTLSVNQOORMDLE3Y3PQLJ4J5MY7MN2K7BIFI4";
+    const PUBLIC_KEY: &str = "MCAYXRPJ3Z6NWUWFOIAB3CD26VDWONPKU374RJ2PJIVZNQXAIGWKWWNO";
+    const TESTDIR: &str = "integration_keys_sign_verify";
+
+    let keys_dir = test_dir_with_subfolder(TESTDIR);
+    let dir = keys_dir.to_str().unwrap();
+    let mut file = File::create(test_dir_file(TESTDIR, "echo_module.nk")).unwrap();
+    file.write_all(SEED.as_bytes()).unwrap();
+    let payload = test_dir_file(TESTDIR, "config.json");
+    let mut file = File::create(&payload).unwrap();
+    file.write_all(br#"{"replicas": 3}"#).unwrap();
+    let payload = payload.to_str().unwrap();
+
+    let sign_output = wash()
+        .args([
+            "keys",
+            "sign",
+            "echo_module",
+            payload,
+            "-e",
+            "hex",
+            "-d",
+            dir,
+        ])
+        .output()
+        .expect("failed to sign file with keys sign");
+    assert!(sign_output.status.success());
+    let signature = output_to_string(sign_output).trim().to_string();
+    assert_eq!(signature.len(), 128);
+
+    let verify_output = wash()
+        .args([
+            "keys", "verify", PUBLIC_KEY, payload, &signature, "-e", "hex",
+        ])
+        .output()
+        .expect("failed to verify signature with keys verify");
+    assert!(verify_output.status.success());
+    assert!(output_to_string(verify_output).contains("is valid"));
+
+    // payloads can be read from stdin, and invalid signatures fail the command
+    let mut verify_stdin = wash()
+        .args(["keys", "verify", PUBLIC_KEY, "-", &signature, "-e", "hex"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to run keys verify");
+    verify_stdin
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(br#"{"replicas": 30}"#)
+        .unwrap();
+    let verify_output = verify_stdin.wait_with_output().unwrap();
+    assert!(!verify_output.status.success());
+    assert!(output_to_string(verify_output).contains("is not valid"));
+
+    remove_dir_all(keys_dir).unwrap();
+}
+
+#[test]
+fn integration_keys_gen_prefix_and_entropy() {
+    const ENTROPY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    let generate = || {
+        let output = wash()
+            .args([
+                "keys",
+                "gen",
+                "module",
+                "--from-entropy",
+                ENTROPY,
+                "-o",
+                "json",
+            ])
+            .output()
+            .expect("failed to generate key from entropy");
+        assert!(output.status.success());
+        serde_json::from_str::<serde_json::Value>(&output_to_string(output)).unwrap()
+    };
+    // the same entropy always gives the same keypair
+    let first = generate();
+    assert_eq!(first, generate());
+    assert!(first["seed"].as_str().unwrap().starts_with("SM"));
+
+    let vanity = wash()
+        .args(["keys", "gen", "account", "--prefix", "AB", "-o", "json"])
+        .output()
+        .expect("failed to generate key with a prefix");
+    assert!(vanity.status.success());
+    let vanity: serde_json::Value = serde_json::from_str(&output_to_string(vanity)).unwrap();
+    assert!(vanity["public_key"].as_str().unwrap().starts_with("AAB"));
+
+    let impossible = wash()
+        .args(["keys", "gen", "account", "--prefix", "XYZ"])
+        .output()
+        .expect("failed to run keys gen");
+    assert!(!impossible.status.success());
+}